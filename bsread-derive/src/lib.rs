@@ -0,0 +1,153 @@
+//! `#[derive(BsreadRecord)]`: maps the named fields of a struct to bsread channels.
+//!
+//! Struct attribute:
+//! - `#[bsread(prefix = "SARFE10-PBPS053:")]`: prepended to all channel names.
+//!
+//! Field attributes:
+//! - `#[bsread(name = "X1")]`: channel name, defaults to the field name.
+//! - `#[bsread(compression = "bitshuffle_lz4")]`: "none" (default), "lz4" or "bitshuffle_lz4".
+//! - `#[bsread(endianness = "big")]`: "little" (default) or "big".
+//! - `#[bsread(shape = [20, 10])]`: array shape, defaults to the length of the field value.
+//!
+//! Field types must convert into `bsread::Value` and from `&bsread::Value`
+//! (primitives, `String` and `Vec` of them).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{bracketed, parse_macro_input, Data, DeriveInput, Fields, LitInt, LitStr, Token};
+
+struct FieldConfig {
+    ident: syn::Ident,
+    name: String,
+    compression: TokenStream2,
+    little_endian: bool,
+    shape: Option<Vec<u32>>,
+}
+
+//Parses `[d1, d2, ...]`
+fn parse_shape(input: syn::parse::ParseStream) -> syn::Result<Vec<u32>> {
+    let content;
+    let brackets = bracketed!(content in input);
+    let dims = Punctuated::<LitInt, Token![,]>::parse_terminated(&content)?;
+    let shape = dims.iter().map(LitInt::base10_parse::<u32>).collect::<syn::Result<Vec<u32>>>()?;
+    if shape.is_empty() {
+        return Err(syn::Error::new(brackets.span.join(), "shape must not be empty"));
+    }
+    Ok(shape)
+}
+
+fn parse_prefix(input: &DeriveInput) -> syn::Result<String> {
+    let mut prefix = String::new();
+    for attr in input.attrs.iter().filter(|attr| attr.path().is_ident("bsread")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("prefix") {
+                prefix = meta.value()?.parse::<LitStr>()?.value();
+                Ok(())
+            } else {
+                Err(meta.error("unsupported bsread struct attribute, expected `prefix`"))
+            }
+        })?;
+    }
+    Ok(prefix)
+}
+
+fn parse_field(field: &syn::Field, prefix: &str) -> syn::Result<FieldConfig> {
+    let ident = field.ident.clone().ok_or_else(|| syn::Error::new_spanned(field, "expected a named field"))?;
+    let mut config = FieldConfig {
+        name: ident.to_string(),
+        ident,
+        compression: quote!(::bsread::Compression::None),
+        little_endian: true,
+        shape: None,
+    };
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("bsread")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                config.name = meta.value()?.parse::<LitStr>()?.value();
+            } else if meta.path.is_ident("compression") {
+                let compression = meta.value()?.parse::<LitStr>()?;
+                config.compression = match compression.value().as_str() {
+                    "none" => quote!(::bsread::Compression::None),
+                    "lz4" => quote!(::bsread::Compression::Lz4),
+                    "bitshuffle_lz4" => quote!(::bsread::Compression::BitshuffleLz4),
+                    _ => return Err(syn::Error::new_spanned(compression, "expected \"none\", \"lz4\" or \"bitshuffle_lz4\"")),
+                };
+            } else if meta.path.is_ident("endianness") {
+                let endianness = meta.value()?.parse::<LitStr>()?;
+                config.little_endian = match endianness.value().as_str() {
+                    "little" => true,
+                    "big" => false,
+                    _ => return Err(syn::Error::new_spanned(endianness, "expected \"little\" or \"big\"")),
+                };
+            } else if meta.path.is_ident("shape") {
+                config.shape = Some(parse_shape(meta.value()?)?);
+            } else {
+                return Err(meta.error("unsupported bsread field attribute, expected `name`, `compression`, `endianness` or `shape`"));
+            }
+            Ok(())
+        })?;
+    }
+    config.name = format!("{}{}", prefix, config.name);
+    Ok(config)
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => return Err(syn::Error::new_spanned(&input.ident, "BsreadRecord requires a struct with named fields")),
+        },
+        _ => return Err(syn::Error::new_spanned(&input.ident, "BsreadRecord can only be derived for structs")),
+    };
+    let prefix = parse_prefix(&input)?;
+    let fields = fields.iter().map(|field| parse_field(field, &prefix)).collect::<syn::Result<Vec<_>>>()?;
+
+    let channels = fields.iter().map(|field| {
+        let FieldConfig { ident, name, compression, little_endian, shape } = field;
+        let shape = match shape {
+            Some(shape) => {
+                let dims = shape.iter().map(|dim| LitInt::new(&dim.to_string(), proc_macro2::Span::call_site()));
+                quote!(Some(vec![#(#dims),*]))
+            }
+            None => quote!(if value.is_array() { Some(vec![value.size() as u32]) } else { None }),
+        };
+        quote! {
+            let value = ::bsread::Value::from(::std::clone::Clone::clone(&self.#ident));
+            let shape: Option<Vec<u32>> = #shape;
+            channels.push(::bsread::channel::new(#name.to_string(), value.kind().to_string(), shape, #little_endian, #compression, false)?);
+        }
+    });
+    let data = fields.iter().map(|FieldConfig { ident, .. }| {
+        quote!(Some(::bsread::ChannelData::new(::bsread::Value::from(::std::clone::Clone::clone(&self.#ident)), timestamp)))
+    });
+    let reads = fields.iter().map(|FieldConfig { ident, name, .. }| quote!(#ident: message.get(#name)?));
+    let count = fields.len();
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::bsread::BsreadRecord for #ident #ty_generics #where_clause {
+            fn channels(&self) -> ::bsread::BsreadResult<Vec<Box<dyn ::bsread::ChannelTrait>>> {
+                let mut channels: Vec<Box<dyn ::bsread::ChannelTrait>> = Vec::with_capacity(#count);
+                #({ #channels })*
+                Ok(channels)
+            }
+
+            fn channel_data(&self, timestamp: (u64, u64)) -> Vec<Option<::bsread::ChannelData>> {
+                vec![#(#data),*]
+            }
+
+            fn from_message(message: &::bsread::Message) -> ::bsread::BsreadResult<Self> {
+                Ok(Self { #(#reads),* })
+            }
+        }
+    })
+}
+
+#[proc_macro_derive(BsreadRecord, attributes(bsread))]
+pub fn derive_bsread_record(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
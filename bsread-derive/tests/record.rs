@@ -0,0 +1,84 @@
+use ::bsread::*;
+
+#[derive(BsreadRecord, Debug, Clone, PartialEq)]
+#[bsread(prefix = "SARFE10-PBPS053:")]
+struct BpmReading {
+    #[bsread(name = "X1")]
+    x: f64,
+    #[bsread(name = "Y1")]
+    y: f64,
+    charge: f32,
+    valid: bool,
+    state: String,
+    #[bsread(compression = "bitshuffle_lz4", endianness = "big")]
+    waveform: Vec<u16>,
+    #[bsread(shape = [4, 2], compression = "lz4")]
+    image: Vec<i32>,
+}
+
+fn reading() -> BpmReading {
+    BpmReading {
+        x: 1.5,
+        y: -0.25,
+        charge: 200.0,
+        valid: true,
+        state: "OK".to_string(),
+        waveform: (0..100).collect(),
+        image: (0..8).collect(),
+    }
+}
+
+#[test]
+fn channel_definitions() -> BsreadResult<()> {
+    let channels = reading().channels()?;
+    let configs: Vec<(String, String, Option<Vec<u32>>)> = channels.iter()
+        .map(|channel| (channel.config().name(), channel.config().kind(), channel.config().shape()))
+        .collect();
+    assert_eq!(configs, vec![
+        ("SARFE10-PBPS053:X1".to_string(), "float64".to_string(), None),
+        ("SARFE10-PBPS053:Y1".to_string(), "float64".to_string(), None),
+        ("SARFE10-PBPS053:charge".to_string(), "float32".to_string(), None),
+        ("SARFE10-PBPS053:valid".to_string(), "bool".to_string(), None),
+        ("SARFE10-PBPS053:state".to_string(), "string".to_string(), None),
+        ("SARFE10-PBPS053:waveform".to_string(), "uint16".to_string(), Some(vec![100])),
+        ("SARFE10-PBPS053:image".to_string(), "int32".to_string(), Some(vec![4, 2])),
+    ]);
+    assert_eq!(channels[5].config().compression(), Compression::BitshuffleLz4);
+    assert!(!channels[5].config().is_little_endian());
+    assert_eq!(channels[6].config().compression(), Compression::Lz4);
+    assert!(channels[0].config().is_little_endian());
+    Ok(())
+}
+
+#[test]
+fn message_round_trip() -> BsreadResult<()> {
+    let record = reading();
+    let data = record.channel_data((1, 2));
+    assert_eq!(data.len(), 7);
+    assert_eq!(data[0].as_ref().unwrap().value(), &Value::F64(1.5));
+    assert_eq!(data[0].as_ref().unwrap().timestamp(), &(1, 2));
+
+    let message = record.to_message(1000, (1, 2))?;
+    assert_eq!(message.id(), 1000);
+    assert_eq!(message.get::<f32>("SARFE10-PBPS053:charge")?, 200.0);
+    assert_eq!(BpmReading::from_message(&message)?, record);
+
+    //Values are converted with Message::get
+    let message = MessageBuilder::new()
+        .channel("SARFE10-PBPS053:X1", 1)
+        .channel("SARFE10-PBPS053:Y1", 2u8)
+        .channel("SARFE10-PBPS053:charge", 3.0)
+        .channel("SARFE10-PBPS053:valid", 0)
+        .channel("SARFE10-PBPS053:state", "OK")
+        .channel("SARFE10-PBPS053:waveform", vec![1i64, 2])
+        .channel("SARFE10-PBPS053:image", vec![1.0, 2.0])
+        .build()?;
+    let converted = BpmReading::from_message(&message)?;
+    assert_eq!((converted.x, converted.y, converted.charge, converted.valid), (1.0, 2.0, 3.0, false));
+    assert_eq!(converted.waveform, vec![1, 2]);
+
+    let message = MessageBuilder::new().channel("SARFE10-PBPS053:X1", 1.0).build()?;
+    let error = BpmReading::from_message(&message).err().unwrap();
+    assert_eq!(error.context().unwrap().channel.as_deref(), Some("SARFE10-PBPS053:Y1"));
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use crate::*;
+use ndarray::{Array2, ArrayBase, ArrayViewD, Data, Dimension, Ix2, IxDyn};
+
+// The data header lists the dimensions from the fastest varying one (e.g. [width, height] for images),
+// while ndarray uses row-major (C) order: the shapes are reversed.
+
+/// ndarray shape of a bsread channel shape.
+pub fn to_ndarray_shape(shape: &[u32]) -> Vec<usize> {
+    shape.iter().rev().map(|&dim| dim as usize).collect()
+}
+
+/// bsread channel shape of an ndarray shape.
+pub fn from_ndarray_shape(shape: &[usize]) -> Vec<u32> {
+    shape.iter().rev().map(|&dim| dim as u32).collect()
+}
+
+impl Message {
+    /// N-dimensional view of an array channel. It does not copy the data if the channel was received in zero-copy mode.
+    pub fn array_view<T: Element>(&self, channel_name: &str) -> BsreadResult<ArrayViewD<'_, T>> {
+        let missing = || BsreadError::validation(ValidationError::MissingChannel).with_channel(channel_name).with_pulse_id(self.id());
+        let channel = self.channels().iter().find(|channel| channel.config().name_str() == channel_name).ok_or_else(missing)?;
+        let data = self.try_channel_data(channel_name)?.ok_or_else(missing)?;
+        let context = |e: BsreadError| e.with_channel(channel_name).with_pulse_id(self.id());
+        let slice = data.as_slice::<T>()
+            .ok_or_else(|| context(BsreadError::conversion(std::any::type_name::<T>(), data.value().name())))?;
+        let shape = channel.config().shape().map(|shape| to_ndarray_shape(&shape)).unwrap_or(vec![slice.len()]);
+        ArrayViewD::from_shape(IxDyn(&shape), slice)
+            .map_err(|e| context(BsreadError::parsing(ParsingError::InvalidData(e.to_string()))))
+    }
+
+    /// Copy of a 2-dimensional array channel (image or matrix), indexed as [row, column].
+    pub fn array2<T: Element>(&self, channel_name: &str) -> BsreadResult<Array2<T>> {
+        let view = self.array_view::<T>(channel_name)?;
+        let dims = format!("{}-dimensional array", view.ndim());
+        view.into_dimensionality::<Ix2>()
+            .map(|view| view.to_owned())
+            .map_err(|_| BsreadError::conversion("Array2", dims).with_channel(channel_name).with_pulse_id(self.id()))
+    }
+}
+
+impl MessageBuilder {
+    pub fn array<S, D>(self, name: &str, array: &ArrayBase<S, D>) -> Self
+    where
+        S: Data,
+        S::Elem: Clone,
+        D: Dimension,
+        Vec<S::Elem>: Into<Value>,
+    {
+        self.array_with(name, array, Compression::None)
+    }
+
+    /// Adds an n-dimensional array channel, with the shape in the bsread convention.
+    pub fn array_with<S, D>(self, name: &str, array: &ArrayBase<S, D>, compression: Compression) -> Self
+    where
+        S: Data,
+        S::Elem: Clone,
+        D: Dimension,
+        Vec<S::Elem>: Into<Value>,
+    {
+        let shape = from_ndarray_shape(array.shape());
+        //Iterates in logical (row-major) order, whatever the memory layout
+        let data: Vec<S::Elem> = array.iter().cloned().collect();
+        self.channel_with_shape(name, data, shape, compression)
+    }
+}
@@ -0,0 +1,182 @@
+use crate::*;
+use arrow_array::{ArrayRef, BinaryArray, BooleanArray, FixedSizeListArray, Float32Array, Float64Array,
+                  Int16Array, Int32Array, Int64Array, Int8Array, RecordBatch, StringArray, TimestampNanosecondArray,
+                  UInt16Array, UInt32Array, UInt64Array, UInt8Array};
+use arrow_buffer::NullBuffer;
+use arrow_schema::{ArrowError, DataType, Field, Schema, TimeUnit};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+// Columns of a batch: "pulse_id", "global_timestamp", then for each channel "<name>" and "<name>.timestamp".
+// Array channels are FixedSizeList columns with the number of elements of the shape, which is kept in the
+// "shape" metadata of the field. Raw and opaque channels are Binary columns.
+
+const TIMESTAMP_SUFFIX: &str = ".timestamp";
+
+fn encoding_error(e: ArrowError) -> BsreadError {
+    BsreadError::validation(ValidationError::Encoding(e.to_string()))
+}
+
+fn timestamp_type() -> DataType {
+    DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
+}
+
+fn timestamp_nanos(timestamp: &(u64, u64)) -> i64 {
+    (timestamp.0 as i64).saturating_mul(1_000_000_000).saturating_add(timestamp.1 as i64)
+}
+
+fn is_binary(config: &ChannelConfig) -> bool {
+    config.is_raw() || config.is_opaque()
+}
+
+fn element_type(config: &ChannelConfig) -> BsreadResult<DataType> {
+    match config.kind().as_str() {
+        "bool" => Ok(DataType::Boolean),
+        "string" => Ok(DataType::Utf8),
+        "int8" => Ok(DataType::Int8),
+        "uint8" => Ok(DataType::UInt8),
+        "int16" => Ok(DataType::Int16),
+        "uint16" => Ok(DataType::UInt16),
+        "int32" => Ok(DataType::Int32),
+        "uint32" => Ok(DataType::UInt32),
+        "int64" => Ok(DataType::Int64),
+        "uint64" => Ok(DataType::UInt64),
+        "float32" => Ok(DataType::Float32),
+        "float64" => Ok(DataType::Float64),
+        kind => Err(BsreadError::parsing(ParsingError::UnsupportedType(kind.to_string())).with_channel(config.name_str())),
+    }
+}
+
+fn channel_field(config: &ChannelConfig) -> BsreadResult<Field> {
+    let data_type = if is_binary(config) {
+        DataType::Binary
+    } else if channel::is_array(&config.shape()) {
+        DataType::FixedSizeList(Arc::new(Field::new_list_field(element_type(config)?, false)), config.elements() as i32)
+    } else {
+        element_type(config)?
+    };
+    let mut field = Field::new(config.name(), data_type, true);
+    if let Some(shape) = config.shape() {
+        field = field.with_metadata(HashMap::from([("shape".to_string(), serde_json::to_string(&shape)?)]));
+    }
+    Ok(field)
+}
+
+/// Arrow schema of the messages sharing the data header of this message.
+pub fn schema(message: &Message) -> BsreadResult<Schema> {
+    let mut fields = vec![
+        Field::new("pulse_id", DataType::UInt64, false),
+        Field::new("global_timestamp", timestamp_type(), false),
+    ];
+    for channel in message.channels() {
+        let config = channel.config();
+        fields.push(channel_field(config)?);
+        fields.push(Field::new(format!("{}{}", config.name(), TIMESTAMP_SUFFIX), timestamp_type(), true));
+    }
+    Ok(Schema::new(fields))
+}
+
+fn channel_column(field: &Field, config: &ChannelConfig, messages: &[Message]) -> BsreadResult<ArrayRef> {
+    let data: Vec<Option<&ChannelData>> = messages.iter().map(|message| message.channel_data(config.name_str())).collect();
+    let array = channel::is_array(&config.shape());
+    let binary = is_binary(config);
+    //Values must match the channel definition
+    for (message, value) in messages.iter().zip(data.iter()) {
+        if let Some(value) = value.map(ChannelData::value) {
+            let valid = if binary { value.as_au8().is_some() } else { value.kind() == config.kind() && value.is_array() == array };
+            if !valid {
+                return Err(BsreadError::validation(ValidationError::InvalidChannel(format!("{} value in a {} channel", value.name(), config.kind())))
+                    .with_channel(config.name_str()).with_pulse_id(message.id()));
+            }
+        }
+    }
+    let values: Vec<Option<&Value>> = data.iter().map(|data| data.map(ChannelData::value)).collect();
+    if binary {
+        return Ok(Arc::new(values.iter().map(|value| value.and_then(Value::as_au8)).collect::<BinaryArray>()));
+    }
+    let size = config.elements();
+
+    macro_rules! column {
+        ($array:ty, $as_scalar:ident, $as_array:ident) => {
+            if array {
+                let mut items = Vec::with_capacity(values.len() * size);
+                for (message, value) in messages.iter().zip(values.iter()) {
+                    match value.and_then(Value::$as_array) {
+                        Some(slice) if slice.len() == size => items.extend_from_slice(slice),
+                        Some(slice) => return Err(BsreadError::validation(ValidationError::InvalidChannel(format!("expected {} elements, got {}", size, slice.len())))
+                            .with_channel(config.name_str()).with_pulse_id(message.id())),
+                        None => items.resize(items.len() + size, Default::default()),
+                    }
+                }
+                let DataType::FixedSizeList(item, _) = field.data_type() else { unreachable!() };
+                let nulls = NullBuffer::from(values.iter().map(Option::is_some).collect::<Vec<bool>>());
+                let list = FixedSizeListArray::try_new(item.clone(), size as i32, Arc::new(<$array>::from(items)), Some(nulls))
+                    .map_err(encoding_error)?;
+                Arc::new(list) as ArrayRef
+            } else {
+                Arc::new(values.iter().map(|value| value.and_then(Value::$as_scalar)).collect::<$array>()) as ArrayRef
+            }
+        };
+    }
+
+    Ok(match config.kind().as_str() {
+        "bool" => column!(BooleanArray, as_bool, as_abool),
+        "string" => column!(StringArray, as_str, as_astr),
+        "int8" => column!(Int8Array, as_i8, as_ai8),
+        "uint8" => column!(UInt8Array, as_u8, as_au8),
+        "int16" => column!(Int16Array, as_i16, as_ai16),
+        "uint16" => column!(UInt16Array, as_u16, as_au16),
+        "int32" => column!(Int32Array, as_i32, as_ai32),
+        "uint32" => column!(UInt32Array, as_u32, as_au32),
+        "int64" => column!(Int64Array, as_i64, as_ai64),
+        "uint64" => column!(UInt64Array, as_u64, as_au64),
+        "float32" => column!(Float32Array, as_f32, as_af32),
+        "float64" => column!(Float64Array, as_f64, as_af64),
+        kind => return Err(BsreadError::parsing(ParsingError::UnsupportedType(kind.to_string())).with_channel(config.name_str())),
+    })
+}
+
+//Equal hashes come from the same data header, but messages created locally may differ only in the key order
+fn same_header(message: &Message, other: &Message) -> bool {
+    message.hash() == other.hash()
+        || message.channels().iter().map(|channel| channel.config()).eq(other.channels().iter().map(|channel| channel.config()))
+}
+
+/// Converts messages sharing one data header into a RecordBatch, one row per message.
+/// Channels without data in a message are null. Fails with EmptyMessageList if there are no messages.
+pub fn to_record_batch(messages: &[Message]) -> BsreadResult<RecordBatch> {
+    let first = messages.first().ok_or_else(|| BsreadError::validation(ValidationError::EmptyMessageList))?;
+    if let Some(message) = messages.iter().find(|message| !same_header(message, first)) {
+        return Err(BsreadError::validation(ValidationError::DataHeaderChanged).with_pulse_id(message.id()));
+    }
+    let schema = Arc::new(schema(first)?);
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(messages.iter().map(Message::id).collect::<UInt64Array>()),
+        Arc::new(TimestampNanosecondArray::from(messages.iter().map(|message| timestamp_nanos(&message.timestamp())).collect::<Vec<i64>>()).with_timezone("UTC")),
+    ];
+    for (index, channel) in first.channels().iter().enumerate() {
+        let config = channel.config();
+        columns.push(channel_column(schema.field(2 + 2 * index), config, messages)?);
+        let timestamps = messages.iter()
+            .map(|message| message.channel_data(config.name_str()).map(|data| timestamp_nanos(data.timestamp())))
+            .collect::<TimestampNanosecondArray>().with_timezone("UTC");
+        columns.push(Arc::new(timestamps));
+    }
+    RecordBatch::try_new(schema, columns).map_err(encoding_error)
+}
+
+/// Converts a sequence of messages into RecordBatches, starting a new batch whenever the data header changes.
+pub fn to_record_batches(messages: &[Message]) -> BsreadResult<Vec<RecordBatch>> {
+    let mut batches = Vec::new();
+    let mut start = 0;
+    for index in 1..=messages.len() {
+        let split = index == messages.len()
+            || messages[index].header_changed()
+            || !same_header(&messages[index], &messages[start]);
+        if split {
+            batches.push(to_record_batch(&messages[start..index])?);
+            start = index;
+        }
+    }
+    Ok(batches)
+}
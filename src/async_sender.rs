@@ -0,0 +1,278 @@
+use crate::*;
+use crate::sender::SenderCounters;
+use std::collections::VecDeque;
+use std::future::{poll_fn, ready};
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+
+enum Outgoing {
+    Message { message: Box<Message>, create_data_header: bool },
+    Frames(Vec<Vec<u8>>),
+}
+
+struct QueueState {
+    items: VecDeque<Outgoing>,
+    closed: bool,
+    //Set on drop: messages that cannot be sent without blocking are discarded
+    detached: bool,
+}
+
+struct SendQueue {
+    state: Mutex<QueueState>,
+    capacity: usize,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+    //Wakes the I/O task
+    not_empty: Notify,
+    //Wakes producers waiting for space
+    not_full: Notify,
+}
+
+impl SendQueue {
+    fn closed_error() -> IOError {
+        IOError::new(ErrorKind::NotConnected, "Sender closed")
+    }
+
+    //Returns false if the item was dropped
+    fn try_push(&self, item: Outgoing) -> Result<bool, Outgoing> {
+        let mut state = self.state.lock().unwrap();
+        if state.items.len() >= self.capacity {
+            match self.policy {
+                OverflowPolicy::Wait => return Err(item),
+                OverflowPolicy::DropNewest => {
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                    return Ok(false);
+                }
+                OverflowPolicy::DropOldest => {
+                    state.items.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+            }
+        }
+        state.items.push_back(item);
+        self.not_empty.notify_one();
+        Ok(true)
+    }
+
+    async fn push(&self, mut item: Outgoing) -> IOResult<bool> {
+        loop {
+            //Registered before checking, so that no notification is missed
+            let mut notified = pin!(self.not_full.notified());
+            notified.as_mut().enable();
+            if self.state.lock().unwrap().closed {
+                return Err(Self::closed_error());
+            }
+            match self.try_push(item) {
+                Ok(queued) => return Ok(queued),
+                Err(rejected) => item = rejected,
+            }
+            notified.await;
+        }
+    }
+
+    async fn pop(&self) -> Option<Outgoing> {
+        loop {
+            let mut notified = pin!(self.not_empty.notified());
+            notified.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(item) = state.items.pop_front() {
+                    self.not_full.notify_one();
+                    return Some(item);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            notified.await;
+        }
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.not_empty.notify_one();
+        self.not_full.notify_waiters();
+    }
+
+    fn detach(&self) {
+        self.state.lock().unwrap().detached = true;
+        self.close();
+    }
+
+    fn is_detached(&self) -> bool {
+        self.state.lock().unwrap().detached
+    }
+
+    //Returns the count of discarded messages
+    fn clear(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let count = state.items.len();
+        state.items.clear();
+        count
+    }
+}
+
+//Waits for the socket to accept a message. Returns false if the AsyncSender was dropped meanwhile.
+async fn wait_writable(sender: &mut Sender, queue: &SendQueue) -> IOResult<bool> {
+    loop {
+        //Notified on detach
+        let mut notified = pin!(queue.not_empty.notified());
+        notified.as_mut().enable();
+        let detached = queue.is_detached();
+        tokio::select! {
+            biased;
+            result = poll_fn(|cx| sender.poll_writable(cx)) => return result.map(|_| true),
+            _ = ready(()), if detached => return Ok(false),
+            _ = notified => {}
+        }
+    }
+}
+
+//Sends the queued messages until the queue is closed and empty, or until the AsyncSender is dropped and the socket blocks.
+//The socket is used in non-blocking mode: for blocking senders, the task waits for it to be writable.
+async fn send_queued(sender: &mut Sender, queue: &SendQueue, block: bool) {
+    while let Some(item) = queue.pop().await {
+        loop {
+            if block {
+                match wait_writable(sender, queue).await {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        log::warn!("Discarding {} messages to {}: sender dropped", queue.clear() + 1, sender.endpoint());
+                        return;
+                    }
+                    Err(e) => log::warn!("Error waiting for {}: {}", sender.endpoint(), e),
+                }
+            }
+            let result = match &item {
+                Outgoing::Message { message, create_data_header } => sender.send_message(message, *create_data_header).map(|_| ()),
+                Outgoing::Frames(frames) => sender.forward(frames),
+            };
+            match result {
+                //The socket stopped accepting messages after the wait
+                Err(e) if block && e.kind() == ErrorKind::WouldBlock => continue,
+                //Non-blocking senders drop the messages the socket does not accept
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    queue.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e) => log::warn!("Error sending to {}: {}", sender.endpoint(), e),
+                Ok(()) => {}
+            }
+            break;
+        }
+    }
+}
+
+/// Asynchronous sender: messages are queued, up to a capacity, and sent by a dedicated I/O task,
+/// so that callers never block on the socket.
+///
+/// When the queue is full, the overflow policy either waits for space, drops the new message or drops the oldest queued
+/// one. Non-blocking senders also drop the messages the socket does not accept. Send errors are logged and counted in
+/// `error_count`.
+///
+/// The I/O task never blocks in the socket: for blocking senders it waits in the tokio reactor until the socket is writable.
+/// `close` waits for all queued messages to be sent. On drop the task keeps running until it has sent the queued messages
+/// the socket accepts, the others are discarded, and the socket is closed with zero linger, so that the context can be
+/// terminated.
+pub struct AsyncSender {
+    queue: Arc<SendQueue>,
+    counters: Arc<SenderCounters>,
+    endpoint: String,
+    handle: Option<JoinHandle<Sender>>,
+}
+
+impl AsyncSender {
+    /// Takes ownership of a started sender. Must be called within a tokio runtime, which runs the I/O task.
+    pub fn new(sender: Sender, capacity: usize, policy: OverflowPolicy) -> IOResult<Self> {
+        let runtime = tokio::runtime::Handle::try_current().map_err(|e| IOError::new(ErrorKind::Unsupported, e))?;
+        let queue = Arc::new(SendQueue {
+            state: Mutex::new(QueueState { items: VecDeque::new(), closed: false, detached: false }),
+            capacity: capacity.max(1),
+            policy,
+            dropped: AtomicU64::new(0),
+            not_empty: Notify::new(),
+            not_full: Notify::new(),
+        });
+        let counters = sender.counters();
+        let endpoint = sender.endpoint();
+        let io_queue = queue.clone();
+        let mut sender = sender;
+        let block = sender.is_blocking();
+        let handle = runtime.spawn(async move {
+            match sender.register_async() {
+                Ok(()) => {
+                    sender.set_blocking(false);
+                    send_queued(&mut sender, &io_queue, block).await;
+                    sender.set_blocking(block);
+                    sender.deregister_async();
+                }
+                Err(e) => log::warn!("Error registering {}: discarding {} messages: {}", sender.endpoint(), io_queue.clear(), e),
+            }
+            if io_queue.is_detached() && let Err(e) = sender.set_linger(0) {
+                log::warn!("Error setting linger of {}: {}", sender.endpoint(), e);
+            }
+            sender
+        });
+        Ok(Self { queue, counters, endpoint, handle: Some(handle) })
+    }
+
+    /// Queues a message, returning false if it was dropped.
+    pub async fn send_message(&self, message: Message, create_data_header: bool) -> IOResult<bool> {
+        self.queue.push(Outgoing::Message { message: Box::new(message), create_data_header }).await
+    }
+
+    /// Queues raw frames, returning false if they were dropped.
+    pub async fn forward(&self, frames: Vec<Vec<u8>>) -> IOResult<bool> {
+        self.queue.push(Outgoing::Frames(frames)).await
+    }
+
+    /// Sends all queued messages and stops the I/O task, returning the sender.
+    pub async fn close(mut self) -> IOResult<Sender> {
+        self.queue.close();
+        let handle = self.handle.take().unwrap();
+        handle.await.map_err(|e| IOError::other(format!("Tokio join error: {}", e)))
+    }
+
+    pub fn endpoint(&self) -> String {
+        self.endpoint.clone()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.queue.capacity
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.queue.policy
+    }
+
+    /// Messages waiting to be sent.
+    pub fn queued(&self) -> usize {
+        self.queue.state.lock().unwrap().items.len()
+    }
+
+    /// Messages dropped because the queue was full or, for non-blocking senders, because the socket did not accept them.
+    pub fn dropped_count(&self) -> u64 {
+        self.queue.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn message_count(&self) -> u64 {
+        self.counters.messages.load(Ordering::Relaxed)
+    }
+
+    pub fn byte_count(&self) -> u64 {
+        self.counters.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn error_count(&self) -> u64 {
+        self.counters.errors.load(Ordering::Relaxed)
+    }
+}
+
+impl Drop for AsyncSender {
+    //Also releases the task if the future of close is dropped
+    fn drop(&mut self) {
+        self.queue.detach();
+    }
+}
@@ -5,6 +5,8 @@ use crate::sockets::{Transport};
 use crate::sender::{Sender};
 use crate::pool::Pool;
 use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::sync::{Mutex, Weak};
 use std::sync::atomic::{AtomicBool, Ordering};
 use zmq::{Context, SocketType};
 
@@ -12,6 +14,9 @@ use zmq::{Context, SocketType};
 pub struct Bsread {
     context: Context,
     interrupted: Arc<AtomicBool>,
+    //Async listeners of the receivers, woken on interrupt
+    #[cfg(feature = "async")]
+    wakeups: Mutex<Vec<Weak<tokio::sync::Notify>>>,
 }
 
 impl Bsread {
@@ -23,7 +28,10 @@ impl Bsread {
 
     pub fn new_with_interrupted(interrupted: Arc<AtomicBool>) ->IOResult<Arc<Self>> {
         let context = Context::new();
-        Ok(Arc::new(Self { context,interrupted }))
+        Ok(Arc::new(Self { context,interrupted,
+            #[cfg(feature = "async")]
+            wakeups: Mutex::new(Vec::new()),
+        }))
     }
 
     pub fn receiver(self: &Arc<Self>, endpoints: Option<Vec<&str>>, socket_type: SocketType, connection_mode: ConnectionMode ) -> IOResult<Receiver> {
@@ -46,6 +54,17 @@ impl Bsread {
 
     pub fn interrupt(&self) {
         self.interrupted.store(true, Ordering::Relaxed);
+        #[cfg(feature = "async")]
+        for wakeup in self.wakeups.lock().unwrap().iter().filter_map(Weak::upgrade) {
+            wakeup.notify_one();
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn register_wakeup(&self, wakeup: &Arc<tokio::sync::Notify>) {
+        let mut wakeups = self.wakeups.lock().unwrap();
+        wakeups.retain(|wakeup| wakeup.strong_count() > 0);
+        wakeups.push(Arc::downgrade(wakeup));
     }
 
     pub fn is_interrupted(&self) -> bool {
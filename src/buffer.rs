@@ -0,0 +1,198 @@
+use crate::*;
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::fmt;
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+enum BufferData {
+    Frame(zmq::Message),
+    Vec(Vec<u8>),
+}
+
+impl BufferData {
+    fn as_slice(&self) -> &[u8] {
+        match self {
+            BufferData::Frame(msg) => msg,
+            BufferData::Vec(v) => v.as_slice(),
+        }
+    }
+}
+
+/// Reference-counted view into a received zmq frame or a decompression buffer.
+/// Cloning and slicing never copy the underlying bytes.
+#[derive(Clone)]
+pub struct SharedBuffer {
+    data: Arc<BufferData>,
+    range: Range<usize>,
+}
+
+impl SharedBuffer {
+    pub fn from_frame(frame: zmq::Message) -> Self {
+        let len = frame.len();
+        Self { data: Arc::new(BufferData::Frame(frame)), range: 0..len }
+    }
+
+    pub fn from_vec(data: Vec<u8>) -> Self {
+        let len = data.len();
+        Self { data: Arc::new(BufferData::Vec(data)), range: 0..len }
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data.as_slice()[self.range.clone()]
+    }
+
+    /// Sub-view of this buffer, or None if the range is out of bounds.
+    pub fn slice(&self, range: Range<usize>) -> Option<Self> {
+        if range.start > range.end || range.end > self.len() {
+            return None;
+        }
+        let start = self.range.start + range.start;
+        let end = self.range.start + range.end;
+        Some(Self { data: self.data.clone(), range: start..end })
+    }
+
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.as_slice().to_vec()
+    }
+
+    /// Reinterprets the bytes as a slice of T in native byte order.
+    /// Returns None if the buffer is not aligned for T or its size is not a multiple of T.
+    pub fn as_typed<T: Element>(&self) -> Option<&[T]> {
+        //Safe: Element is only implemented for primitive types valid for any bit pattern
+        let (prefix, data, suffix) = unsafe { self.as_slice().align_to::<T>() };
+        (prefix.is_empty() && suffix.is_empty()).then_some(data)
+    }
+}
+
+impl Deref for SharedBuffer {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl AsRef<[u8]> for SharedBuffer {
+    fn as_ref(&self) -> &[u8] {
+        self.as_slice()
+    }
+}
+
+impl From<Vec<u8>> for SharedBuffer {
+    fn from(data: Vec<u8>) -> Self {
+        SharedBuffer::from_vec(data)
+    }
+}
+
+impl fmt::Debug for SharedBuffer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedBuffer({} bytes)", self.len())
+    }
+}
+
+/// Primitive element types of array channels that can be exposed as typed slices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElementType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    F32,
+    F64,
+}
+
+impl ElementType {
+    pub fn from_kind(kind: &str) -> Option<Self> {
+        match kind {
+            "int8" => Some(ElementType::I8),
+            "uint8" => Some(ElementType::U8),
+            "int16" => Some(ElementType::I16),
+            "uint16" => Some(ElementType::U16),
+            "int32" => Some(ElementType::I32),
+            "uint32" => Some(ElementType::U32),
+            "int64" => Some(ElementType::I64),
+            "uint64" => Some(ElementType::U64),
+            "float32" => Some(ElementType::F32),
+            "float64" => Some(ElementType::F64),
+            _ => None,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            ElementType::I8 | ElementType::U8 => 1,
+            ElementType::I16 | ElementType::U16 => 2,
+            ElementType::I32 | ElementType::U32 | ElementType::F32 => 4,
+            ElementType::I64 | ElementType::U64 | ElementType::F64 => 8,
+        }
+    }
+
+    /// Decodes the bytes into an owned array value. The length must be a multiple of the element size.
+    pub fn decode(&self, bytes: &[u8], little_endian: bool) -> Value {
+        macro_rules! decode {
+            ($t:ty, $variant:ident, $read:ident) => {{
+                let mut data = vec![<$t>::default(); bytes.len() / std::mem::size_of::<$t>()];
+                if little_endian {
+                    LittleEndian::$read(bytes, &mut data);
+                } else {
+                    BigEndian::$read(bytes, &mut data);
+                }
+                Value::$variant(data)
+            }};
+        }
+        match self {
+            ElementType::I8 => Value::AI8(bytes.iter().map(|&b| b as i8).collect()),
+            ElementType::U8 => Value::AU8(bytes.to_vec()),
+            ElementType::I16 => decode!(i16, AI16, read_i16_into),
+            ElementType::U16 => decode!(u16, AU16, read_u16_into),
+            ElementType::I32 => decode!(i32, AI32, read_i32_into),
+            ElementType::U32 => decode!(u32, AU32, read_u32_into),
+            ElementType::I64 => decode!(i64, AI64, read_i64_into),
+            ElementType::U64 => decode!(u64, AU64, read_u64_into),
+            ElementType::F32 => decode!(f32, AF32, read_f32_into),
+            ElementType::F64 => decode!(f64, AF64, read_f64_into),
+        }
+    }
+}
+
+/// Primitive types that can be viewed directly in a SharedBuffer.
+pub trait Element: Copy + Default + 'static {
+    const TYPE: ElementType;
+    fn from_value(value: &Value) -> Option<&[Self]>;
+}
+
+macro_rules! impl_element {
+    ($t:ty, $type:ident, $variant:ident) => {
+        impl Element for $t {
+            const TYPE: ElementType = ElementType::$type;
+            fn from_value(value: &Value) -> Option<&[Self]> {
+                match value {
+                    Value::$variant(data) => Some(data.as_slice()),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_element!(i8, I8, AI8);
+impl_element!(u8, U8, AU8);
+impl_element!(i16, I16, AI16);
+impl_element!(u16, U16, AU16);
+impl_element!(i32, I32, AI32);
+impl_element!(u32, U32, AU32);
+impl_element!(i64, I64, AI64);
+impl_element!(u64, U64, AU64);
+impl_element!(f32, F32, AF32);
+impl_element!(f64, F64, AF64);
@@ -0,0 +1,111 @@
+use crate::*;
+use crate::message::{ID_SIMULATED, TIMESTAMP_NOW};
+use indexmap::IndexMap;
+use std::collections::HashSet;
+
+struct ChannelEntry {
+    name: String,
+    value: Value,
+    shape: Option<Vec<u32>>,
+    compression: Compression,
+}
+
+/// Fluent construction of a Message. The bsread type and shape of each channel are inferred from its Value.
+pub struct MessageBuilder {
+    pulse_id: u64,
+    timestamp: (u64, u64),
+    little_endian: bool,
+    channels: Vec<ChannelEntry>,
+}
+
+impl Default for MessageBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MessageBuilder {
+    pub fn new() -> Self {
+        Self { pulse_id: ID_SIMULATED, timestamp: TIMESTAMP_NOW, little_endian: true, channels: Vec::new() }
+    }
+
+    /// Defaults to ID_SIMULATED.
+    pub fn pulse_id(mut self, pulse_id: u64) -> Self {
+        self.pulse_id = pulse_id;
+        self
+    }
+
+    /// Global timestamp (seconds, nanoseconds), also used for the channels. Defaults to TIMESTAMP_NOW.
+    pub fn timestamp(mut self, timestamp: (u64, u64)) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Byte order of all channels. Defaults to little endian.
+    pub fn little_endian(mut self, little_endian: bool) -> Self {
+        self.little_endian = little_endian;
+        self
+    }
+
+    pub fn channel(self, name: &str, value: impl Into<Value>) -> Self {
+        self.channel_with(name, value, Compression::None)
+    }
+
+    pub fn channel_with(self, name: &str, value: impl Into<Value>, compression: Compression) -> Self {
+        self.add(name, value.into(), None, compression)
+    }
+
+    /// Multidimensional arrays: the product of the shape must match the array size.
+    pub fn channel_with_shape(self, name: &str, value: impl Into<Value>, shape: Vec<u32>, compression: Compression) -> Self {
+        self.add(name, value.into(), Some(shape), compression)
+    }
+
+    fn add(mut self, name: &str, value: Value, shape: Option<Vec<u32>>, compression: Compression) -> Self {
+        self.channels.push(ChannelEntry { name: name.to_string(), value, shape, compression });
+        self
+    }
+
+    fn shape(entry: &ChannelEntry) -> BsreadResult<Option<Vec<u32>>> {
+        let invalid = |reason: String| BsreadError::validation(ValidationError::InvalidChannel(reason)).with_channel(&entry.name);
+        let value = &entry.value;
+        if value.is_scalar() {
+            return match &entry.shape {
+                None => Ok(None),
+                Some(_) => Err(invalid("shape given for a scalar value".to_string())),
+            };
+        }
+        //Bsread does not support arrays with size=1
+        if value.size() < 2 {
+            return Err(invalid(format!("array of size {}", value.size())));
+        }
+        match &entry.shape {
+            None => Ok(Some(vec![value.size() as u32])),
+            Some(shape) => {
+                let elements = shape.iter().map(|&dim| dim as usize).product::<usize>();
+                if elements != value.size() || !channel::is_array(&entry.shape) {
+                    return Err(invalid(format!("shape {:?} does not match array of size {}", shape, value.size())));
+                }
+                Ok(Some(shape.clone()))
+            }
+        }
+    }
+
+    pub fn build(self) -> BsreadResult<Message> {
+        if self.channels.is_empty() {
+            return Err(BsreadError::validation(ValidationError::EmptyChannelData));
+        }
+        let mut names = HashSet::new();
+        let mut channels = Vec::with_capacity(self.channels.len());
+        let mut data = IndexMap::with_capacity(self.channels.len());
+        for entry in self.channels {
+            if !names.insert(entry.name.clone()) {
+                return Err(BsreadError::validation(ValidationError::InvalidChannel("duplicated name".to_string())).with_channel(&entry.name));
+            }
+            let shape = Self::shape(&entry)?;
+            let channel = channel::new(entry.name.clone(), entry.value.kind().to_string(), shape, self.little_endian, entry.compression, false)?;
+            channels.push(channel);
+            data.insert(entry.name, Some(ChannelData::new(entry.value, self.timestamp)));
+        }
+        Message::new_from_channel_map(self.pulse_id, self.timestamp, channels, data)
+    }
+}
@@ -21,7 +21,8 @@
 
 // Constants.
 // Use fast decompression instead of safe decompression for LZ4.
-#define BSHUF_LZ4_DECOMPRESS_FAST
+// Disabled: the fast decoder trusts its input and can read past the end of hostile blocks.
+//#define BSHUF_LZ4_DECOMPRESS_FAST
 
 
 // Macros.
@@ -0,0 +1,310 @@
+use crate::*;
+use crate::utils::current_timestamp;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// Capture file format, all integers little endian:
+//
+// File header: magic "BSCAPTUR" (8 bytes), version (u32), flags (u32, reserved).
+// Records, one per multipart message, appended after the header:
+//   magic "BSRC" (4 bytes), body length (u32), body, CRC32 of the body (u32)
+//   body: receive time seconds (u64), nanoseconds (u32), endpoint length (u16), frame count (u32),
+//         endpoint (UTF-8), frame index (frame count x u32 frame lengths), frame bytes.
+//
+// A record is only valid once completely written: a reader stops at a truncated record and returns all the previous ones.
+// After a corrupt record header (invalid magic or length), the reader resyncs on the next record magic.
+
+const FILE_MAGIC: &[u8; 8] = b"BSCAPTUR";
+const RECORD_MAGIC: &[u8; 4] = b"BSRC";
+pub const CAPTURE_VERSION: u32 = 1;
+const FILE_HEADER_SIZE: u64 = 16;
+/// Maximum size of a record body: larger lengths in a capture file are treated as corrupt.
+pub const MAX_RECORD_SIZE: usize = 256 * 1024 * 1024;
+
+pub const FILE_SCHEME: &str = "file://";
+
+fn invalid(reason: impl Into<String>) -> BsreadError {
+    BsreadError::parsing(ParsingError::InvalidData(reason.into()))
+}
+
+/// Path of the capture file of a receiver endpoint such as "file:///data/run42.bsr", None for socket endpoints.
+pub fn file_endpoint_path(endpoint: &str) -> Option<&Path> {
+    endpoint.strip_prefix(FILE_SCHEME).map(Path::new)
+}
+
+/// Message read from a capture file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CapturedMessage {
+    /// Time the message was received.
+    pub timestamp: (u64, u64),
+    pub endpoint: Option<String>,
+    /// Multipart frames, as received.
+    pub frames: Vec<Vec<u8>>,
+}
+
+fn encode_record<T: AsRef<[u8]>>(timestamp: (u64, u64), endpoint: &Option<String>, frames: &[T]) -> BsreadResult<Vec<u8>> {
+    let endpoint = endpoint.as_deref().unwrap_or("");
+    let endpoint_len = u16::try_from(endpoint.len()).map_err(|_| invalid("endpoint name too long"))?;
+    let frame_count = u32::try_from(frames.len()).map_err(|_| invalid("too many frames"))?;
+    let data_size: usize = frames.iter().map(|frame| frame.as_ref().len()).sum();
+    let mut body = Vec::with_capacity(18 + endpoint.len() + 4 * frames.len() + data_size);
+    body.write_u64::<LittleEndian>(timestamp.0)?;
+    body.write_u32::<LittleEndian>(timestamp.1 as u32)?;
+    body.write_u16::<LittleEndian>(endpoint_len)?;
+    body.write_u32::<LittleEndian>(frame_count)?;
+    body.extend_from_slice(endpoint.as_bytes());
+    for frame in frames {
+        let len = u32::try_from(frame.as_ref().len()).map_err(|_| invalid("frame too large"))?;
+        body.write_u32::<LittleEndian>(len)?;
+    }
+    for frame in frames {
+        body.extend_from_slice(frame.as_ref());
+    }
+    if body.len() > MAX_RECORD_SIZE {
+        return Err(invalid("message too large"));
+    }
+    let body_len = body.len() as u32;
+
+    let mut record = Vec::with_capacity(body.len() + 12);
+    record.extend_from_slice(RECORD_MAGIC);
+    record.write_u32::<LittleEndian>(body_len)?;
+    record.extend_from_slice(&body);
+    record.write_u32::<LittleEndian>(crc32fast::hash(&body))?;
+    Ok(record)
+}
+
+fn decode_body(body: &[u8]) -> BsreadResult<CapturedMessage> {
+    let mut cursor = body;
+    let secs = cursor.read_u64::<LittleEndian>()?;
+    let nanos = cursor.read_u32::<LittleEndian>()?;
+    let endpoint_len = cursor.read_u16::<LittleEndian>()? as usize;
+    let frame_count = cursor.read_u32::<LittleEndian>()? as usize;
+    if cursor.len() < endpoint_len + 4 * frame_count {
+        return Err(invalid("invalid record index"));
+    }
+    let (endpoint, mut cursor) = cursor.split_at(endpoint_len);
+    let endpoint = String::from_utf8(endpoint.to_vec()).map_err(|e| invalid(e.to_string()))?;
+    let mut lengths = Vec::with_capacity(frame_count);
+    for _ in 0..frame_count {
+        lengths.push(cursor.read_u32::<LittleEndian>()? as usize);
+    }
+    if lengths.iter().sum::<usize>() != cursor.len() {
+        return Err(invalid("frame index does not match record size"));
+    }
+    let mut frames = Vec::with_capacity(frame_count);
+    for len in lengths {
+        let (frame, rest) = cursor.split_at(len);
+        frames.push(frame.to_vec());
+        cursor = rest;
+    }
+    let endpoint = (!endpoint.is_empty()).then_some(endpoint);
+    Ok(CapturedMessage { timestamp: (secs, nanos as u64), endpoint, frames })
+}
+
+//Reads until the buffer is full or EOF, returning the number of bytes read
+fn read_fully<R: Read>(reader: &mut R, buf: &mut [u8]) -> BsreadResult<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(read)
+}
+
+/// Sequential reader of capture files. Iteration stops at the end of the file or at a truncated record.
+pub struct CaptureReader<R = BufReader<File>> {
+    reader: R,
+    offset: u64,
+    position: u64,
+    //Last bytes read, if a corrupt record header must be skipped
+    resync: Option<[u8; 4]>,
+    truncated: bool,
+    finished: bool,
+}
+
+impl CaptureReader {
+    pub fn open(path: impl AsRef<Path>) -> BsreadResult<Self> {
+        CaptureReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    pub fn new(mut reader: R) -> BsreadResult<Self> {
+        let mut header = [0u8; FILE_HEADER_SIZE as usize];
+        if read_fully(&mut reader, &mut header)? < header.len() || &header[..8] != FILE_MAGIC {
+            return Err(invalid("not a capture file"));
+        }
+        let version = u32::from_le_bytes([header[8], header[9], header[10], header[11]]);
+        if version > CAPTURE_VERSION {
+            return Err(invalid(format!("unsupported capture file version {}", version)));
+        }
+        Ok(Self { reader, offset: FILE_HEADER_SIZE, position: FILE_HEADER_SIZE, resync: None, truncated: false, finished: false })
+    }
+
+    /// True if the file ends with an incomplete record, or with bytes that are not a record.
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Offset of the end of the last complete record read.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> BsreadResult<usize> {
+        let read = read_fully(&mut self.reader, buf)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+
+    //Skips bytes until the window holds a record magic. Returns false at the end of the file.
+    fn skip_to_magic(&mut self, mut window: [u8; 4]) -> BsreadResult<bool> {
+        let mut byte = [0u8; 1];
+        while &window != RECORD_MAGIC {
+            if self.read(&mut byte)? == 0 {
+                return Ok(false);
+            }
+            window.rotate_left(1);
+            window[3] = byte[0];
+        }
+        Ok(true)
+    }
+
+    fn read_record(&mut self) -> BsreadResult<Option<CapturedMessage>> {
+        match self.resync.take() {
+            Some(window) => {
+                if !self.skip_to_magic(window)? {
+                    self.truncated = true;
+                    return Ok(None);
+                }
+            }
+            None => {
+                let mut magic = [0u8; 4];
+                match self.read(&mut magic)? {
+                    0 => return Ok(None),
+                    4 => {}
+                    _ => {
+                        self.truncated = true;
+                        return Ok(None);
+                    }
+                }
+                if &magic != RECORD_MAGIC {
+                    self.resync = Some(magic);
+                    return Err(invalid(format!("invalid record at offset {}", self.offset - 4)));
+                }
+            }
+        }
+        let offset = self.offset - 4;
+        let mut length = [0u8; 4];
+        if self.read(&mut length)? < length.len() {
+            self.truncated = true;
+            return Ok(None);
+        }
+        let body_len = u32::from_le_bytes(length) as usize;
+        if body_len > MAX_RECORD_SIZE {
+            self.resync = Some(length);
+            return Err(invalid(format!("invalid record length {} at offset {}", body_len, offset)));
+        }
+        let mut body = vec![0u8; body_len + 4];
+        if self.read(&mut body)? < body.len() {
+            self.truncated = true;
+            return Ok(None);
+        }
+        let checksum = body.split_off(body_len);
+        self.position = self.offset;
+        if crc32fast::hash(&body).to_le_bytes() != checksum.as_slice() {
+            return Err(invalid(format!("checksum mismatch in record at offset {}", offset)));
+        }
+        decode_body(&body).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = BsreadResult<CapturedMessage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let result = self.read_record().transpose();
+        if result.is_none() {
+            self.finished = true;
+        }
+        result
+    }
+}
+
+struct RecorderFile {
+    file: File,
+    count: u64,
+}
+
+/// Appends the raw multipart frames of received messages to a capture file.
+/// Clones share the same file, so one recorder can be set to several receivers.
+#[derive(Clone)]
+pub struct Recorder {
+    path: PathBuf,
+    file: Arc<Mutex<RecorderFile>>,
+}
+
+impl Recorder {
+    /// Creates a new capture file, replacing any existing one.
+    pub fn create(path: impl AsRef<Path>) -> BsreadResult<Self> {
+        let mut file = File::create(path.as_ref())?;
+        let mut header = Vec::with_capacity(FILE_HEADER_SIZE as usize);
+        header.extend_from_slice(FILE_MAGIC);
+        header.write_u32::<LittleEndian>(CAPTURE_VERSION)?;
+        header.write_u32::<LittleEndian>(0)?;
+        file.write_all(&header)?;
+        Ok(Self { path: path.as_ref().to_path_buf(), file: Arc::new(Mutex::new(RecorderFile { file, count: 0 })) })
+    }
+
+    /// Appends to an existing capture file, or creates it. A truncated record at the end of the file is discarded.
+    pub fn append(path: impl AsRef<Path>) -> BsreadResult<Self> {
+        if !path.as_ref().exists() || std::fs::metadata(path.as_ref())?.len() == 0 {
+            return Recorder::create(path);
+        }
+        let mut reader = CaptureReader::open(path.as_ref())?;
+        let count = reader.by_ref().filter(Result::is_ok).count() as u64;
+        let mut file = OpenOptions::new().write(true).open(path.as_ref())?;
+        if !reader.is_truncated() && reader.position() < file.metadata()?.len() {
+            return Err(invalid(format!("invalid record at offset {}", reader.position())));
+        }
+        if reader.is_truncated() {
+            log::warn!("Discarding truncated record at the end of {}", path.as_ref().display());
+            file.set_len(reader.position())?;
+        }
+        file.seek(SeekFrom::Start(reader.position()))?;
+        Ok(Self { path: path.as_ref().to_path_buf(), file: Arc::new(Mutex::new(RecorderFile { file, count })) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Number of messages in the file.
+    pub fn count(&self) -> u64 {
+        self.file.lock().unwrap().count
+    }
+
+    /// Writes a message, timestamped with the current time.
+    pub fn record<T: AsRef<[u8]>>(&self, endpoint: &Option<String>, frames: &[T]) -> BsreadResult<()> {
+        self.record_at(current_timestamp(), endpoint, frames)
+    }
+
+    pub fn record_at<T: AsRef<[u8]>>(&self, timestamp: (u64, u64), endpoint: &Option<String>, frames: &[T]) -> BsreadResult<()> {
+        //Encoded beforehand so that each record is written with a single call
+        let record = encode_record(timestamp, endpoint, frames)?;
+        let mut state = self.file.lock().unwrap();
+        state.file.write_all(&record)?;
+        state.count += 1;
+        Ok(())
+    }
+}
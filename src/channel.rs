@@ -1,14 +1,15 @@
 use crate::*;
 use crate::value::Value;
-use crate::reader::{READER_ABF32, READER_ABF64, READER_ABI16, READER_ABI32, READER_ABI64, READER_ABOOL, READER_ABU16, READER_ABU32, READER_ABU64, READER_AF32, READER_AF64, READER_AI16, READER_AI32, READER_AI64, READER_AI8, READER_AU16, READER_AU32, READER_AU64, READER_AU8, READER_BF32, READER_BF64, READER_BI16, READER_BI32, READER_BI64, READER_BOOL, READER_BU16, READER_BU32, READER_BU64, READER_F32, READER_F64, READER_I16, READER_I32, READER_I64, READER_I8, READER_STRING, READER_U16, READER_U32, READER_U64, READER_U8, READER_RAW, READER_BRAW16, READER_BRAW32, READER_BRAW64};
-use crate::writer::{WRITER_ABF32, WRITER_ABF64, WRITER_ABI16, WRITER_ABI32, WRITER_ABI64, WRITER_ABOOL, WRITER_ABU16, WRITER_ABU32, WRITER_ABU64, WRITER_AF32, WRITER_AF64, WRITER_AI16, WRITER_AI32, WRITER_AI64, WRITER_AI8, WRITER_AU16, WRITER_AU32, WRITER_AU64, WRITER_AU8, WRITER_BF32, WRITER_BF64, WRITER_BI16, WRITER_BI32, WRITER_BI64, WRITER_BOOL, WRITER_BU16, WRITER_BU32, WRITER_BU64, WRITER_F32, WRITER_F64, WRITER_I16, WRITER_I32, WRITER_I64, WRITER_I8, WRITER_STRING, WRITER_U16, WRITER_U32, WRITER_U64, WRITER_U8, WRITER_RAW, WRITER_BRAW16, WRITER_BRAW32, WRITER_BRAW64};
-use std::io::Cursor;
+use crate::reader::{READER_ABF32, READER_ABF64, READER_ABI16, READER_ABI32, READER_ABI64, READER_ABOOL, READER_ABU16, READER_ABU32, READER_ABU64, READER_AF32, READER_AF64, READER_AI16, READER_AI32, READER_AI64, READER_AI8, READER_AU16, READER_AU32, READER_AU64, READER_AU8, READER_BF32, READER_BF64, READER_BI16, READER_BI32, READER_BI64, READER_BOOL, READER_BU16, READER_BU32, READER_BU64, READER_F32, READER_F64, READER_I16, READER_I32, READER_I64, READER_I8, READER_STRING, READER_ASTRING, READER_U16, READER_U32, READER_U64, READER_U8, READER_RAW, READER_BRAW16, READER_BRAW32, READER_BRAW64};
+use crate::writer::{WRITER_ABF32, WRITER_ABF64, WRITER_ABI16, WRITER_ABI32, WRITER_ABI64, WRITER_ABOOL, WRITER_ABU16, WRITER_ABU32, WRITER_ABU64, WRITER_AF32, WRITER_AF64, WRITER_AI16, WRITER_AI32, WRITER_AI64, WRITER_AI8, WRITER_AU16, WRITER_AU32, WRITER_AU64, WRITER_AU8, WRITER_BF32, WRITER_BF64, WRITER_BI16, WRITER_BI32, WRITER_BI64, WRITER_BOOL, WRITER_BU16, WRITER_BU32, WRITER_BU64, WRITER_F32, WRITER_F64, WRITER_I16, WRITER_I32, WRITER_I64, WRITER_I8, WRITER_STRING, WRITER_ASTRING, WRITER_U16, WRITER_U32, WRITER_U64, WRITER_U8, WRITER_RAW, WRITER_BRAW16, WRITER_BRAW32, WRITER_BRAW64};
+use std::io::{Cursor, Read, Write};
 use std::collections::HashMap;
 use serde_json::Value as JsonValue;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use crate::compression::{decompress_bitshuffle_lz4, decompress_lz4};
 
 #[derive(Debug)]
-#[derive(Clone)]
+#[derive(Clone, PartialEq)]
 pub struct ChannelConfig {
     name: String,
     kind: String,
@@ -18,6 +19,7 @@ pub struct ChannelConfig {
     little_endian: bool,
     compression: Compression,
     raw: bool,
+    opaque: bool,
 }
 
 
@@ -25,6 +27,9 @@ impl ChannelConfig {
     pub fn name(&self) -> String {
         self.name.clone()
     }
+    pub(crate) fn name_str(&self) -> &str {
+        &self.name
+    }
     pub fn kind(&self) -> String {
         self.kind.clone()
     }
@@ -38,7 +43,7 @@ impl ChannelConfig {
         self.element_size
     }
     pub fn size(&self) -> usize {
-        self.element_size * self.elements
+        self.element_size.saturating_mul(self.elements)
     }
     pub fn is_little_endian(&self) -> bool {
         self.little_endian
@@ -49,6 +54,9 @@ impl ChannelConfig {
     pub fn is_raw(&self) -> bool {
         self.raw
     }
+    pub fn is_opaque(&self) -> bool {
+        self.opaque
+    }
 
     pub fn metadata(&self) -> HashMap<String, JsonValue> {
         let mut metadata: HashMap<String, JsonValue> = HashMap::new();
@@ -67,35 +75,91 @@ impl ChannelConfig {
 }
 
 
+//Serialized with the field names of the data header
+#[derive(Serialize, Deserialize)]
+struct SerializedConfig {
+    name: String,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    shape: Option<Vec<u32>>,
+    encoding: String,
+    compression: Compression,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    raw: bool,
+}
+
+impl Serialize for ChannelConfig {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedConfig {
+            name: self.name.clone(),
+            kind: self.kind.clone(),
+            shape: self.shape.clone(),
+            encoding: (if self.little_endian { "little" } else { "big" }).to_string(),
+            compression: self.compression,
+            raw: self.raw,
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChannelConfig {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SerializedConfig { name, kind, shape, encoding, compression, raw } = SerializedConfig::deserialize(deserializer)?;
+        let little_endian = match encoding.as_str() {
+            "little" => true,
+            "big" => false,
+            _ => return Err(serde::de::Error::custom(format!("invalid encoding: {}", encoding))),
+        };
+        let opaque = !is_known_type(&kind);
+        let element_size = if opaque { opaque_element_size(&kind) } else { element_size(&kind) };
+        let elements = elements(&shape);
+        Ok(ChannelConfig { name, kind, shape, elements, element_size, little_endian, compression, raw, opaque })
+    }
+}
+
 #[derive(Clone)]
 pub struct ChannelScalar<T> {
     config: ChannelConfig,
-    reader: fn(&mut Cursor<&Vec<u8>>) -> IOResult<T>,
+    reader: fn(&mut Cursor<&[u8]>) -> IOResult<T>,
     writer: fn(&mut Cursor<&mut Vec<u8>>, &T) -> IOResult<()>
 }
 
 #[derive(Clone)]
 pub struct ChannelArray<T> {
     config: ChannelConfig,
-    reader: fn(&mut Cursor<&Vec<u8>>, &mut [T]) -> IOResult<()>,
+    reader: fn(&mut Cursor<&[u8]>, &mut [T]) -> IOResult<()>,
     writer: fn(&mut Cursor<&mut Vec<u8>>, &[T]) -> IOResult<()>
 }
 
 #[derive(Clone)]
 pub struct ChannelRaw {
     config: ChannelConfig,
-    reader: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()>,
+    reader: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()>,
     writer: fn(&mut Cursor<&mut Vec<u8>>, &[u8]) -> IOResult<()>
 }
 
+//Channel of a type unknown to this library: the bytes are kept as they are
+#[derive(Clone)]
+pub struct ChannelOpaque {
+    config: ChannelConfig,
+}
+
 pub fn elements(shape: &Option<Vec<u32>>) -> usize {
-    let nelm = shape.clone()
+    let nelm = shape.as_ref()
         .filter(|v| !v.is_empty()) // Ensure it's not empty
-        .map(|v| v.into_iter().product()) // Compute product of elements
+        .map(|v| v.iter().fold(1usize, |acc, &n| acc.saturating_mul(n as usize))) // Compute product of elements
         .unwrap_or(1);
     //Default to 1 if None or empty
-    let elements = nelm as usize;
-    elements
+    nelm
+}
+
+//Checks the buffer holds the expected bytes before allocating, as the shape comes from the (untrusted) data header
+fn check_remaining(cursor: &Cursor<&[u8]>, size: usize) -> IOResult<()> {
+    let remaining = (cursor.get_ref().len() as u64).saturating_sub(cursor.position());
+    if remaining < size as u64 {
+        return Err(IOError::new(ErrorKind::UnexpectedEof, format!("Invalid data size: expected {} bytes, got {}", size, remaining)));
+    }
+    Ok(())
 }
 
 fn element_size(typ: &str) -> usize {
@@ -115,12 +179,25 @@ fn element_size(typ: &str) -> usize {
         _ => 4,
     }
 }
+
+pub fn is_known_type(typ: &str) -> bool {
+    matches!(typ, "bool" | "string" | "int8" | "uint8" | "int16" | "uint16" | "int32" | "uint32" | "int64" | "uint64" | "float32" | "float64")
+}
+
+//Element size of unknown types, guessed from the bit count suffix (float16, complex64...), used for decompression.
+fn opaque_element_size(typ: &str) -> usize {
+    let digits = typ.trim_start_matches(|c: char| !c.is_ascii_digit());
+    match digits.parse::<usize>() {
+        Ok(bits) if bits >= 8 && bits % 8 == 0 && bits <= 1024 => bits / 8,
+        _ => 1,
+    }
+}
 impl<T: Default + Clone> ChannelScalar<T> {
     pub fn new(name: String, typ: String, shape: Option<Vec<u32>>, little_endian: bool, compression: Compression,
-               reader: fn(&mut Cursor<&Vec<u8>>) -> IOResult<T>, writer: fn(&mut Cursor<&mut Vec<u8>>, &T) -> IOResult<()>) -> Self {
+               reader: fn(&mut Cursor<&[u8]>) -> IOResult<T>, writer: fn(&mut Cursor<&mut Vec<u8>>, &T) -> IOResult<()>) -> Self {
         let elements = elements(&shape);
         let element_size = element_size(&typ);
-        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw:false };
+        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw:false, opaque: false };
         Self { config, reader, writer }
     }
 }
@@ -128,25 +205,34 @@ impl<T: Default + Clone> ChannelScalar<T> {
 
 impl<T: Default + Clone> ChannelArray<T> {
     pub fn new(name: String, typ: String, shape: Option<Vec<u32>>, little_endian: bool, compression: Compression,
-               reader: fn(&mut Cursor<&Vec<u8>>, &mut [T]) -> IOResult<()>,  writer: fn(&mut Cursor<&mut Vec<u8>>, &[T]) -> IOResult<()>) -> Self {
+               reader: fn(&mut Cursor<&[u8]>, &mut [T]) -> IOResult<()>,  writer: fn(&mut Cursor<&mut Vec<u8>>, &[T]) -> IOResult<()>) -> Self {
         let elements = elements(&shape);
         let element_size = element_size(&typ);
-        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw: false };
+        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw: false, opaque: false };
         Self { config, reader, writer }
     }
 }
 
 impl ChannelRaw {
     pub fn new(name: String, typ: String, shape: Option<Vec<u32>>, little_endian: bool, compression: Compression,
-               reader: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()>, writer: fn(&mut Cursor<&mut Vec<u8>>, &[u8]) -> IOResult<()>) -> Self {
+               reader: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()>, writer: fn(&mut Cursor<&mut Vec<u8>>, &[u8]) -> IOResult<()>) -> Self {
         let elements = elements(&shape);
         let element_size = element_size(&typ);
-        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw: true };
+        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw: true, opaque: false };
         Self { config, reader, writer }
     }
 }
 
-static EMPTY_CONFIG: ChannelConfig = ChannelConfig { name: String::new(), kind: String::new(), shape: None, elements: 0, element_size: 0, little_endian: false, compression: Compression::None, raw: false};
+impl ChannelOpaque {
+    pub fn new(name: String, typ: String, shape: Option<Vec<u32>>, little_endian: bool, compression: Compression, raw: bool) -> Self {
+        let elements = elements(&shape);
+        let element_size = opaque_element_size(&typ);
+        let config = ChannelConfig { name, kind: typ, shape, elements, element_size, little_endian, compression, raw, opaque: true };
+        Self { config }
+    }
+}
+
+static EMPTY_CONFIG: ChannelConfig = ChannelConfig { name: String::new(), kind: String::new(), shape: None, elements: 0, element_size: 0, little_endian: false, compression: Compression::None, raw: false, opaque: false};
 
 
 pub trait ChannelClone {
@@ -157,7 +243,7 @@ pub trait ChannelTrait: Send + ChannelClone {
     fn config(&self) -> &ChannelConfig {
         &EMPTY_CONFIG
     }
-    fn read(&self, _: &mut Cursor<&Vec<u8>>) -> IOResult<Value> {
+    fn read(&self, _: &mut Cursor<&[u8]>) -> IOResult<Value> {
         Err(IOError::new(ErrorKind::Unsupported, "Unsupported channel type"))
     }
 
@@ -187,8 +273,9 @@ impl ChannelTrait for ChannelRaw {
         return &self.config
     }
 
-    fn read(&self, cursor: &mut Cursor<&Vec<u8>>) -> IOResult<Value> {
-        let size = self.config.elements * self.config.elements;
+    fn read(&self, cursor: &mut Cursor<&[u8]>) -> IOResult<Value> {
+        let size = self.config.size();
+        check_remaining(cursor, size)?;
         let mut buffer: Vec<u8> = Vec::with_capacity(size);
         unsafe {
             buffer.set_len(size); // Initialize the buffer without default values
@@ -207,11 +294,30 @@ impl ChannelTrait for ChannelRaw {
     }
 }
 
+impl ChannelTrait for ChannelOpaque {
+    fn config(&self) -> &ChannelConfig{
+        return &self.config
+    }
+
+    fn read(&self, cursor: &mut Cursor<&[u8]>) -> IOResult<Value> {
+        let mut buffer = Vec::new();
+        cursor.read_to_end(&mut buffer)?;
+        Ok(Value::AU8(buffer))
+    }
+
+    fn write(&self, cursor: &mut Cursor<&mut Vec<u8>>, value: &Value) -> IOResult<()> {
+        if let Value::AU8(data) = value {
+            cursor.write_all(data)
+        } else {
+            Err(IOError::new(ErrorKind::InvalidInput, "Opaque channel write requires a byte array"))
+        }
+    }
+}
 
 macro_rules! impl_channel_scalar_trait {
     ($t:ty, $variant:ident) => {
         impl ChannelTrait for ChannelScalar<$t> {
-            fn read(&self, cursor: &mut Cursor<&Vec<u8>>) -> IOResult<Value> {
+            fn read(&self, cursor: &mut Cursor<&[u8]>) -> IOResult<Value> {
                 let result = (self.reader)(cursor)?;
                 Ok(Value::$variant(result))
             }
@@ -236,7 +342,8 @@ macro_rules! impl_channel_scalar_trait {
 macro_rules! impl_channel_array_trait {
     ($t:ty, $variant:ident) => {
         impl ChannelTrait for ChannelArray<$t> {
-           fn read(&self, cursor: &mut Cursor<&Vec<u8>>) -> IOResult<Value> {
+           fn read(&self, cursor: &mut Cursor<&[u8]>) -> IOResult<Value> {
+                check_remaining(cursor, self.config.elements.saturating_mul(std::mem::size_of::<$t>()))?;
                 let mut buffer: Vec<$t> = Vec::with_capacity(self.config.elements);
                 unsafe {
                     buffer.set_len(self.config.elements); // Initialize the buffer without default values
@@ -289,8 +396,25 @@ impl_channel_array_trait!(f32, AF32);
 impl_channel_array_trait!(f64, AF64);
 
 impl ChannelTrait for ChannelArray<String> {
-    fn read(&self, _: &mut Cursor<&Vec<u8>>) -> IOResult<Value> {
-        Err(IOError::new(ErrorKind::Unsupported, "String array not supported"))
+    fn read(&self, cursor: &mut Cursor<&[u8]>) -> IOResult<Value> {
+        //Every element takes at least one byte, either padding or separator
+        check_remaining(cursor, self.config.elements)?;
+        let mut buffer = vec![String::new(); self.config.elements];
+        (self.reader)(cursor, &mut buffer)?;
+        Ok(Value::ASTR(buffer))
+    }
+
+    fn write(&self, cursor: &mut Cursor<&mut Vec<u8>>, value:&Value) -> IOResult<()> {
+        if let Value::ASTR(data) = value {
+            (self.writer)(cursor, data)?;
+            Ok(())
+        } else {
+            Err(IOError::new(ErrorKind::InvalidInput, "Channel write with invalid variant"))
+        }
+    }
+
+    fn config(&self) -> &ChannelConfig{
+        return &self.config
     }
 }
 
@@ -302,8 +426,12 @@ pub fn is_array(shape:&Option<Vec<u32>>) -> bool{
     }
 }
 
-pub fn new(name: String, typ:String, shape:Option<Vec<u32>>, little_endian:bool, compression:Compression, raw:bool) -> IOResult<Box<dyn ChannelTrait>> {
+pub fn new(name: String, typ:String, shape:Option<Vec<u32>>, little_endian:bool, compression:Compression, raw:bool) -> BsreadResult<Box<dyn ChannelTrait>> {
     let array = is_array(&shape);
+    if !is_known_type(&typ) {
+        log::debug!("Unknown data type {} for channel {}: keeping raw bytes", typ, name);
+        return Ok(Box::new(ChannelOpaque::new(name, typ, shape, little_endian, compression, raw)));
+    }
     if raw && (typ.as_str()!="string") {
         if little_endian || (element_size(&typ)==1){
             Ok(Box::new(ChannelRaw::new(name, typ, shape, little_endian, compression, READER_RAW,  WRITER_RAW )))
@@ -312,7 +440,7 @@ pub fn new(name: String, typ:String, shape:Option<Vec<u32>>, little_endian:bool,
                 2 =>  Ok(Box::new(ChannelRaw::new(name, typ, shape, little_endian, compression, READER_BRAW16,  WRITER_BRAW16 ))),
                 4 => Ok(Box::new(ChannelRaw::new(name, typ, shape, little_endian, compression, READER_BRAW32,  WRITER_BRAW32 ))),
                 8 => Ok(Box::new(ChannelRaw::new(name, typ, shape, little_endian, compression, READER_BRAW64,  WRITER_BRAW64 ))),
-                _ => Err(IOError::new(ErrorKind::Unsupported, "Unsupported data type"))
+                _ => Err(BsreadError::parsing(ParsingError::UnsupportedType(typ)).with_channel(&name))
             }
 
         }
@@ -320,8 +448,7 @@ pub fn new(name: String, typ:String, shape:Option<Vec<u32>>, little_endian:bool,
         if array {
             match typ.as_str() {
                 "bool" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, READER_ABOOL, WRITER_ABOOL))),
-                //"string" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, READER_ASTRING, WRITER_ASTRING))),
-                "string" => Ok(Box::new(ChannelScalar::new(name, typ, shape, little_endian, compression, READER_STRING, WRITER_STRING))),
+                "string" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, READER_ASTRING, WRITER_ASTRING))),
                 "int8" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, READER_AI8, WRITER_AI8))),
                 "uint8" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, READER_AU8, WRITER_AU8))),
                 "int16" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, if little_endian { READER_AI16 } else { READER_ABI16 }, if little_endian { WRITER_AI16 } else { WRITER_ABI16 }))),
@@ -332,7 +459,7 @@ pub fn new(name: String, typ:String, shape:Option<Vec<u32>>, little_endian:bool,
                 "uint64" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, if little_endian { READER_AU64 } else { READER_ABU64 }, if little_endian { WRITER_AU64 } else { WRITER_ABU64 }))),
                 "float32" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, if little_endian { READER_AF32 } else { READER_ABF32 }, if little_endian { WRITER_AF32 } else { WRITER_ABF32 }))),
                 "float64" => Ok(Box::new(ChannelArray::new(name, typ, shape, little_endian, compression, if little_endian { READER_AF64 } else { READER_ABF64 }, if little_endian { WRITER_AF64 } else { WRITER_ABF64 }))),
-                _ => Err(IOError::new(ErrorKind::Unsupported, "Unsupported data type"))
+                _ => Err(BsreadError::parsing(ParsingError::UnsupportedType(typ)).with_channel(&name))
             }
         } else {
             match typ.as_str() {
@@ -348,17 +475,21 @@ pub fn new(name: String, typ:String, shape:Option<Vec<u32>>, little_endian:bool,
                 "uint64" => Ok(Box::new(ChannelScalar::new(name, typ, shape, little_endian, compression, if little_endian { READER_U64 } else { READER_BU64 }, if little_endian { WRITER_U64 } else { WRITER_BU64 }))),
                 "float32" => Ok(Box::new(ChannelScalar::new(name, typ, shape, little_endian, compression, if little_endian { READER_F32 } else { READER_BF32 }, if little_endian { WRITER_F32 } else { WRITER_BF32 }))),
                 "float64" => Ok(Box::new(ChannelScalar::new(name, typ, shape, little_endian, compression, if little_endian { READER_F64 } else { READER_BF64 }, if little_endian { WRITER_F64 } else { WRITER_BF64 }))),
-                _ => Err(IOError::new(ErrorKind::Unsupported, "Unsupported data type"))
+                _ => Err(BsreadError::parsing(ParsingError::UnsupportedType(typ)).with_channel(&name))
             }
         }
     }
 }
-pub fn copy(channel:& Box<dyn ChannelTrait>) -> IOResult<Box<dyn ChannelTrait>> {
-    let name = channel.config().name().to_string();
-    let typ = channel.config().kind();
-    let shape = channel.config().shape();
-    let little_endian   =   channel.config().is_little_endian();
-    let compression = channel.config().compression();
-    let raw = channel.config().is_raw();
+pub fn copy(channel:& Box<dyn ChannelTrait>) -> BsreadResult<Box<dyn ChannelTrait>> {
+    from_config(channel.config())
+}
+
+pub fn from_config(config: &ChannelConfig) -> BsreadResult<Box<dyn ChannelTrait>> {
+    let name = config.name().to_string();
+    let typ = config.kind();
+    let shape = config.shape();
+    let little_endian   =   config.is_little_endian();
+    let compression = config.compression();
+    let raw = config.is_raw();
     new(name, typ, shape, little_endian, compression, raw)
 }
\ No newline at end of file
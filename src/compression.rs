@@ -29,14 +29,74 @@ unsafe extern "C" {
         block_size: usize) ->usize;
 }
 
+// Bitshuffle block sizes are multiples of 8 elements
+const BSHUF_BLOCKED_MULT: usize = 8;
+// Upper bound of the LZ4 compression ratio
+const LZ4_MAX_RATIO: usize = 255;
+
+fn bshuf_default_block_size(elem_size: usize) -> usize {
+    let target_block_size = 8192;
+    let minimum_block_size = 128;
+    let block_size = target_block_size / elem_size;
+    let block_size = (block_size / BSHUF_BLOCKED_MULT) * BSHUF_BLOCKED_MULT;
+    cmp::max(block_size, minimum_block_size)
+}
+
+//The C library trusts the block headers, so they are checked against the input length before the call.
+fn bshuf_check_blocks(blob: &[u8], elements: usize, elem_size: usize, block_size: usize) -> Result<(), String> {
+    let mut offset = 0usize;
+    let mut check_block = |block_elements: usize| -> Result<(), String> {
+        let header = blob.get(offset..offset + 4).ok_or("Truncated block header")?;
+        let nbytes = i32::from_be_bytes([header[0], header[1], header[2], header[3]]);
+        if nbytes < 0 {
+            return Err(format!("Invalid block size {}", nbytes));
+        }
+        let nbytes = nbytes as usize;
+        if blob.len() - offset - 4 < nbytes {
+            return Err(format!("Truncated block: expected {} bytes, got {}", nbytes, blob.len() - offset - 4));
+        }
+        if block_elements * elem_size > nbytes.saturating_mul(LZ4_MAX_RATIO) + 16 {
+            return Err(format!("Invalid block: {} bytes cannot hold {} elements", nbytes, block_elements));
+        }
+        offset += 4 + nbytes;
+        Ok(())
+    };
+    for _ in 0..elements / block_size {
+        check_block(block_size)?;
+    }
+    let last_block_size = elements % block_size;
+    let last_block_size = last_block_size - last_block_size % BSHUF_BLOCKED_MULT;
+    if last_block_size > 0 {
+        check_block(last_block_size)?;
+    }
+    let leftover_bytes = (elements % BSHUF_BLOCKED_MULT) * elem_size;
+    if blob.len() - offset < leftover_bytes {
+        return Err(format!("Truncated data: expected {} leftover bytes, got {}", leftover_bytes, blob.len() - offset));
+    }
+    Ok(())
+}
+
 fn bshuf_untrans_bit_elem(input: &[u8],  elem_size: usize, ) -> Result<Vec<u8>, String> {
-    let elem_size = elem_size;
+    if elem_size == 0 {
+        return Err("Invalid element size 0".to_string());
+    }
+    if input.len() < 12 {
+        return Err(format!("Invalid header size {}", input.len()));
+    }
     let mut c =  Cursor::new(input);
-    let size_out =   c.read_u64::<BigEndian>().unwrap() as usize;
+    let size_out =   c.read_u64::<BigEndian>().map_err(|e| e.to_string())?;
+    let size_out = usize::try_from(size_out).map_err(|_| format!("Invalid size {}", size_out))?;
     let elements = size_out/elem_size;
-    let block_size =c.read_u32::<BigEndian>().unwrap();
-    let block_size = block_size / (elem_size as u32);
+    let block_size =c.read_u32::<BigEndian>().map_err(|e| e.to_string())? as usize;
+    let block_size = match block_size / elem_size {
+        0 => bshuf_default_block_size(elem_size),
+        n => n
+    };
+    if block_size % BSHUF_BLOCKED_MULT != 0 {
+        return Err(format!("Invalid block size {}", block_size));
+    }
     let blob = &input[12..];
+    bshuf_check_blocks(blob, elements, elem_size, block_size)?;
     let mut output = vec![0u8; size_out];
 
     let ret  = unsafe {
@@ -45,7 +105,7 @@ fn bshuf_untrans_bit_elem(input: &[u8],  elem_size: usize, ) -> Result<Vec<u8>,
             output.as_mut_ptr(),
             elements,
             elem_size,
-            block_size as usize,
+            block_size,
         )
     };
 
@@ -59,14 +119,8 @@ fn bshuf_untrans_bit_elem(input: &[u8],  elem_size: usize, ) -> Result<Vec<u8>,
 
 
 fn  bshuf_trans_bit_elem(input: &[u8],  elem_size: usize, ) -> Result<Vec<u8>, String> {
-    let elem_size = elem_size;
     let blob_in = &input[0..];
-    let target_block_size = 8192;
-    let minimum_block_size = 128;
-    let block_size_multiplier = 8;
-    let block_size = target_block_size / elem_size;
-    let block_size = (block_size / block_size_multiplier) * block_size_multiplier;
-    let block_size = cmp::max(block_size, minimum_block_size);
+    let block_size = bshuf_default_block_size(elem_size);
     let elements= input.len() / elem_size;
     let output_bound = unsafe {
         bshuf_compress_lz4_bound(elements, elem_size, block_size)
@@ -97,33 +151,52 @@ fn  bshuf_trans_bit_elem(input: &[u8],  elem_size: usize, ) -> Result<Vec<u8>, S
 
 
 
-pub fn decompress_bitshuffle_lz4(compressed_data: &[u8], element_size: usize) -> IOResult<Vec<u8>> {
+pub fn decompress_bitshuffle_lz4(compressed_data: &[u8], element_size: usize) -> BsreadResult<Vec<u8>> {
     match bshuf_untrans_bit_elem(&compressed_data, element_size) {
         Ok(out) => {Ok(out)}
-        Err(e) => {Err(IOError::new(ErrorKind::InvalidInput, e.as_str()))}
+        Err(e) => {Err(BsreadError::decompression(e))}
     }
 }
 
-pub fn decompress_lz4(compressed_data: &[u8], little_endian:bool) -> IOResult<Vec<u8>> {
+pub fn decompress_lz4(compressed_data: &[u8], little_endian:bool) -> BsreadResult<Vec<u8>> {
+    if compressed_data.len() < 4 {
+        return Err(BsreadError::decompression(format!("Invalid header size {}", compressed_data.len())));
+    }
     let size = if little_endian {
         (compressed_data[0] as i32) | (compressed_data[1] as i32) << 8 | (compressed_data[2] as i32) << 16 | (compressed_data[3] as i32) << 24
     } else {
         (compressed_data[3] as i32) | (compressed_data[2] as i32) << 8 | (compressed_data[1] as i32) << 16 | (compressed_data[0] as i32) << 24
     };
     let blob: &[u8] = &compressed_data[4..];
-    let output = lz4_decompress(blob, Some(size))?;
+    if size < 0 || (size as usize) > blob.len().saturating_mul(LZ4_MAX_RATIO) + 16 {
+        return Err(BsreadError::decompression(format!("Invalid size {} for {} compressed bytes", size, blob.len())));
+    }
+    let output = lz4_decompress(blob, Some(size))
+        .map_err(|e| BsreadError::decompression(e.to_string()))?;
     Ok(output)
 }
 
 
-pub fn compress_bitshuffle_lz4(data: &[u8], element_size: usize) -> IOResult<Vec<u8>> {
+/// Size of a channel value after decompression, read from the compression header.
+pub fn decompressed_size(data: &[u8], compression: Compression, little_endian: bool) -> Option<usize> {
+    match compression {
+        Compression::None => Some(data.len()),
+        Compression::BitshuffleLz4 => data.get(..8).map(|header| u64::from_be_bytes(header.try_into().unwrap()) as usize),
+        Compression::Lz4 => data.get(..4).map(|header| {
+            let size = if little_endian { u32::from_le_bytes(header.try_into().unwrap()) } else { u32::from_be_bytes(header.try_into().unwrap()) };
+            size as usize
+        }),
+    }
+}
+
+pub fn compress_bitshuffle_lz4(data: &[u8], element_size: usize) -> BsreadResult<Vec<u8>> {
     match bshuf_trans_bit_elem(data, element_size){
         Ok(out) => {Ok(out)}
-        Err(e) => {Err(IOError::new(ErrorKind::InvalidInput, e.as_str()))}
+        Err(e) => {Err(BsreadError::validation(ValidationError::Encoding(e)))}
     }
 }
 
-pub fn compress_lz4(data: &[u8], little_endian:bool) -> IOResult<Vec<u8>> {
+pub fn compress_lz4(data: &[u8], little_endian:bool) -> BsreadResult<Vec<u8>> {
     let size = data.len();
     let header  = if little_endian {
         [size as u8,  (size >> 8) as u8, (size >> 16) as u8, (size >> 24) as u8]
@@ -131,7 +204,8 @@ pub fn compress_lz4(data: &[u8], little_endian:bool) -> IOResult<Vec<u8>> {
         [(size >> 24)  as u8,  (size >> 16) as u8, (size >> 8) as u8, size as u8]
     };
 
-    let blob = lz4_compress(data, Some(CompressionMode::DEFAULT), false)?;
+    let blob = lz4_compress(data, Some(CompressionMode::DEFAULT), false)
+        .map_err(|e| BsreadError::validation(ValidationError::Encoding(e.to_string())))?;
 
     let mut output = Vec::with_capacity(4 + blob.len());
     output.extend_from_slice(&header);
@@ -155,6 +155,16 @@ pub fn print_stats_rec(rec: &Receiver) -> () {
             }
         }
     }
+    for (endpoint, throughput) in rec.throughput() {
+        print_throughput(&endpoint, &throughput);
+    }
+}
+
+fn print_throughput(endpoint: &str, throughput: &Throughput) {
+    let rates = throughput.rates();
+    println!("\tEndpoint Throughput: {}", endpoint);
+    println!("\t\tMessages: {} Frames: {} Bytes: {} Decompressed: {}", throughput.messages, throughput.frames, throughput.compressed_bytes, throughput.decompressed_bytes);
+    println!("\t\tRates: {:.1} msg/s {:.3} MB/s Compression ratio: {:.2}", rates.messages_per_second, rates.megabytes_per_second(), rates.compression_ratio().unwrap_or(1.0));
 }
 
 
@@ -178,6 +188,9 @@ pub fn print_stats_pool(pool: &Pool) -> () {
             }
         }
     }
+    for (endpoint, throughput) in pool.throughput() {
+        print_throughput(&endpoint, &throughput);
+    }
 
 
 
@@ -240,7 +253,7 @@ fn create_message(v:u64, s:usize, compression:Option<Compression>, flawed:bool,
         false => {ID_SIMULATED}
     };
 
-    Message::new_from_channel_map(id,TIMESTAMP_NOW, channels, data)
+    Ok(Message::new_from_channel_map(id,TIMESTAMP_NOW, channels, data)?)
 }
 
 pub fn start_sender(bsread: Option<&Arc<Bsread>>, transport:Transport, socket_type:SocketType, interval_ms:u64, block:Option<bool>, compression:Option<Compression>, timeout:Option<u64>, flawed:bool) -> IOResult<()> {
@@ -331,7 +344,8 @@ pub fn running_senders() -> usize {
 }
 
 pub fn assert_message_contents_ok(msg:&Message){
-    let n = msg.id().to_u32().unwrap() -1;
+    //Test values wrap around, as in create_test_values
+    let n = (msg.id().to_u32().unwrap() -1) % 100;
     let array_size = MESSAGE_ARRAY_SIZE;
 
     if msg.is_raw() {
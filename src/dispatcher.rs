@@ -44,12 +44,31 @@ struct ChannelValidation {
 
 pub struct DispatcherStream {
     endpoint: String,
+    id_step: u64,
 }
 
 impl DispatcherStream{
     pub fn endpoint(&self) -> &str {
         self.endpoint.as_str()
     }
+
+    /// Expected increment of the pulse IDs of the stream, to be set to the receiver for gap detection.
+    pub fn id_step(&self) -> u64 {
+        self.id_step
+    }
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+//Greatest common divisor of the channel modulos and of the differences of the channel offsets
+fn id_step(channels: &[ChannelDescription]) -> u64 {
+    let offset = |channel: &ChannelDescription| channel.offset.unwrap_or(0) as u64;
+    let first_offset = channels.first().map(offset).unwrap_or(0);
+    channels.iter()
+        .fold(0, |step, channel| gcd(gcd(step, channel.modulo.unwrap_or(1) as u64), offset(channel).abs_diff(first_offset)))
+        .max(1)
 }
 
 impl Drop for DispatcherStream {
@@ -62,7 +81,7 @@ impl Drop for DispatcherStream {
 }
 
 pub fn request_stream(channels: Vec<ChannelDescription>, stream_type: Option<String>, inconsistency_resolution: Option<String>,
-                      verify: bool,disable_compression: bool,) -> IOResult<DispatcherStream> {
+                      verify: bool,disable_compression: bool,) -> BsreadResult<DispatcherStream> {
     let stream_type = stream_type.unwrap_or_else(|| "pub_sub".to_string());
     let inconsistency_resolution = inconsistency_resolution.unwrap_or_else(|| {
         if verify {
@@ -72,6 +91,7 @@ pub fn request_stream(channels: Vec<ChannelDescription>, stream_type: Option<Str
         }
     });
 
+    let id_step = id_step(&channels);
     let mut config = Config {
         channels,
         stream_type,
@@ -97,24 +117,27 @@ pub fn request_stream(channels: Vec<ChannelDescription>, stream_type: Option<Str
         .headers(headers)
         .json(&config)
         .send()
-        .map_err(|e: ReqwestError|IOError::new(ErrorKind::ConnectionRefused, e.to_string().as_str()))?;
+        .map_err(|e: ReqwestError|BsreadError::dispatcher(e.to_string(), e.status().map(|s| s.as_u16())))?;
 
     if !response.status().is_success() {
+        let status = response.status().as_u16();
         let error_msg = match response.text(){
             Ok(msg) => { format!("Unable to request stream {:?}: {}", config, msg)}
             Err(err) => {format!("Error requesting stream {:?}: {}", config, err.to_string())}
         };
-        return Err( IOError::new (ErrorKind::Other, error_msg.as_str()));
+        return Err(BsreadError::dispatcher(error_msg, Some(status)));
     }
 
-    let json: serde_json::Value = response.json().map_err(|e: ReqwestError|IOError::new(ErrorKind::InvalidData, e.to_string().as_str()))?;
-    let endpoint = json["stream"].as_str().unwrap().to_string();
+    let json: serde_json::Value = response.json().map_err(|e: ReqwestError|BsreadError::dispatcher(e.to_string(), None))?;
+    let endpoint = json["stream"].as_str()
+        .ok_or_else(|| BsreadError::dispatcher(format!("Invalid stream response: {}", json), None))?
+        .to_string();
     log::info!("Created stream : {}", endpoint);
-    Ok(DispatcherStream{endpoint})
+    Ok(DispatcherStream{endpoint, id_step})
 }
 
 
-fn remove_stream(stream: &str) -> IOResult<()> {
+fn remove_stream(stream: &str) -> BsreadResult<()> {
     log::info!("Removing stream: {}", stream);
     let client = Client::new();
     let url = format!("{}/stream", BASE_URL);
@@ -127,13 +150,15 @@ fn remove_stream(stream: &str) -> IOResult<()> {
         .headers(headers)
         .body(stream.to_string())  // Send the stream as the body
         .send()
-        .map_err(|e: ReqwestError| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        .map_err(|e: ReqwestError| BsreadError::dispatcher(e.to_string(), e.status().map(|s| s.as_u16())))?;
 
     if !response.status().is_success() {
-        match response.text(){
+        let status = response.status().as_u16();
+        let error_msg = match response.text(){
             Ok(msg) => { format!("Unable to delete stream {}: {}", stream, msg)}
             Err(err) => {format!("Error deleting stream {}: {}", stream, err.to_string())}
         };
+        return Err(BsreadError::dispatcher(error_msg, Some(status)));
     }
     Ok(())
 }
@@ -0,0 +1,305 @@
+use std::fmt;
+use std::io;
+use std::io::ErrorKind;
+
+pub type BsreadResult<T> = Result<T, BsreadError>;
+
+/// Where an error happened: filled in as the error travels up the decode/send path.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub endpoint: Option<String>,
+    pub channel: Option<String>,
+    pub pulse_id: Option<u64>,
+}
+
+impl ErrorContext {
+    pub fn is_empty(&self) -> bool {
+        self.endpoint.is_none() && self.channel.is_none() && self.pulse_id.is_none()
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut fields = Vec::new();
+        if let Some(endpoint) = &self.endpoint {
+            fields.push(format!("endpoint={}", endpoint));
+        }
+        if let Some(channel) = &self.channel {
+            fields.push(format!("channel={}", channel));
+        }
+        if let Some(pulse_id) = &self.pulse_id {
+            fields.push(format!("pulse_id={}", pulse_id));
+        }
+        write!(f, "[{}]", fields.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParsingError {
+    InvalidMessage(String),
+    InvalidJson(String),
+    MissingField(&'static str),
+    InvalidField(&'static str),
+    InvalidHtype(String),
+    InvalidCompression(String),
+    UnsupportedType(String),
+    FrameCount { expected: usize, actual: usize },
+    ShapeMismatch { expected: usize, actual: usize },
+    InvalidData(String),
+}
+
+impl fmt::Display for ParsingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParsingError::InvalidMessage(s) => write!(f, "invalid message: {}", s),
+            ParsingError::InvalidJson(s) => write!(f, "invalid json: {}", s),
+            ParsingError::MissingField(field) => write!(f, "'{}' missing", field),
+            ParsingError::InvalidField(field) => write!(f, "'{}' has an invalid format", field),
+            ParsingError::InvalidHtype(htype) => write!(f, "invalid htype: {}", htype),
+            ParsingError::InvalidCompression(s) => write!(f, "invalid compression: {}", s),
+            ParsingError::UnsupportedType(typ) => write!(f, "unsupported data type: {}", typ),
+            ParsingError::FrameCount { expected, actual } => write!(f, "invalid number of frames: expected {}, got {}", expected, actual),
+            ParsingError::ShapeMismatch { expected, actual } => write!(f, "shape mismatch: expected {} bytes, got {}", expected, actual),
+            ParsingError::InvalidData(s) => write!(f, "invalid data: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    NonPositiveId,
+    OutOfRangeId,
+    DecreasingId { last_id: u64 },
+    RepeatedId,
+    EmptyChannelData,
+    ChannelCount { expected: usize, actual: usize },
+    Encoding(String),
+    InvalidPattern(String),
+    InvalidChannel(String),
+    MissingChannel,
+    DataHeaderChanged,
+    EmptyMessageList,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::NonPositiveId => write!(f, "non positive ID"),
+            ValidationError::OutOfRangeId => write!(f, "out of range ID"),
+            ValidationError::DecreasingId { last_id } => write!(f, "decreasing ID (last ID {})", last_id),
+            ValidationError::RepeatedId => write!(f, "repeated ID"),
+            ValidationError::EmptyChannelData => write!(f, "empty channel data list"),
+            ValidationError::ChannelCount { expected, actual } => write!(f, "invalid size of channel data list: expected {}, got {}", expected, actual),
+            ValidationError::Encoding(s) => write!(f, "encoding error: {}", s),
+            ValidationError::InvalidPattern(s) => write!(f, "invalid pattern: {}", s),
+            ValidationError::InvalidChannel(s) => write!(f, "invalid channel: {}", s),
+            ValidationError::MissingChannel => write!(f, "channel not found or without data"),
+            ValidationError::DataHeaderChanged => write!(f, "messages have different data headers"),
+            ValidationError::EmptyMessageList => write!(f, "empty message list"),
+        }
+    }
+}
+
+/// Crate-wide error type. Converts into `std::io::Error` so `IOResult` callers keep working.
+#[derive(Debug)]
+pub enum BsreadError {
+    Parsing { reason: ParsingError, context: ErrorContext },
+    Decompression { reason: String, context: ErrorContext },
+    Validation { reason: ValidationError, context: ErrorContext },
+    Transport { source: io::Error, context: ErrorContext },
+    Conversion { expected: &'static str, actual: String, context: ErrorContext },
+    Dispatcher { reason: String, status: Option<u16> },
+    Io(io::Error),
+}
+
+impl Clone for BsreadError {
+    fn clone(&self) -> Self {
+        //io::Error is not Clone: keep its kind and message
+        let clone_io = |e: &io::Error| io::Error::new(e.kind(), e.to_string());
+        match self {
+            BsreadError::Parsing { reason, context } => BsreadError::Parsing { reason: reason.clone(), context: context.clone() },
+            BsreadError::Decompression { reason, context } => BsreadError::Decompression { reason: reason.clone(), context: context.clone() },
+            BsreadError::Validation { reason, context } => BsreadError::Validation { reason: reason.clone(), context: context.clone() },
+            BsreadError::Transport { source, context } => BsreadError::Transport { source: clone_io(source), context: context.clone() },
+            BsreadError::Conversion { expected, actual, context } => BsreadError::Conversion { expected, actual: actual.clone(), context: context.clone() },
+            BsreadError::Dispatcher { reason, status } => BsreadError::Dispatcher { reason: reason.clone(), status: *status },
+            BsreadError::Io(e) => BsreadError::Io(clone_io(e)),
+        }
+    }
+}
+
+impl BsreadError {
+    pub fn parsing(reason: ParsingError) -> Self {
+        BsreadError::Parsing { reason, context: ErrorContext::default() }
+    }
+
+    pub fn decompression(reason: impl Into<String>) -> Self {
+        BsreadError::Decompression { reason: reason.into(), context: ErrorContext::default() }
+    }
+
+    pub fn validation(reason: ValidationError) -> Self {
+        BsreadError::Validation { reason, context: ErrorContext::default() }
+    }
+
+    pub fn transport(source: impl Into<io::Error>) -> Self {
+        BsreadError::Transport { source: source.into(), context: ErrorContext::default() }
+    }
+
+    pub fn conversion(expected: &'static str, actual: impl Into<String>) -> Self {
+        BsreadError::Conversion { expected, actual: actual.into(), context: ErrorContext::default() }
+    }
+
+    pub fn dispatcher(reason: impl Into<String>, status: Option<u16>) -> Self {
+        BsreadError::Dispatcher { reason: reason.into(), status }
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            BsreadError::Parsing { context, .. }
+            | BsreadError::Decompression { context, .. }
+            | BsreadError::Validation { context, .. }
+            | BsreadError::Transport { context, .. }
+            | BsreadError::Conversion { context, .. } => Some(context),
+            BsreadError::Dispatcher { .. } | BsreadError::Io(_) => None,
+        }
+    }
+
+    fn context_mut(&mut self) -> Option<&mut ErrorContext> {
+        match self {
+            BsreadError::Parsing { context, .. }
+            | BsreadError::Decompression { context, .. }
+            | BsreadError::Validation { context, .. }
+            | BsreadError::Transport { context, .. }
+            | BsreadError::Conversion { context, .. } => Some(context),
+            BsreadError::Dispatcher { .. } | BsreadError::Io(_) => None,
+        }
+    }
+
+    /// Sets the endpoint, unless already set closer to the origin of the error.
+    pub fn with_endpoint(mut self, endpoint: &Option<String>) -> Self {
+        if let Some(context) = self.context_mut() {
+            if context.endpoint.is_none() {
+                context.endpoint = endpoint.clone();
+            }
+        }
+        self
+    }
+
+    pub fn with_channel(mut self, channel: &str) -> Self {
+        if let Some(context) = self.context_mut() {
+            if context.channel.is_none() {
+                context.channel = Some(channel.to_string());
+            }
+        }
+        self
+    }
+
+    pub fn with_pulse_id(mut self, pulse_id: u64) -> Self {
+        if let Some(context) = self.context_mut() {
+            if context.pulse_id.is_none() {
+                context.pulse_id = Some(pulse_id);
+            }
+        }
+        self
+    }
+
+    pub fn is_parsing(&self) -> bool {
+        matches!(self, BsreadError::Parsing { .. })
+    }
+
+    pub fn is_decompression(&self) -> bool {
+        matches!(self, BsreadError::Decompression { .. })
+    }
+
+    pub fn is_validation(&self) -> bool {
+        matches!(self, BsreadError::Validation { .. })
+    }
+
+    pub fn is_transport(&self) -> bool {
+        matches!(self, BsreadError::Transport { .. })
+    }
+
+    pub fn is_conversion(&self) -> bool {
+        matches!(self, BsreadError::Conversion { .. })
+    }
+
+    /// The std::io::ErrorKind this error maps to when converted to std::io::Error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            BsreadError::Parsing { .. } => ErrorKind::InvalidData,
+            BsreadError::Decompression { .. } => ErrorKind::InvalidData,
+            BsreadError::Validation { .. } => ErrorKind::InvalidData,
+            BsreadError::Transport { source, .. } => source.kind(),
+            BsreadError::Conversion { .. } => ErrorKind::InvalidData,
+            BsreadError::Dispatcher { .. } => ErrorKind::Other,
+            BsreadError::Io(e) => e.kind(),
+        }
+    }
+}
+
+impl fmt::Display for BsreadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BsreadError::Parsing { reason, .. } => write!(f, "Parsing error: {}", reason)?,
+            BsreadError::Decompression { reason, .. } => write!(f, "Decompression error: {}", reason)?,
+            BsreadError::Validation { reason, .. } => write!(f, "Validation error: {}", reason)?,
+            BsreadError::Transport { source, .. } => write!(f, "Transport error: {}", source)?,
+            BsreadError::Conversion { expected, actual, .. } => write!(f, "Conversion error: cannot convert {} to {}", actual, expected)?,
+            BsreadError::Dispatcher { reason, status } => match status {
+                Some(status) => write!(f, "Dispatcher error ({}): {}", status, reason)?,
+                None => write!(f, "Dispatcher error: {}", reason)?,
+            },
+            BsreadError::Io(e) => write!(f, "{}", e)?,
+        }
+        if let Some(context) = self.context() {
+            if !context.is_empty() {
+                write!(f, " {}", context)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for BsreadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BsreadError::Transport { source, .. } => Some(source),
+            BsreadError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for BsreadError {
+    fn from(e: io::Error) -> Self {
+        //Unwrap errors that were converted to io::Error before
+        if e.get_ref().is_some_and(|inner| inner.is::<BsreadError>()) {
+            if let Some(Ok(inner)) = e.into_inner().map(|inner| inner.downcast::<BsreadError>()) {
+                return *inner;
+            }
+            return BsreadError::Io(io::Error::other("invalid error"));
+        }
+        BsreadError::Io(e)
+    }
+}
+
+impl From<zmq::Error> for BsreadError {
+    fn from(e: zmq::Error) -> Self {
+        BsreadError::transport(e)
+    }
+}
+
+impl From<serde_json::Error> for BsreadError {
+    fn from(e: serde_json::Error) -> Self {
+        BsreadError::parsing(ParsingError::InvalidJson(e.to_string()))
+    }
+}
+
+impl From<BsreadError> for io::Error {
+    fn from(e: BsreadError) -> Self {
+        match e {
+            BsreadError::Io(e) => e,
+            e => io::Error::new(e.kind(), e),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::*;
+use regex::Regex;
+use std::collections::HashSet;
+
+/// Selection of the channels to be decoded by a Receiver, given by exact names, glob patterns
+/// (`*` and `?` wildcards) or regular expressions. A channel is selected if it matches any of them.
+#[derive(Debug, Clone, Default)]
+pub struct ChannelFilter {
+    names: HashSet<String>,
+    patterns: Vec<Regex>,
+}
+
+impl ChannelFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_names(names: &[&str]) -> Self {
+        Self { names: names.iter().map(|name| name.to_string()).collect(), patterns: Vec::new() }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.names.insert(name.to_string());
+        self
+    }
+
+    pub fn glob(self, pattern: &str) -> BsreadResult<Self> {
+        let mut regex = String::from("^");
+        for c in pattern.chars() {
+            match c {
+                '*' => regex.push_str(".*"),
+                '?' => regex.push('.'),
+                c => regex.push_str(&regex::escape(&c.to_string())),
+            }
+        }
+        regex.push('$');
+        self.regex(&regex)
+    }
+
+    pub fn regex(mut self, pattern: &str) -> BsreadResult<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| BsreadError::validation(ValidationError::InvalidPattern(e.to_string())))?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.patterns.is_empty()
+    }
+
+    pub fn matches(&self, channel_name: &str) -> bool {
+        self.names.contains(channel_name) || self.patterns.iter().any(|regex| regex.is_match(channel_name))
+    }
+
+    /// Indexes of the selected channels.
+    pub fn select(&self, channels: &[Box<dyn ChannelTrait>]) -> Vec<usize> {
+        channels.iter().enumerate()
+            .filter(|(_, channel)| self.matches(channel.config().name_str()))
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::BTreeMap;
+
+// Buckets: one per value below 16, then 8 per power of two. Percentiles are the bucket midpoints,
+// with a relative error below 6.25%, clamped to the exact minimum and maximum.
+
+const LINEAR_BUCKETS: u64 = 16;
+const SUB_BUCKET_BITS: u32 = 3;
+const SUB_BUCKETS: u64 = 1 << SUB_BUCKET_BITS;
+const FIRST_EXPONENT: u32 = 4;
+
+fn bucket(value: i64) -> i32 {
+    let magnitude = value.unsigned_abs();
+    let index = if magnitude < LINEAR_BUCKETS {
+        magnitude
+    } else {
+        let exponent = 63 - magnitude.leading_zeros();
+        let sub_bucket = (magnitude >> (exponent - SUB_BUCKET_BITS)) & (SUB_BUCKETS - 1);
+        LINEAR_BUCKETS + (exponent - FIRST_EXPONENT) as u64 * SUB_BUCKETS + sub_bucket
+    } as i32;
+    if value < 0 { -index } else { index }
+}
+
+fn bucket_value(index: i32) -> i64 {
+    let index_magnitude = index.unsigned_abs() as u64;
+    let magnitude = if index_magnitude < LINEAR_BUCKETS {
+        index_magnitude
+    } else {
+        let exponent = ((index_magnitude - LINEAR_BUCKETS) / SUB_BUCKETS) as u32 + FIRST_EXPONENT;
+        let sub_bucket = (index_magnitude - LINEAR_BUCKETS) % SUB_BUCKETS;
+        let width = 1u64 << (exponent - SUB_BUCKET_BITS);
+        (SUB_BUCKETS + sub_bucket) * width + width / 2
+    };
+    let magnitude = magnitude.min(i64::MAX as u64) as i64;
+    if index < 0 { -magnitude } else { magnitude }
+}
+
+/// Histogram of signed integer values with logarithmic buckets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    buckets: BTreeMap<i32, u64>,
+    count: u64,
+    sum: i128,
+    min: i64,
+    max: i64,
+}
+
+impl Histogram {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, value: i64) {
+        *self.buckets.entry(bucket(value)).or_insert(0) += 1;
+        if self.count == 0 {
+            self.min = value;
+            self.max = value;
+        } else {
+            self.min = self.min.min(value);
+            self.max = self.max.max(value);
+        }
+        self.count += 1;
+        self.sum += value as i128;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<i64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<i64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Value below which the given percentage (0 to 100) of the recorded values are.
+    pub fn percentile(&self, percentile: f64) -> Option<i64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0 * self.count as f64).ceil() as u64).max(1);
+        //The extremes are exact
+        if rank == 1 {
+            return Some(self.min);
+        } else if rank >= self.count {
+            return Some(self.max);
+        }
+        let mut accumulated = 0;
+        for (index, count) in self.buckets.iter() {
+            accumulated += count;
+            if accumulated >= rank {
+                return Some(bucket_value(*index).clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+/// Timing statistics of an endpoint, in microseconds.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimingStats {
+    /// Receive time minus the global timestamp of the messages.
+    pub latency: Histogram,
+    /// Channel timestamps minus the global timestamp of their message.
+    pub skew: Histogram,
+}
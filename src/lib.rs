@@ -1,14 +1,32 @@
 extern crate core;
 
 pub use crate::bsread::{Bsread};
-pub use crate::channel::{ChannelConfig, ChannelArray, ChannelScalar, ChannelTrait};
+pub use crate::channel::{ChannelConfig, ChannelArray, ChannelScalar, ChannelOpaque, ChannelTrait};
 pub use crate::value::{Value};
+pub use crate::buffer::{SharedBuffer, Element, ElementType};
+pub use crate::filter::ChannelFilter;
+pub use crate::builder::MessageBuilder;
+pub use crate::record::BsreadRecord;
+pub use crate::capture::{Recorder, CaptureReader, CapturedMessage};
+pub use crate::replay::{Replayer, ReplayTiming};
+#[cfg(feature = "derive")]
+pub use bsread_derive::BsreadRecord;
 pub use crate::message::{ChannelData, Message, DataHeaderInfo, ID_SIMULATED, TIMESTAMP_NOW};
 pub use crate::sockets::{Transport,EndpointState, EndpointDiag, EndpointEvent, SocketConfig};
-pub use crate::utils::{init_id_t0, init_sf_id_t0};
+pub use crate::utils::{init_id_t0, init_sf_id_t0, OverflowPolicy};
 pub use crate::receiver::{Receiver, DeliveryMode, ConnectionMode, ReceivedMessage, ForwarderConfig};
 pub use crate::pool::Pool;
+#[cfg(feature = "async")]
+pub use crate::stream::ReceiverStream;
+pub use crate::synchronizer::{Synchronizer, SynchronizedEvent};
+pub use crate::histogram::{Histogram, TimingStats};
+pub use crate::throughput::{Throughput, ChannelThroughput, Rates};
 pub use crate::sender::Sender;
+#[cfg(feature = "async")]
+pub use crate::async_sender::AsyncSender;
+#[cfg(feature = "metrics")]
+pub use crate::metrics::MetricsExporter;
+pub use crate::error::{BsreadError, BsreadResult, ErrorContext, ParsingError, ValidationError};
 pub use zmq::SocketType;
 pub use std::io::Result as IOResult;
 pub use std::io::Error as IOError;
@@ -34,13 +52,13 @@ pub enum Compression {
 }
 
 impl FromStr for Compression {
-    type Err = IOError;
-    fn from_str(s: &str) -> IOResult<Self> {
+    type Err = BsreadError;
+    fn from_str(s: &str) -> BsreadResult<Self> {
         match s {
             "none" => Ok(Compression::None),
             "bitshuffle_lz4" => Ok(Compression::BitshuffleLz4),
             "lz4" => Ok(Compression::Lz4),
-            _ => Err(IOError::new(std::io::ErrorKind::InvalidInput, "invalid compression")),
+            _ => Err(BsreadError::parsing(ParsingError::InvalidCompression(s.to_string()))),
         }
     }
 }
@@ -56,12 +74,36 @@ impl Display for Compression {
     }
 }
 
+impl serde::Serialize for Compression {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Compression {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Compression::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 //Result<(), Box<dyn std::error::Error>>
 #[cfg(test)]
 mod tests;
 pub mod bsread;
+pub mod error;
 pub mod channel;
 pub mod message;
+pub mod buffer;
+pub mod filter;
+pub mod builder;
+pub mod record;
+pub mod capture;
+pub mod replay;
+#[cfg(feature = "ndarray")]
+pub mod array;
+#[cfg(feature = "arrow")]
+pub mod arrow;
 pub mod reader;
 pub mod writer;
 pub mod receiver;
@@ -71,9 +113,18 @@ pub mod convert;
 pub mod value;
 pub mod debug;
 pub mod pool;
+pub mod synchronizer;
+pub mod histogram;
+pub mod throughput;
 #[cfg(feature = "dispatcher")]
 pub mod dispatcher;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "async")]
+pub mod stream;
 pub mod sender;
+#[cfg(feature = "async")]
+pub mod async_sender;
 
 pub mod sockets;
 
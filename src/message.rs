@@ -6,44 +6,44 @@ use crate::compression::*;
 use crate::utils::LimitedHashMap;
 use std::collections::HashMap;
 use std::io::{Cursor};
+use std::sync::{Arc, OnceLock};
+use crate::buffer::{SharedBuffer, ElementType, Element};
 use std::thread;
 use indexmap::IndexMap;
 use serde_json::Error as JSonError;
 use serde_json::Value as JsonValue;
 use serde_json::Map as JsonMap;
 use serde_json::Number as JsonNumber;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 pub const ID_SIMULATED:u64 = 0;
 pub const TIMESTAMP_NOW:(u64,u64) = (0,0);
 
-// ErrorKind::NotSeekable is used internally as a marker for decompression failures
-pub const DECOMPRESSION_ERROR:ErrorKind = ErrorKind::NotSeekable;
-
-fn decode_json(bytes: &Vec<u8>) -> Result<HashMap<String, JsonValue>, JSonError> {
+fn decode_json(bytes: &[u8]) -> Result<HashMap<String, JsonValue>, JSonError> {
     serde_json::from_slice(&bytes)
 }
 
-fn convert_shape_val_to_vec(opt_val: Option<&JsonValue>) -> Option<Vec<u32>> {
-    opt_val.and_then(|val| {
-        if let JsonValue::Array(arr) = val {
+fn convert_shape_val_to_vec(opt_val: Option<&JsonValue>) -> BsreadResult<Option<Vec<u32>>> {
+    match opt_val {
+        Some(JsonValue::Array(arr)) => {
             // Try converting all elements to integers
             let vec: Option<Vec<u32>> = arr
                 .into_iter()
-                .map(|item| item.as_i64().and_then(|n| Some(n as u32)))
+                .map(|item| item.as_u64().and_then(|n| u32::try_from(n).ok()))
                 .collect();
+            let vec = vec.ok_or(BsreadError::parsing(ParsingError::InvalidField("shape")))?;
 
             // Return None if vec is empty, otherwise Some(vec)
-            vec.filter(|v| !v.is_empty())
-        } else {
-            None
+            Ok(Some(vec).filter(|v| !v.is_empty()))
         }
-    })
+        _ => Ok(None)
+    }
 }
 
-fn parse_channel(channel_data: &JsonMap<String, JsonValue>, raw: bool) -> IOResult<Box<dyn ChannelTrait>> {
+fn parse_channel(channel_data: &JsonMap<String, JsonValue>, raw: bool) -> BsreadResult<Box<dyn ChannelTrait>> {
     let name = channel_data.get("name")
         .and_then(|v| v.as_str())
-        .ok_or(IOError::new(ErrorKind::InvalidInput,"Invalid format: 'name' missing or not a string"))?
+        .ok_or(BsreadError::parsing(ParsingError::InvalidField("name")))?
         .to_string();
 
     let typ = channel_data.get("type")
@@ -51,7 +51,7 @@ fn parse_channel(channel_data: &JsonMap<String, JsonValue>, raw: bool) -> IOResu
         .unwrap_or("float64")
         .to_string();
 
-    let shape = convert_shape_val_to_vec(channel_data.get("shape"));
+    let shape = convert_shape_val_to_vec(channel_data.get("shape")).map_err(|e| e.with_channel(&name))?;
     let encoding = channel_data.get("encoding")
         .and_then(|v| v.as_str())
         .unwrap_or("")
@@ -61,17 +61,18 @@ fn parse_channel(channel_data: &JsonMap<String, JsonValue>, raw: bool) -> IOResu
     //"none" or "bitshuffle_lz4"
     let compression = Compression::from_str(channel_data.get("compression")
         .and_then(|v| v.as_str())
-        .unwrap_or("none"))?;
+        .unwrap_or("none"))
+        .map_err(|e| e.with_channel(&name))?;
 
     channel::new(name, typ, shape, little_endian, compression, raw)
 }
 
-fn parse_channels(data_header: &HashMap<String, JsonValue>, raw: bool) -> IOResult<Vec<Box<dyn ChannelTrait>>> {
+fn parse_channels(data_header: &HashMap<String, JsonValue>, raw: bool) -> BsreadResult<Vec<Box<dyn ChannelTrait>>> {
     // Attempt to get the "channels" key and ensure it is an array
     let items = data_header
         .get("channels")
         .and_then(|v| v.as_array())
-        .ok_or(IOError::new(ErrorKind::InvalidInput,"Invalid format: 'channels' missing or not an array"))?;
+        .ok_or(BsreadError::parsing(ParsingError::InvalidField("channels")))?;
 
     // Initialize the resulting HashMap
     //let mut channels = HashMap::new();
@@ -81,73 +82,168 @@ fn parse_channels(data_header: &HashMap<String, JsonValue>, raw: bool) -> IOResu
     for item in items {
         // Ensure each item is a map with string keys and string values
         let channel_data = item.as_object().
-            ok_or(IOError::new(ErrorKind::InvalidInput,"Invalid format: is not an object"))?;
-        let channel = parse_channel(channel_data, raw).unwrap();
+            ok_or(BsreadError::parsing(ParsingError::InvalidField("channels")))?;
+        let channel = parse_channel(channel_data, raw)?;
         //channels.insert(name, channel);
         channels.push(channel);
     }
     Ok(channels)
 }
 
+#[derive(Debug, Clone)]
+enum ChannelValue {
+    Owned(Value),
+    //Bytes kept in the received frame or decompression buffer, decoded only if value() is called
+    Shared {buffer: SharedBuffer, element_type: ElementType, little_endian: bool, value: OnceLock<Value>},
+}
+
 #[derive(Debug, Clone)]
 pub struct ChannelData {
-    value: Value,
+    //Shared, so that clones (e.g. Message::data() in lazy mode) don't copy the value
+    value: Arc<ChannelValue>,
     timestamp: (u64, u64),
 }
 
 impl ChannelData {
     pub fn new(value: Value, timestamp: (u64, u64)) -> Self {
-        Self { value, timestamp }
+        Self { value: Arc::new(ChannelValue::Owned(value)), timestamp }
+    }
+    pub fn new_shared(buffer: SharedBuffer, element_type: ElementType, little_endian: bool, timestamp: (u64, u64)) -> Self {
+        Self { value: Arc::new(ChannelValue::Shared { buffer, element_type, little_endian, value: OnceLock::new() }), timestamp }
     }
     pub fn value(&self) -> &Value {
-        &self.value
+        match &*self.value {
+            ChannelValue::Owned(value) => value,
+            ChannelValue::Shared { buffer, element_type, little_endian, value } => {
+                value.get_or_init(|| element_type.decode(buffer, *little_endian))
+            }
+        }
     }
     pub fn timestamp(&self) -> &(u64, u64) {
         &self.timestamp
     }
+    pub fn is_shared(&self) -> bool {
+        matches!(*self.value, ChannelValue::Shared { .. })
+    }
+    /// Undecoded bytes of the channel, if received in zero-copy mode.
+    pub fn buffer(&self) -> Option<&SharedBuffer> {
+        match &*self.value {
+            ChannelValue::Owned(_) => None,
+            ChannelValue::Shared { buffer, .. } => Some(buffer),
+        }
+    }
+    /// Typed view of an array value. In zero-copy mode, native-endian data is not copied.
+    pub fn as_slice<T: Element>(&self) -> Option<&[T]> {
+        if let ChannelValue::Shared { buffer, element_type, little_endian, .. } = &*self.value {
+            let native = T::TYPE.size() == 1 || *little_endian == cfg!(target_endian = "little");
+            if *element_type == T::TYPE && native {
+                if let Some(slice) = buffer.as_typed::<T>() {
+                    return Some(slice);
+                }
+            }
+        }
+        T::from_value(self.value())
+    }
 }
 
-fn parse_channel_data(global_timestamp:&(u64, u64), channel: &Box<dyn ChannelTrait>, v: &Vec<u8>, t: &Vec<u8>, raw:bool) -> IOResult<ChannelData> {
-    //if t.len() != 16 {
-    //    return Err(IOError::new(ErrorKind::InvalidData, format!("Invalid channel timestamp: {:?}", t).as_str()));
-    //}
-    let timestamp = if (t.len() == 16){
+impl PartialEq for ChannelData {
+    fn eq(&self, other: &Self) -> bool {
+        self.timestamp == other.timestamp && self.value() == other.value()
+    }
+}
+
+#[derive(Serialize)]
+struct SerializedChannelDataRef<'a> {
+    #[serde(flatten)]
+    value: &'a Value,
+    timestamp: (u64, u64),
+}
+
+#[derive(Deserialize)]
+struct SerializedChannelData {
+    #[serde(flatten)]
+    value: Value,
+    timestamp: (u64, u64),
+}
+
+impl Serialize for ChannelData {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedChannelDataRef { value: self.value(), timestamp: self.timestamp }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChannelData {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let SerializedChannelData { value, timestamp } = SerializedChannelData::deserialize(deserializer)?;
+        Ok(ChannelData::new(value, timestamp))
+    }
+}
+
+//Channels without timestamp frame have the global timestamp
+fn channel_timestamp(global_timestamp:&(u64, u64), t: &[u8]) -> BsreadResult<(u64, u64)> {
+    Ok(frame_timestamp(t)?.unwrap_or(*global_timestamp))
+}
+
+//Timestamp of a channel timestamp frame, None if the frame is absent
+pub(crate) fn frame_timestamp(t: &[u8]) -> BsreadResult<Option<(u64, u64)>> {
+    if t.len() == 16 {
         let mut cursor = Cursor::new(t);
         let timestamp_secs = READER_U64(&mut cursor)?;
         let timestamp_nanos = READER_U64(&mut cursor)?;
-        (timestamp_secs, timestamp_nanos)
+        Ok(Some((timestamp_secs, timestamp_nanos)))
     } else {
-        global_timestamp.clone()
-    };
+        Ok(None)
+    }
+}
+
+fn parse_channel_data(global_timestamp:&(u64, u64), channel: &Box<dyn ChannelTrait>, v: &SharedBuffer, t: &[u8], raw:bool, zero_copy:bool) -> BsreadResult<ChannelData> {
+    //if t.len() != 16 {
+    //    return Err(IOError::new(ErrorKind::InvalidData, format!("Invalid channel timestamp: {:?}", t).as_str()));
+    //}
+    let timestamp = channel_timestamp(global_timestamp, t)?;
 
     let data = match channel.config().compression() {
         Compression::BitshuffleLz4 => {
-            &decompress_bitshuffle_lz4(v, channel.config().element_size())
-                .map_err(|e| IOError::new(DECOMPRESSION_ERROR, e))?
+            SharedBuffer::from_vec(decompress_bitshuffle_lz4(v, channel.config().element_size())?)
         }
         Compression::Lz4 => {
-            &decompress_lz4(v,  channel.config().is_little_endian())
-                .map_err(|e| IOError::new(DECOMPRESSION_ERROR, e))?
+            SharedBuffer::from_vec(decompress_lz4(v,  channel.config().is_little_endian())?)
         }
-        Compression::None => { v }
+        Compression::None => { v.clone() }
     };
-    // Create a Cursor to read from the vector
+    if zero_copy {
+        let config = channel.config();
+        if raw {
+            return Ok(ChannelData::new_shared(data, ElementType::U8, config.is_little_endian(), timestamp));
+        }
+        let element_type = ElementType::from_kind(&config.kind())
+            .filter(|_| channel::is_array(&config.shape()) && !config.is_opaque());
+        if let Some(element_type) = element_type {
+            let size = config.size();
+            let data = data.slice(0..size).ok_or_else(||
+                BsreadError::parsing(ParsingError::ShapeMismatch{expected: size, actual: data.len()}))?;
+            return Ok(ChannelData::new_shared(data, element_type, config.is_little_endian(), timestamp));
+        }
+    }
+    // Create a Cursor to read from the buffer
     if raw {
-        Ok(ChannelData { value: Value::AU8(data.clone()), timestamp: timestamp })
+        Ok(ChannelData::new(Value::AU8(data.to_vec()), timestamp))
     } else {
-        let mut cursor = Cursor::new(data);
-        let value = channel.read(&mut cursor);
-        Ok(ChannelData { value: value.unwrap(), timestamp: timestamp })
+        let mut cursor = Cursor::new(data.as_slice());
+        let value = channel.read(&mut cursor)
+            .map_err(|e| BsreadError::parsing(ParsingError::InvalidData(e.to_string())))?;
+        Ok(ChannelData::new(value, timestamp))
     }
 }
 
-pub fn serialize_channel(channel: &Box<dyn ChannelTrait>, channel_data: & ChannelData) -> IOResult<(Vec<u8>,Vec<u8>)> {
+pub fn serialize_channel(channel: &Box<dyn ChannelTrait>, channel_data: & ChannelData) -> BsreadResult<(Vec<u8>,Vec<u8>)> {
     let  value = channel_data.value();
     let  timestamp = channel_data.timestamp();
     let size = channel.config().size();
     let mut buf = vec![0u8; size];
     let mut cursor = Cursor::new(&mut buf);
-    channel.write(& mut cursor, &value)?;
+    channel.write(& mut cursor, &value)
+        .map_err(|e| BsreadError::validation(ValidationError::Encoding(e.to_string())).with_channel(&channel.config().name()))?;
     let data = match channel.config().compression() {
         Compression::BitshuffleLz4 => {
             compress_bitshuffle_lz4(&buf, channel.config().element_size())?
@@ -167,11 +263,25 @@ pub fn serialize_channel(channel: &Box<dyn ChannelTrait>, channel_data: & Channe
 }
 
 
+/// Called once for each channel that fails to decode in lazy mode.
+pub type ChannelErrorHook = Arc<dyn Fn(&BsreadError) + Send + Sync>;
+
+//Undecoded channel frames of a message parsed in lazy mode
+struct LazyChannels {
+    frames: Vec<SharedBuffer>,
+    endpoint: Option<String>,
+    global_timestamp: (u64, u64),
+    zero_copy: bool,
+    decoded: Vec<OnceLock<BsreadResult<ChannelData>>>,
+    error_hook: Option<ChannelErrorHook>,
+}
+
 pub struct Message {
     main_header: HashMap<String, JsonValue>,
     data_header: HashMap<String, JsonValue>,
     channels: Vec<Box<dyn ChannelTrait>>,
-    data: IndexMap<String, Option<ChannelData>>,
+    data: OnceLock<IndexMap<String, Option<ChannelData>>>,
+    lazy: Option<LazyChannels>,
     id: u64,
     hash: String,
     htype: String,
@@ -186,58 +296,56 @@ pub struct DataHeaderInfo {
     pub hash: String,
     pub data_header: HashMap<String, JsonValue>,
     pub channels: Vec<Box<dyn ChannelTrait>>,
+    /// Indexes of the channels selected by the receiver filter, None if all are selected
+    pub selection: Option<Vec<usize>>,
 }
 
-fn id(main_header: &HashMap<String, JsonValue>) -> IOResult<u64> {
+fn id(main_header: &HashMap<String, JsonValue>) -> BsreadResult<u64> {
     let v = main_header.get("pulse_id").ok_or_else(|| {
-            IOError::new(ErrorKind::InvalidInput, "Missing 'pulse_id'")
+            BsreadError::parsing(ParsingError::MissingField("pulse_id"))
         })?;
 
     match v.as_i64() {
         Some(id) if id >= 0 => Ok(id as u64),
-        Some(_) => Err(IOError::new(ErrorKind::InvalidInput,"'pulse_id' cannot be negative",)),
-        None => Err(IOError::new(ErrorKind::InvalidInput,"'pulse_id' is not a valid integer",)),
+        _ => Err(BsreadError::parsing(ParsingError::InvalidField("pulse_id"))),
     }
 }
 
-fn hash(main_header: &HashMap<String, JsonValue>) -> IOResult<String> {
+fn hash(main_header: &HashMap<String, JsonValue>) -> BsreadResult<String> {
     main_header.get("hash").and_then(|v| v.as_str()).map(|s| s.to_string()).ok_or_else( ||
-        IOError::new(ErrorKind::InvalidInput,"Invalid format: 'hash' missing or not a string")
+        BsreadError::parsing(ParsingError::InvalidField("hash"))
     )
 }
-fn htype(main_header: &HashMap<String, JsonValue>) -> IOResult<String> {
+fn htype(main_header: &HashMap<String, JsonValue>) -> BsreadResult<String> {
     let h = main_header.get("htype").and_then(|v| v.as_str()).map(|s| s.to_string()).ok_or_else( ||
-        IOError::new(ErrorKind::InvalidInput,"Invalid format: 'htype' missing or not a string")
+        BsreadError::parsing(ParsingError::InvalidField("htype"))
     )?;
     if h != HTYPE {
-        return Err(IOError::new(ErrorKind::InvalidInput,"Invalid field: 'htype'"));
+        return Err(BsreadError::parsing(ParsingError::InvalidHtype(h)));
     }
     Ok(h)
 }
 
-fn dh_compression(main_header: &HashMap<String, JsonValue>) -> IOResult<Compression> {
+fn dh_compression(main_header: &HashMap<String, JsonValue>) -> BsreadResult<Compression> {
     match main_header.get("dh_compression") {
         None => Ok(Compression::None),
         Some(v) => {
             let s = v.as_str().ok_or_else(|| {
-                IOError::new(
-                    ErrorKind::InvalidInput,
-                    "Invalid format: 'dh_compression' is not a string",
-                )
+                BsreadError::parsing(ParsingError::InvalidField("dh_compression"))
             })?;
             Compression::from_str(s)
         }
     }
 }
 
-fn timestamp(main_header: &HashMap<String, JsonValue>) -> (u64, u64) {
+fn timestamp(main_header: &HashMap<String, JsonValue>) -> BsreadResult<(u64, u64)> {
     match main_header.get("global_timestamp") {
-        None => { (0, 0) }
+        None => { Ok((0, 0)) }
         Some(v) => {
             let m = v.as_object();
-            let ns = m.unwrap().get("ns").unwrap().as_u64().unwrap();
-            let sec = m.unwrap().get("sec").unwrap().as_u64().unwrap();
-            (sec, ns)
+            let field = |name| m.and_then(|m| m.get(name)).and_then(|v| v.as_u64())
+                .ok_or(BsreadError::parsing(ParsingError::InvalidField("global_timestamp")));
+            Ok((field("sec")?, field("ns")?))
         }
     }
 }
@@ -248,16 +356,28 @@ impl Message {
            channels: Vec<Box<dyn ChannelTrait>>,
            data: IndexMap<String, Option<ChannelData>>,
            header_changed: Option<bool>,
-           raw: bool) -> IOResult<Self> {
+           raw: bool) -> BsreadResult<Self> {
         let hash = hash(&main_header)?;
         let id = id(&main_header)?;
         let htype =htype(&main_header)?;
         let dh_compression = dh_compression(&main_header)?;
-        let timestamp = timestamp(&main_header);
+        let timestamp = timestamp(&main_header)?;
+
+        Ok(Self { main_header, data_header, channels, data: OnceLock::from(data), lazy: None, id, hash, htype, dh_compression, timestamp, header_changed, raw })
+    }
 
-        Ok(Self { main_header, data_header, channels, data, id, hash, htype, dh_compression, timestamp, header_changed, raw })
+    fn new_lazy(main_header: HashMap<String, JsonValue>,
+           data_header: HashMap<String, JsonValue>,
+           channels: Vec<Box<dyn ChannelTrait>>,
+           lazy: LazyChannels,
+           header_changed: Option<bool>,
+           raw: bool) -> BsreadResult<Self> {
+        let mut message = Message::new(main_header, data_header, channels, IndexMap::new(), header_changed, raw)?;
+        message.data = OnceLock::new();
+        message.lazy = Some(lazy);
+        Ok(message)
     }
-    pub fn new_from_channel_map(id:u64, timestamp: (u64, u64),  channels: Vec<Box<dyn ChannelTrait>>, channel_data:IndexMap<String, Option<ChannelData>>) -> IOResult<Self> {
+    pub fn new_from_channel_map(id:u64, timestamp: (u64, u64),  channels: Vec<Box<dyn ChannelTrait>>, channel_data:IndexMap<String, Option<ChannelData>>) -> BsreadResult<Self> {
         let mut main_header: HashMap<String, JsonValue> = HashMap::new();
         main_header.insert("htype".to_string(), JsonValue::String(HTYPE.to_string()));
         main_header.insert("pulse_id".to_string(),  JsonValue::Number(JsonNumber::from(id)));
@@ -273,7 +393,7 @@ impl Message {
         Message::new(main_header, data_header, channels, channel_data, None, false)
     }
 
-    pub fn new_from_channel_vec(id:u64, timestamp: (u64, u64),  channels: &Vec<Box<dyn ChannelTrait>>, mut channel_data:Vec<Option<ChannelData>>) -> IOResult<Self> {
+    pub fn new_from_channel_vec(id:u64, timestamp: (u64, u64),  channels: &Vec<Box<dyn ChannelTrait>>, mut channel_data:Vec<Option<ChannelData>>) -> BsreadResult<Self> {
         let mut data: IndexMap<String, Option<ChannelData>> = IndexMap::new();
         for i in 0..channels.len() {
             //data.insert(channels[i].config().get_name().clone(),channel_data[i].clone());
@@ -298,8 +418,50 @@ impl Message {
         &self.channels
     }
 
+    /// In lazy mode, decodes all channels not yet accessed.
     pub fn data(&self) -> &IndexMap<String, Option<ChannelData>> {
-        &self.data
+        self.data.get_or_init(|| {
+            self.channels.iter().enumerate()
+                .map(|(index, channel)| (channel.config().name(), self.decode_channel(index).ok().cloned()))
+                .collect()
+        })
+    }
+
+    pub fn is_lazy(&self) -> bool {
+        self.lazy.is_some()
+    }
+
+    /// Timestamps of the channels with data. In lazy mode they are read without decoding the channels.
+    pub fn channel_timestamps(&self) -> Vec<(u64, u64)> {
+        match (&self.lazy, self.data.get()) {
+            (Some(lazy), None) => lazy.frames.chunks_exact(2)
+                .filter(|frames| !frames[0].is_empty())
+                .filter_map(|frames| channel_timestamp(&lazy.global_timestamp, frames[1].as_slice()).ok())
+                .collect(),
+            _ => self.data().values().flatten().map(|data| *data.timestamp()).collect(),
+        }
+    }
+
+    pub(crate) fn set_error_hook(&mut self, hook: ChannelErrorHook) {
+        if let Some(lazy) = self.lazy.as_mut() {
+            lazy.error_hook = Some(hook);
+        }
+    }
+
+    //Decodes a channel of a lazy message on first access, memoizing the result
+    fn decode_channel(&self, index: usize) -> BsreadResult<&ChannelData> {
+        let lazy = self.lazy.as_ref().ok_or_else(|| BsreadError::parsing(ParsingError::InvalidMessage("Message is not lazy".to_string())))?;
+        let result = lazy.decoded[index].get_or_init(|| {
+            let channel = &self.channels[index];
+            let (v, t) = (&lazy.frames[2 * index], &lazy.frames[2 * index + 1]);
+            let result = parse_channel_data(&lazy.global_timestamp, channel, v, t, self.raw, lazy.zero_copy)
+                .map_err(|e| e.with_endpoint(&lazy.endpoint).with_channel(channel.config().name_str()).with_pulse_id(self.id));
+            if let (Err(e), Some(hook)) = (&result, &lazy.error_hook) {
+                hook(e);
+            }
+            result
+        });
+        result.as_ref().map_err(BsreadError::clone)
     }
 
     pub fn hash(&self) -> String {
@@ -330,25 +492,84 @@ impl Message {
     }
 
     pub fn channel_data(&self, channel_name: &str) -> Option<&ChannelData> {
-        self.data().get(channel_name)?.as_ref()
+        self.try_channel_data(channel_name).ok().flatten()
+    }
+    /// Same as channel_data, but returns the decoding error of the channel in lazy mode.
+    pub fn try_channel_data(&self, channel_name: &str) -> BsreadResult<Option<&ChannelData>> {
+        if let Some(data) = self.data.get() {
+            return Ok(data.get(channel_name).and_then(Option::as_ref));
+        }
+        match self.channels.iter().position(|channel| channel.config().name_str() == channel_name) {
+            Some(index) => self.decode_channel(index).map(Some),
+            None => Ok(None),
+        }
     }
     pub fn channel_value(&self, channel_name: &str) -> Option<&Value> {
         self.channel_data(channel_name).map(ChannelData::value)
     }
 
-    fn clone_data_header_info(&self, hash:String) -> Option<DataHeaderInfo> {
-        let data_header = self.data_header.clone();
-        //TODO: is there a better way to clone channels?
-        let channels;
-        match parse_channels(&data_header, self.raw){
-            Ok(ch) => {channels = ch;}
-            Err(_) => {return None}
+    /// Value of a channel converted to T, e.g. message.get::<f64>("X") or message.get::<Vec<u16>>("Y").
+    pub fn get<T>(&self, channel_name: &str) -> BsreadResult<T>
+    where
+        T: for<'a> TryFrom<&'a Value, Error = BsreadError>,
+    {
+        let data = self.try_channel_data(channel_name)?.ok_or_else(||
+            BsreadError::validation(ValidationError::MissingChannel).with_channel(channel_name).with_pulse_id(self.id))?;
+        T::try_from(data.value()).map_err(|e| e.with_channel(channel_name).with_pulse_id(self.id))
+    }
+}
+
+/// Messages are equal if they have the same pulse ID, global timestamp, channel definitions and data.
+impl PartialEq for Message {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.timestamp == other.timestamp
+            && self.channels.iter().map(|channel| channel.config()).eq(other.channels.iter().map(|channel| channel.config()))
+            && self.data() == other.data()
+    }
+}
+
+//Channel definitions and data are serialized in the same order
+#[derive(Serialize)]
+struct SerializedMessageRef<'a> {
+    pulse_id: u64,
+    global_timestamp: (u64, u64),
+    channels: Vec<&'a ChannelConfig>,
+    data: Vec<Option<&'a ChannelData>>,
+}
+
+#[derive(Deserialize)]
+struct SerializedMessage {
+    pulse_id: u64,
+    global_timestamp: (u64, u64),
+    channels: Vec<ChannelConfig>,
+    data: Vec<Option<ChannelData>>,
+}
+
+impl Serialize for Message {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedMessageRef {
+            pulse_id: self.id,
+            global_timestamp: self.timestamp,
+            channels: self.channels.iter().map(|channel| channel.config()).collect(),
+            data: self.channels.iter().map(|channel| self.channel_data(channel.config().name_str())).collect(),
+        }.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Message {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        let SerializedMessage { pulse_id, global_timestamp, channels, data } = SerializedMessage::deserialize(deserializer)?;
+        if channels.len() != data.len() {
+            return Err(D::Error::custom(ValidationError::ChannelCount { expected: channels.len(), actual: data.len() }));
         }
-        Some(DataHeaderInfo {hash, data_header, channels})
+        let channels = channels.iter().map(channel::from_config).collect::<BsreadResult<Vec<_>>>().map_err(D::Error::custom)?;
+        let data = channels.iter().map(|channel| channel.config().name()).zip(data).collect();
+        Message::new_from_channel_map(pulse_id, global_timestamp, channels, data).map_err(D::Error::custom)
     }
 }
 
-pub fn create_data_header(channels: &Vec<Box<dyn ChannelTrait>>,)-> IOResult<HashMap<String,JsonValue>> {
+pub fn create_data_header(channels: &Vec<Box<dyn ChannelTrait>>,)-> BsreadResult<HashMap<String,JsonValue>> {
     let mut data_header = HashMap::new();
     data_header.insert("htype".to_string(), JsonValue::String("bsr_d-1.1".to_string()));
 
@@ -373,14 +594,30 @@ pub fn create_data_header(channels: &Vec<Box<dyn ChannelTrait>>,)-> IOResult<Has
     Ok(data_header)
 }
 
-pub fn parse_message(message_parts: Vec<Vec<u8>>, endpoint:&Option<String>, last_headers:& mut LimitedHashMap<String, DataHeaderInfo>, raw:bool) -> IOResult<Message> {
+pub fn parse_message(message_parts: Vec<Vec<u8>>, endpoint:&Option<String>, last_headers:& mut LimitedHashMap<String, DataHeaderInfo>, raw:bool) -> BsreadResult<Message> {
+    let message_parts = message_parts.into_iter().map(SharedBuffer::from_vec).collect();
+    parse_frames(message_parts, endpoint, last_headers, raw, false, false, None)
+}
+
+/// Parses the received frames. In zero-copy mode, the values of numeric arrays stay in the frames
+/// (or decompression buffers) and are only decoded when accessed.
+/// In lazy mode, the channel frames are kept and each channel is decoded on first access.
+/// If a filter is given, only the selected channels are decoded and included in the message.
+/// The selection is evaluated when a new data header is received.
+pub fn parse_frames(message_parts: Vec<SharedBuffer>, endpoint:&Option<String>, last_headers:& mut LimitedHashMap<String, DataHeaderInfo>, raw:bool, zero_copy:bool, lazy:bool, filter:Option<&ChannelFilter>) -> BsreadResult<Message> {
     let mut data = IndexMap::new();
     if message_parts.len() < 2 {
-        return Err(IOError::new(ErrorKind::InvalidData, "Invalid message format"));
+        return Err(BsreadError::parsing(ParsingError::FrameCount{expected: 2, actual: message_parts.len()}).with_endpoint(endpoint));
     }
-    let main_header = decode_json(&message_parts[0])?;
-    let hash = hash(&main_header)?;
-    let global_timestamp = timestamp(&main_header);
+    let main_header = decode_json(&message_parts[0]).map_err(|e| BsreadError::from(e).with_endpoint(endpoint))?;
+    let add_context = |e: BsreadError| {
+        match id(&main_header) {
+            Ok(pulse_id) => e.with_endpoint(endpoint).with_pulse_id(pulse_id),
+            Err(_) => e.with_endpoint(endpoint),
+        }
+    };
+    let hash = hash(&main_header).map_err(add_context)?;
+    let global_timestamp = timestamp(&main_header).map_err(add_context)?;
 
     let endpoint_info = endpoint
         .as_ref()
@@ -389,53 +626,65 @@ pub fn parse_message(message_parts: Vec<Vec<u8>>, endpoint:&Option<String>, last
         .cloned();
 
     // Determine whether to reuse or reparse data
-    let (data_header, channels, changed) =
+    let (data_header, channels, selection, changed) =
     if let Some(last_msg) = endpoint_info {
-        (last_msg.data_header.clone(), last_msg.channels.clone(), false)
+        (last_msg.data_header.clone(), last_msg.channels.clone(), last_msg.selection.clone(), false)
     } else if let Some(last_msg) = last_headers.get(&hash) {
         // Reuse the previous data header and channels
-        (last_msg.data_header.clone(), last_msg.channels.clone(), false)
+        (last_msg.data_header.clone(), last_msg.channels.clone(), last_msg.selection.clone(), false)
     } else {
         let blob = &message_parts[1];
-        let compression = dh_compression(&main_header)?;
+        let compression = dh_compression(&main_header).map_err(add_context)?;
 
         let json = match compression {
             Compression::BitshuffleLz4 => {
-                &decompress_bitshuffle_lz4(blob, 1)
-                    .map_err(|e| IOError::new(DECOMPRESSION_ERROR, e))?
+                &decompress_bitshuffle_lz4(blob, 1).map_err(add_context)?
             }
             Compression::Lz4 => {
-                &decompress_lz4(blob, false)
-                    .map_err(|e| IOError::new(DECOMPRESSION_ERROR, e))?
+                &decompress_lz4(blob, false).map_err(add_context)?
             }
-            Compression::None => { &blob }
+            Compression::None => { blob.as_slice() }
         };
-        let data_header = decode_json(json)?;
-        let channels = parse_channels(&data_header, raw).unwrap();
-        (data_header, channels, true)
+        let data_header = decode_json(json).map_err(|e| add_context(e.into()))?;
+        let channels = parse_channels(&data_header, raw).map_err(add_context)?;
+        let selection = filter.map(|filter| filter.select(&channels));
+        (data_header, channels, selection, true)
     };
 
     if message_parts.len() - 2 != channels.len() * 2 {
-        return Err(IOError::new(ErrorKind::InvalidData, "Invalid number of messages"));
-    }
-    for i in 0..channels.len() {
-        let channel = &channels[i];
-        let v = &message_parts[2 * i + 2];
-        let t = &message_parts[2 * i + 3];
-
-        let channel_data = parse_channel_data(&global_timestamp, channel, v, t, raw).ok();
-        data.insert(channel.config().name(), channel_data);
+        return Err(add_context(BsreadError::parsing(ParsingError::FrameCount{expected: channels.len() * 2 + 2, actual: message_parts.len()})));
     }
-    let msg = Message::new(main_header, data_header, channels, data, Some(changed), raw);
+    let header_info = changed.then(|| DataHeaderInfo {hash, data_header: data_header.clone(), channels: channels.clone(), selection: selection.clone()});
+    // Unselected channels are dropped without touching their frames
+    let (channels, indexes): (Vec<Box<dyn ChannelTrait>>, Vec<usize>) = match selection {
+        Some(selection) => (selection.iter().map(|&i| channels[i].clone()).collect(), selection),
+        None => {
+            let indexes = (0..channels.len()).collect();
+            (channels, indexes)
+        }
+    };
+    let msg = if lazy {
+        let frames = indexes.iter().flat_map(|&i| [message_parts[2 * i + 2].clone(), message_parts[2 * i + 3].clone()]).collect();
+        let decoded = (0..channels.len()).map(|_| OnceLock::new()).collect();
+        let lazy = LazyChannels { frames, endpoint: endpoint.clone(), global_timestamp, zero_copy, decoded, error_hook: None };
+        Message::new_lazy(main_header, data_header, channels, lazy, Some(changed), raw)
+    } else {
+        for (channel, &i) in channels.iter().zip(indexes.iter()) {
+            let v = &message_parts[2 * i + 2];
+            let t = &message_parts[2 * i + 3];
 
-    if let Ok(m) = &msg {
-        if changed {
-            if let Some(l) = m.clone_data_header_info(hash) {
-                if let Some(ep) = endpoint {
-                    last_headers.insert(ep.clone(), l);
-                } else {
-                    last_headers.insert(l.hash.clone(), l);
-                }
+            let channel_data = parse_channel_data(&global_timestamp, channel, v, t, raw, zero_copy).ok();
+            data.insert(channel.config().name(), channel_data);
+        }
+        Message::new(main_header, data_header, channels, data, Some(changed), raw)
+    }.map_err(|e| e.with_endpoint(endpoint));
+
+    if msg.is_ok() {
+        if let Some(l) = header_info {
+            if let Some(ep) = endpoint {
+                last_headers.insert(ep.clone(), l);
+            } else {
+                last_headers.insert(l.hash.clone(), l);
             }
         }
     }
@@ -0,0 +1,285 @@
+use crate::*;
+use crate::receiver::Stats;
+use crate::sender::SenderCounters;
+use crate::utils::FifoQueue;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as FmtWrite;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+//Shared state of a receiver, read on every scrape
+pub(crate) struct ReceiverMetrics {
+    pub(crate) index: u32,
+    pub(crate) endpoints: Arc<RwLock<Vec<String>>>,
+    pub(crate) stats: Arc<RwLock<Stats>>,
+    pub(crate) fifo: Option<Arc<FifoQueue<ReceivedMessage>>>,
+    pub(crate) endpoint_states: Option<Arc<Mutex<HashMap<String, EndpointState>>>>,
+    //Set if the monitor is shared among the receivers of a pool
+    pub(crate) shared_monitor: bool,
+}
+
+struct SenderMetrics {
+    endpoint: String,
+    counters: Arc<SenderCounters>,
+}
+
+enum MetricsSource {
+    Receiver(ReceiverMetrics),
+    Sender(SenderMetrics),
+}
+
+struct Family {
+    kind: &'static str,
+    help: &'static str,
+    samples: Vec<(String, String)>,
+}
+
+#[derive(Default)]
+struct Families {
+    families: BTreeMap<String, Family>,
+}
+
+impl Families {
+    fn add<V: ToString>(&mut self, name: &str, kind: &'static str, help: &'static str, labels: &[(&str, &str)], value: V) {
+        if !self.families.contains_key(name) {
+            self.families.insert(name.to_string(), Family { kind, help, samples: Vec::new() });
+        }
+        let labels = labels.iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape_label(value)))
+            .collect::<Vec<_>>()
+            .join(",");
+        self.families.get_mut(name).unwrap().samples.push((labels, value.to_string()));
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, family) in self.families.iter() {
+            let _ = writeln!(out, "# HELP {} {}", name, family.help);
+            let _ = writeln!(out, "# TYPE {} {}", name, family.kind);
+            for (labels, value) in family.samples.iter() {
+                let _ = writeln!(out, "{}{{{}}} {}", name, labels, value);
+            }
+        }
+        out
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+//RepeatedId -> repeated_id
+fn snake_case(name: &str) -> String {
+    let mut ret = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                ret.push('_');
+            }
+            ret.extend(c.to_lowercase());
+        } else {
+            ret.push(c);
+        }
+    }
+    ret
+}
+
+fn state_name(state: &EndpointState) -> &'static str {
+    match state {
+        EndpointState::Connecting => "connecting",
+        EndpointState::Connected => "connected",
+        EndpointState::Disconnected => "disconnected",
+    }
+}
+
+impl ReceiverMetrics {
+    fn collect(&self, families: &mut Families) {
+        let receiver = self.index.to_string();
+        let endpoints = self.endpoints.read().unwrap().clone();
+        {
+            let stats = self.stats.read().unwrap();
+            families.add("bsread_receiver_messages_total", "counter", "Messages received.", &[("receiver", &receiver)], stats.counter_messages);
+            families.add("bsread_receiver_errors_total", "counter", "Receive errors.", &[("receiver", &receiver)], stats.counter_error);
+            for (endpoint, diagnostics) in stats.diagnostics.iter() {
+                for diag in EndpointDiag::ALL {
+                    let value = diagnostics.get(diag).copied().unwrap_or(0);
+                    //The largest gap is not cumulative
+                    let (name, kind) = match diag {
+                        EndpointDiag::LargestGap => (format!("bsread_endpoint_{}", snake_case(&format!("{:?}", diag))), "gauge"),
+                        _ => (format!("bsread_endpoint_{}_total", snake_case(&format!("{:?}", diag))), "counter"),
+                    };
+                    families.add(&name, kind, "Endpoint diagnostic counter.", &[("receiver", &receiver), ("endpoint", endpoint)], value);
+                }
+            }
+        }
+        if let Some(endpoint_states) = &self.endpoint_states {
+            for (endpoint, state) in endpoint_states.lock().unwrap().clone() {
+                if self.shared_monitor && !endpoints.contains(&endpoint) {
+                    continue;
+                }
+                for other in [EndpointState::Connecting, EndpointState::Connected, EndpointState::Disconnected] {
+                    let value = if other == state { 1 } else { 0 };
+                    families.add("bsread_endpoint_state", "gauge", "Connection state of the endpoint (1 for the current state).",
+                                 &[("receiver", &receiver), ("endpoint", &endpoint), ("state", state_name(&other))], value);
+                }
+            }
+        }
+        let (available, bytes, dropped) = match &self.fifo {
+            None => (0, 0, 0),
+            Some(fifo) => (fifo.available_count(), fifo.available_bytes(), fifo.dropped_count()),
+        };
+        families.add("bsread_fifo_available", "gauge", "Messages waiting in the receiver buffer.", &[("receiver", &receiver)], available);
+        families.add("bsread_fifo_bytes", "gauge", "Decoded size of the messages waiting in the receiver buffer.", &[("receiver", &receiver)], bytes);
+        families.add("bsread_fifo_dropped_total", "counter", "Messages dropped because the receiver buffer was full.", &[("receiver", &receiver)], dropped);
+    }
+}
+
+impl SenderMetrics {
+    fn collect(&self, families: &mut Families) {
+        let labels = [("endpoint", self.endpoint.as_str())];
+        families.add("bsread_sender_messages_total", "counter", "Messages sent.", &labels, self.counters.messages.load(Ordering::Relaxed));
+        families.add("bsread_sender_bytes_total", "counter", "Bytes sent.", &labels, self.counters.bytes.load(Ordering::Relaxed));
+        families.add("bsread_sender_errors_total", "counter", "Send errors.", &labels, self.counters.errors.load(Ordering::Relaxed));
+    }
+}
+
+fn render(sources: &Mutex<Vec<MetricsSource>>) -> String {
+    let mut families = Families::default();
+    for source in sources.lock().unwrap().iter() {
+        match source {
+            MetricsSource::Receiver(receiver) => receiver.collect(&mut families),
+            MetricsSource::Sender(sender) => sender.collect(&mut families),
+        }
+    }
+    families.render()
+}
+
+fn handle_connection(mut stream: TcpStream, sources: &Mutex<Vec<MetricsSource>>) -> IOResult<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    //Skips the request headers
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim_end().is_empty() {
+            break;
+        }
+    }
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) if path == "/metrics" || path.starts_with("/metrics?") => ("200 OK", CONTENT_TYPE, render(sources)),
+        (Some("GET"), _) => ("404 Not Found", "text/plain", "Not Found\n".to_string()),
+        _ => ("405 Method Not Allowed", "text/plain", "Method Not Allowed\n".to_string()),
+    };
+    write!(stream, "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", status, content_type, body.len(), body)?;
+    stream.flush()
+}
+
+/// HTTP server exposing the counters of receivers, pools and senders in the Prometheus text format, under `/metrics`.
+///
+/// Receivers and pools must be added after being started, and after monitoring is enabled, so that
+/// their buffers and endpoint states are exported.
+pub struct MetricsExporter {
+    address: SocketAddr,
+    sources: Arc<Mutex<Vec<MetricsSource>>>,
+    interrupted: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MetricsExporter {
+    /// Binds the address (e.g. "0.0.0.0:9100", or port 0 for any free port) and starts serving.
+    pub fn new(address: &str) -> IOResult<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        let sources = Arc::new(Mutex::new(Vec::new()));
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let (server_sources, server_interrupted) = (sources.clone(), interrupted.clone());
+        log::info!("Serving metrics on http://{}/metrics", address);
+        let handle = thread::Builder::new().name("bsread-metrics".to_string()).spawn(move || {
+            //stop() connects to the listener to unblock accept
+            for stream in listener.incoming() {
+                if server_interrupted.load(Ordering::Relaxed) {
+                    break;
+                }
+                match stream {
+                    Ok(stream) => {
+                        //Served off the accept thread, so that a slow client doesn't hold back the others
+                        let sources = server_sources.clone();
+                        let spawned = thread::Builder::new().name("bsread-metrics-conn".to_string()).spawn(move || {
+                            if let Err(e) = handle_connection(stream, &sources) {
+                                log::warn!("Error serving metrics: {}", e);
+                            }
+                        });
+                        if let Err(e) = spawned {
+                            log::warn!("Error serving metrics: {}", e);
+                        }
+                    }
+                    Err(e) => log::warn!("Error accepting metrics connection: {}", e),
+                }
+            }
+        })?;
+        Ok(Self { address, sources, interrupted, handle: Some(handle) })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}/metrics", self.address)
+    }
+
+    pub fn add_receiver(&self, receiver: &Receiver) {
+        self.sources.lock().unwrap().push(MetricsSource::Receiver(receiver.metrics()));
+    }
+
+    pub fn add_pool(&self, pool: &Pool) {
+        let mut sources = self.sources.lock().unwrap();
+        for receiver in pool.metrics() {
+            sources.push(MetricsSource::Receiver(receiver));
+        }
+    }
+
+    pub fn add_sender(&self, sender: &Sender) {
+        self.sources.lock().unwrap().push(MetricsSource::Sender(SenderMetrics { endpoint: sender.endpoint(), counters: sender.counters() }));
+    }
+
+    pub fn clear(&self) {
+        self.sources.lock().unwrap().clear();
+    }
+
+    /// Current metrics, as served.
+    pub fn render(&self) -> String {
+        render(&self.sources)
+    }
+
+    pub fn stop(&mut self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let mut wakeup = self.address;
+            if wakeup.ip().is_unspecified() {
+                wakeup.set_ip(if wakeup.is_ipv4() { IpAddr::V4(Ipv4Addr::LOCALHOST) } else { IpAddr::V6(Ipv6Addr::LOCALHOST) });
+            }
+            match TcpStream::connect_timeout(&wakeup, REQUEST_TIMEOUT) {
+                Ok(_) => { let _ = handle.join(); }
+                Err(e) => log::warn!("Error stopping metrics exporter: {}", e),
+            }
+        }
+    }
+}
+
+impl Drop for MetricsExporter {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
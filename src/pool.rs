@@ -9,6 +9,10 @@ use std::thread;
 use std::time::{Duration, Instant};
 use zmq::SocketType;
 use crate::sockets::{EndpointDiag, EndpointEvent, EndpointState, Heartbeat, KeepAlive, SocketConfig, SocketMonitor, TrackedSocket};
+#[cfg(feature = "metrics")]
+use crate::metrics::ReceiverMetrics;
+#[cfg(feature = "async")]
+use crate::stream::{ReceiverStream, StreamSource};
 
 pub struct Pool {
     socket_type: SocketType,
@@ -144,7 +148,53 @@ Pool {
         self.receivers[0].is_raw()
     }
 
-    pub fn receive(&mut self, index:usize) -> IOResult<ReceivedMessage> {
+    pub fn set_zero_copy(&mut self, zero_copy:bool) {
+        for receiver in & mut self.receivers{
+            receiver.set_zero_copy(zero_copy);
+        }
+    }
+    pub fn is_zero_copy(&self) -> bool{
+        self.receivers[0].is_zero_copy()
+    }
+
+    pub fn set_lazy(&mut self, lazy:bool) {
+        for receiver in & mut self.receivers{
+            receiver.set_lazy(lazy);
+        }
+    }
+    pub fn is_lazy(&self) -> bool{
+        self.receivers[0].is_lazy()
+    }
+
+    pub fn set_channel_filter(&mut self, channel_filter:Option<ChannelFilter>) {
+        for receiver in & mut self.receivers{
+            receiver.set_channel_filter(channel_filter.clone());
+        }
+    }
+    pub fn channel_filter(&self) -> Option<&ChannelFilter>{
+        self.receivers[0].channel_filter()
+    }
+
+    /// All receivers of the pool write to the same capture file.
+    pub fn set_recorder(&mut self, recorder:Option<Recorder>) {
+        for receiver in & mut self.receivers{
+            receiver.set_recorder(recorder.clone());
+        }
+    }
+    pub fn recorder(&self) -> Option<&Recorder>{
+        self.receivers[0].recorder()
+    }
+
+    pub fn set_id_step(&mut self, id_step:u64) {
+        for receiver in & mut self.receivers{
+            receiver.set_id_step(id_step);
+        }
+    }
+    pub fn id_step(&self) -> u64{
+        self.receivers[0].id_step()
+    }
+
+    pub fn receive(&mut self, index:usize) -> BsreadResult<ReceivedMessage> {
          self.receivers[index].receive()
     }
 
@@ -163,7 +213,7 @@ Pool {
                     callback(rx);
                 }
                 if let Some(n) = num_messages {
-                    if self.message_count() >= n {
+                    if self.message_count() >= n as u64 {
                         return Ok(())
                     }
                 }
@@ -192,15 +242,47 @@ Pool {
         Ok(())
     }
 
+    //Threaded Mode, delivering the messages of all endpoints merged by pulse ID
+    pub fn fork_synchronized(&mut self, timeout_ms: u64, max_pending: usize, buffer_size: usize) -> IOResult<Arc<Synchronizer>> {
+        if self.connection_mode() == ConnectionMode::Shared {
+            return Err(IOError::new(ErrorKind::Unsupported, "Synchronization requires individual connection mode"));
+        }
+        let endpoints: Vec<String> = self.endpoints().collect();
+        let synchronizer = Arc::new(Synchronizer::new(endpoints.iter().map(String::as_str).collect(), timeout_ms, max_pending, buffer_size));
+        let sync = synchronizer.clone();
+        self.fork(move |msg| {
+            sync.add(msg);
+        })?;
+        Ok(synchronizer)
+    }
+
     //Buffered mode: non-blocking, messages buffered ibn another thread
     pub fn start(&mut self, buffer_size:usize) -> IOResult<()>
+    {
+        self.start_with_policy(buffer_size, OverflowPolicy::DropOldest, None)
+    }
+
+    /// Buffered mode, with a buffer of buffer_size messages and max_bytes per receiver, and the policy applied
+    /// when it is full.
+    pub fn start_with_policy(&mut self, buffer_size:usize, policy: OverflowPolicy, max_bytes: Option<usize>) -> IOResult<()>
     {
         for receiver in &mut self.receivers{
-            receiver.start(buffer_size);
+            receiver.start_with_policy(buffer_size, policy, max_bytes)?;
         }
         Ok(())
     }
 
+    /// Starts receiving in tasks of the current tokio runtime, one per receiver, returning the messages of all endpoints
+    /// as an asynchronous stream, with up to buffer_size messages queued. Must be called within a tokio runtime.
+    #[cfg(feature = "async")]
+    pub fn into_stream(mut self, buffer_size: usize) -> ReceiverStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(buffer_size.max(1));
+        for receiver in &mut self.receivers {
+            receiver.fork_stream(tx.clone());
+        }
+        ReceiverStream::new(rx, StreamSource::Pool(self))
+    }
+
     pub fn stop(&mut self) -> IOResult<()> {
         for receiver in &mut self.receivers{
             receiver.interrupt();
@@ -303,6 +385,11 @@ Pool {
         &self.receivers
     }
 
+    #[cfg(feature = "async")]
+    pub(crate) fn receivers_mut(&mut self) -> &mut Vec<Receiver> {
+        &mut self.receivers
+    }
+
     pub fn num_receivers(&self) -> usize {
         self.receivers.len()
     }
@@ -328,28 +415,28 @@ Pool {
             .sum()
     }
 
-    pub fn available(&self) -> u32 {
+    pub fn available(&self) -> u64 {
         self.receivers
             .iter()
             .map(|r| r.available())
             .sum()
     }
 
-    pub fn dropped(&self) -> u32 {
+    pub fn dropped(&self) -> u64 {
         self.receivers
             .iter()
             .map(|r| r.dropped())
             .sum()
     }
 
-    pub fn message_count(&self) -> u32 {
+    pub fn message_count(&self) -> u64 {
         self.receivers
             .iter()
             .map(|r| r.message_count())
             .sum()
     }
 
-    pub fn error_count(&self) -> u32 {
+    pub fn error_count(&self) -> u64 {
         self.receivers
             .iter()
             .map(|r| r.error_count())
@@ -361,7 +448,7 @@ Pool {
             receiver.reset_counters();
         }
     }
-    pub fn diagnostics(&self) -> HashMap<String, HashMap<EndpointDiag, u32>> {
+    pub fn diagnostics(&self) -> HashMap<String, HashMap<EndpointDiag, u64>> {
         let mut diagnostics = HashMap::new();
         for receiver in &self.receivers {
             diagnostics.extend(receiver.diagnostics());
@@ -389,17 +476,43 @@ Pool {
     }
 
 
-    pub fn endpoint_diagnostics(& self,  endpoint: &str) -> Option<HashMap<EndpointDiag, u32>> {
+    pub fn endpoint_diagnostics(& self,  endpoint: &str) -> Option<HashMap<EndpointDiag, u64>> {
         self.endpoint_receiver(endpoint)
             .map_or(None, |receiver| receiver.endpoint_diagnostics(endpoint))
     }
 
-    pub fn endpoint_diagnostic(& self,  endpoint: &str, diag:EndpointDiag) -> Option<u32> {
+    pub fn timing_stats(&self) -> HashMap<String, TimingStats> {
+        let mut timing = HashMap::new();
+        for receiver in &self.receivers {
+            timing.extend(receiver.timing_stats());
+        }
+        timing
+    }
+
+    pub fn endpoint_timing_stats(& self,  endpoint: &str) -> Option<TimingStats> {
+        self.endpoint_receiver(endpoint)
+            .and_then(|receiver| receiver.endpoint_timing_stats(endpoint))
+    }
+
+    pub fn throughput(&self) -> HashMap<String, Throughput> {
+        let mut throughput = HashMap::new();
+        for receiver in &self.receivers {
+            throughput.extend(receiver.throughput());
+        }
+        throughput
+    }
+
+    pub fn endpoint_throughput(& self,  endpoint: &str) -> Option<Throughput> {
+        self.endpoint_receiver(endpoint)
+            .and_then(|receiver| receiver.endpoint_throughput(endpoint))
+    }
+
+    pub fn endpoint_diagnostic(& self,  endpoint: &str, diag:EndpointDiag) -> Option<u64> {
         self.endpoint_receiver(endpoint)
             .map_or(None, |receiver| receiver.endpoint_diagnostic(endpoint, diag))
     }
 
-    pub fn header_changes(&self, endpoint: &str) -> u32 {
+    pub fn header_changes(&self, endpoint: &str) -> u64 {
         self.endpoint_receiver(endpoint)
             .map_or(0, |receiver| receiver.header_changes(endpoint))
     }
@@ -417,6 +530,16 @@ Pool {
         }
     }
 
+    #[cfg(feature = "metrics")]
+    pub(crate) fn metrics(&self) -> Vec<ReceiverMetrics> {
+        self.receivers.iter().map(|receiver| {
+            let mut metrics = receiver.metrics();
+            metrics.endpoint_states = self.socket_monitor.as_ref().map(SocketMonitor::shared_endpoint_states);
+            metrics.shared_monitor = true;
+            metrics
+        }).collect()
+    }
+
     pub fn enable_check(& mut self, check:u64){
         for receiver in &mut self.receivers {
             receiver.enable_check(check);
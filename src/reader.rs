@@ -13,26 +13,26 @@ impl<T: AsRef<[u8]>> ReadU8Into for Cursor<T> {
     }
 }
 
-pub const READER_I8: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i8> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i8() };
-pub const READER_I16: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i16> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i16::<LittleEndian>() };
-pub const READER_I32: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i32> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i32::<LittleEndian>() };
-pub const READER_I64: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i64> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i64::<LittleEndian>() };
-pub const READER_U8: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u8> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u8() };
-pub const READER_U16: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u16> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u16::<LittleEndian>() };
-pub const READER_U32: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u32> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u32::<LittleEndian>() };
-pub const READER_U64: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u64> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u64::<LittleEndian>() };
-pub const READER_F32: fn(&mut Cursor<&Vec<u8>>) -> IOResult<f32> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_f32::<LittleEndian>() };
-pub const READER_F64: fn(&mut Cursor<&Vec<u8>>) -> IOResult<f64> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_f64::<LittleEndian>() };
-pub const READER_BI16: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i16> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i16::<BigEndian>() };
-pub const READER_BI32: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i32> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i32::<BigEndian>() };
-pub const READER_BI64: fn(&mut Cursor<&Vec<u8>>) -> IOResult<i64> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_i64::<BigEndian>() };
-pub const READER_BU16: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u16> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u16::<BigEndian>() };
-pub const READER_BU32: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u32> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u32::<BigEndian>() };
-pub const READER_BU64: fn(&mut Cursor<&Vec<u8>>) -> IOResult<u64> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_u64::<BigEndian>() };
-pub const READER_BF32: fn(&mut Cursor<&Vec<u8>>) -> IOResult<f32> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_f32::<BigEndian>() };
-pub const READER_BF64: fn(&mut Cursor<&Vec<u8>>) -> IOResult<f64> = |cursor: &mut Cursor<&Vec<u8>>| { cursor.read_f64::<BigEndian>() };
-pub const READER_BOOL: fn(&mut Cursor<&Vec<u8>>) -> IOResult<bool> = |cursor: &mut Cursor<&Vec<u8>>| READER_U8(cursor).map(|value| value != 0);
-pub const READER_STRING: fn(&mut Cursor<&Vec<u8>>) -> IOResult<String> = |cursor: &mut Cursor<&Vec<u8>>| {
+pub const READER_I8: fn(&mut Cursor<&[u8]>) -> IOResult<i8> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i8() };
+pub const READER_I16: fn(&mut Cursor<&[u8]>) -> IOResult<i16> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i16::<LittleEndian>() };
+pub const READER_I32: fn(&mut Cursor<&[u8]>) -> IOResult<i32> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i32::<LittleEndian>() };
+pub const READER_I64: fn(&mut Cursor<&[u8]>) -> IOResult<i64> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i64::<LittleEndian>() };
+pub const READER_U8: fn(&mut Cursor<&[u8]>) -> IOResult<u8> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u8() };
+pub const READER_U16: fn(&mut Cursor<&[u8]>) -> IOResult<u16> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u16::<LittleEndian>() };
+pub const READER_U32: fn(&mut Cursor<&[u8]>) -> IOResult<u32> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u32::<LittleEndian>() };
+pub const READER_U64: fn(&mut Cursor<&[u8]>) -> IOResult<u64> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u64::<LittleEndian>() };
+pub const READER_F32: fn(&mut Cursor<&[u8]>) -> IOResult<f32> = |cursor: &mut Cursor<&[u8]>| { cursor.read_f32::<LittleEndian>() };
+pub const READER_F64: fn(&mut Cursor<&[u8]>) -> IOResult<f64> = |cursor: &mut Cursor<&[u8]>| { cursor.read_f64::<LittleEndian>() };
+pub const READER_BI16: fn(&mut Cursor<&[u8]>) -> IOResult<i16> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i16::<BigEndian>() };
+pub const READER_BI32: fn(&mut Cursor<&[u8]>) -> IOResult<i32> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i32::<BigEndian>() };
+pub const READER_BI64: fn(&mut Cursor<&[u8]>) -> IOResult<i64> = |cursor: &mut Cursor<&[u8]>| { cursor.read_i64::<BigEndian>() };
+pub const READER_BU16: fn(&mut Cursor<&[u8]>) -> IOResult<u16> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u16::<BigEndian>() };
+pub const READER_BU32: fn(&mut Cursor<&[u8]>) -> IOResult<u32> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u32::<BigEndian>() };
+pub const READER_BU64: fn(&mut Cursor<&[u8]>) -> IOResult<u64> = |cursor: &mut Cursor<&[u8]>| { cursor.read_u64::<BigEndian>() };
+pub const READER_BF32: fn(&mut Cursor<&[u8]>) -> IOResult<f32> = |cursor: &mut Cursor<&[u8]>| { cursor.read_f32::<BigEndian>() };
+pub const READER_BF64: fn(&mut Cursor<&[u8]>) -> IOResult<f64> = |cursor: &mut Cursor<&[u8]>| { cursor.read_f64::<BigEndian>() };
+pub const READER_BOOL: fn(&mut Cursor<&[u8]>) -> IOResult<bool> = |cursor: &mut Cursor<&[u8]>| READER_U8(cursor).map(|value| value != 0);
+pub const READER_STRING: fn(&mut Cursor<&[u8]>) -> IOResult<String> = |cursor: &mut Cursor<&[u8]>| {
     let mut buffer = Vec::new();
     cursor.read_to_end(&mut buffer)?;
     if let Some(pos) = buffer.iter().position(|&b| b == 0) {
@@ -41,33 +41,54 @@ pub const READER_STRING: fn(&mut Cursor<&Vec<u8>>) -> IOResult<String> = |cursor
     String::from_utf8(buffer).map_err(|e| IOError::new(ErrorKind::InvalidData, e.to_string().as_str()))
 };
 
-pub const READER_AI8: fn(&mut Cursor<&Vec<u8>>, &mut [i8]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i8]| { cursor.read_i8_into(arr) };
-pub const READER_AI16: fn(&mut Cursor<&Vec<u8>>, &mut [i16]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i16]| { cursor.read_i16_into::<LittleEndian>(arr) };
-pub const READER_AI32: fn(&mut Cursor<&Vec<u8>>, &mut [i32]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i32]| { cursor.read_i32_into::<LittleEndian>(arr) };
-pub const READER_AI64: fn(&mut Cursor<&Vec<u8>>, &mut [i64]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i64]| { cursor.read_i64_into::<LittleEndian>(arr) };
-pub const READER_AU8: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u8]| { cursor.read_u8_into(arr) };
-pub const READER_AU16: fn(&mut Cursor<&Vec<u8>>, &mut [u16]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u16]| { cursor.read_u16_into::<LittleEndian>(arr) };
-pub const READER_AU32: fn(&mut Cursor<&Vec<u8>>, &mut [u32]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u32]| { cursor.read_u32_into::<LittleEndian>(arr) };
-pub const READER_AU64: fn(&mut Cursor<&Vec<u8>>, &mut [u64]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u64]| { cursor.read_u64_into::<LittleEndian>(arr) };
-pub const READER_AF32: fn(&mut Cursor<&Vec<u8>>, &mut [f32]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [f32]| { cursor.read_f32_into::<LittleEndian>(arr) };
-pub const READER_AF64: fn(&mut Cursor<&Vec<u8>>, &mut [f64]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [f64]| { cursor.read_f64_into::<LittleEndian>(arr) };
-pub const READER_ABI16: fn(&mut Cursor<&Vec<u8>>, &mut [i16]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i16]| { cursor.read_i16_into::<BigEndian>(arr) };
-pub const READER_ABI32: fn(&mut Cursor<&Vec<u8>>, &mut [i32]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i32]| { cursor.read_i32_into::<BigEndian>(arr) };
-pub const READER_ABI64: fn(&mut Cursor<&Vec<u8>>, &mut [i64]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [i64]| { cursor.read_i64_into::<BigEndian>(arr) };
-pub const READER_ABU16: fn(&mut Cursor<&Vec<u8>>, &mut [u16]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u16]| { cursor.read_u16_into::<BigEndian>(arr) };
-pub const READER_ABU32: fn(&mut Cursor<&Vec<u8>>, &mut [u32]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u32]| { cursor.read_u32_into::<BigEndian>(arr) };
-pub const READER_ABU64: fn(&mut Cursor<&Vec<u8>>, &mut [u64]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u64]| { cursor.read_u64_into::<BigEndian>(arr) };
-pub const READER_ABF32: fn(&mut Cursor<&Vec<u8>>, &mut [f32]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [f32]| { cursor.read_f32_into::<BigEndian>(arr) };
-pub const READER_ABF64: fn(&mut Cursor<&Vec<u8>>, &mut [f64]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [f64]| { cursor.read_f64_into::<BigEndian>(arr) };
-pub const READER_ABOOL: fn(&mut Cursor<&Vec<u8>>, &mut [bool]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [bool]| {
+pub const READER_AI8: fn(&mut Cursor<&[u8]>, &mut [i8]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i8]| { cursor.read_i8_into(arr) };
+pub const READER_AI16: fn(&mut Cursor<&[u8]>, &mut [i16]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i16]| { cursor.read_i16_into::<LittleEndian>(arr) };
+pub const READER_AI32: fn(&mut Cursor<&[u8]>, &mut [i32]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i32]| { cursor.read_i32_into::<LittleEndian>(arr) };
+pub const READER_AI64: fn(&mut Cursor<&[u8]>, &mut [i64]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i64]| { cursor.read_i64_into::<LittleEndian>(arr) };
+pub const READER_AU8: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u8]| { cursor.read_u8_into(arr) };
+pub const READER_AU16: fn(&mut Cursor<&[u8]>, &mut [u16]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u16]| { cursor.read_u16_into::<LittleEndian>(arr) };
+pub const READER_AU32: fn(&mut Cursor<&[u8]>, &mut [u32]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u32]| { cursor.read_u32_into::<LittleEndian>(arr) };
+pub const READER_AU64: fn(&mut Cursor<&[u8]>, &mut [u64]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u64]| { cursor.read_u64_into::<LittleEndian>(arr) };
+pub const READER_AF32: fn(&mut Cursor<&[u8]>, &mut [f32]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [f32]| { cursor.read_f32_into::<LittleEndian>(arr) };
+pub const READER_AF64: fn(&mut Cursor<&[u8]>, &mut [f64]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [f64]| { cursor.read_f64_into::<LittleEndian>(arr) };
+pub const READER_ABI16: fn(&mut Cursor<&[u8]>, &mut [i16]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i16]| { cursor.read_i16_into::<BigEndian>(arr) };
+pub const READER_ABI32: fn(&mut Cursor<&[u8]>, &mut [i32]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i32]| { cursor.read_i32_into::<BigEndian>(arr) };
+pub const READER_ABI64: fn(&mut Cursor<&[u8]>, &mut [i64]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [i64]| { cursor.read_i64_into::<BigEndian>(arr) };
+pub const READER_ABU16: fn(&mut Cursor<&[u8]>, &mut [u16]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u16]| { cursor.read_u16_into::<BigEndian>(arr) };
+pub const READER_ABU32: fn(&mut Cursor<&[u8]>, &mut [u32]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u32]| { cursor.read_u32_into::<BigEndian>(arr) };
+pub const READER_ABU64: fn(&mut Cursor<&[u8]>, &mut [u64]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u64]| { cursor.read_u64_into::<BigEndian>(arr) };
+pub const READER_ABF32: fn(&mut Cursor<&[u8]>, &mut [f32]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [f32]| { cursor.read_f32_into::<BigEndian>(arr) };
+pub const READER_ABF64: fn(&mut Cursor<&[u8]>, &mut [f64]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [f64]| { cursor.read_f64_into::<BigEndian>(arr) };
+pub const READER_ABOOL: fn(&mut Cursor<&[u8]>, &mut [bool]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [bool]| {
     for i in 0..arr.len() {
-        arr[i] = READER_BOOL(cursor).unwrap();
+        arr[i] = READER_BOOL(cursor)?;
     }
     return Ok(());
 };
-pub const READER_ASTRING: fn(&mut Cursor<&Vec<u8>>, &mut [String]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [String]| {
-    for i in 0..1 { //arr.len() {
-        arr[i] = READER_STRING(cursor).unwrap();
+//String arrays are fixed-width (each element padded with nulls) if the size is a multiple of the element count
+//and every chunk is null-padded. Otherwise they must hold exactly one null-terminated or null-separated item per element.
+pub const READER_ASTRING: fn(&mut Cursor<&[u8]>, &mut [String]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [String]| {
+    let mut buffer = Vec::new();
+    cursor.read_to_end(&mut buffer)?;
+    let elements = arr.len();
+    if elements == 0 {
+        return Ok(());
+    }
+    let width = buffer.len() / elements;
+    let fixed_width = width > 0 && buffer.len() % elements == 0 &&
+        buffer.chunks(width).all(|chunk| chunk.iter().skip_while(|&&b| b != 0).all(|&b| b == 0));
+    let items: Vec<&[u8]> = if fixed_width {
+        buffer.chunks(width).map(|chunk| chunk.split(|&b| b == 0).next().unwrap_or(&[])).collect()
+    } else if buffer.last() == Some(&0) && buffer.iter().filter(|&&b| b == 0).count() == elements {
+        buffer[..buffer.len() - 1].split(|&b| b == 0).collect()
+    } else {
+        buffer.split(|&b| b == 0).collect()
+    };
+    if items.len() != elements {
+        return Err(IOError::new(ErrorKind::InvalidData, format!("Invalid string array: expected {} elements, got {}", elements, items.len())));
+    }
+    for (i, item) in items.into_iter().enumerate() {
+        arr[i] = String::from_utf8(item.to_vec()).map_err(|e| IOError::new(ErrorKind::InvalidData, e.to_string().as_str()))?;
     }
     return Ok(());
 };
@@ -82,11 +103,11 @@ pub fn new_typed_raw_buffer<T> (count: usize)-> Vec<u8>{
     let element_size = std::mem::size_of::<T>();
     new_raw_buffer(element_size, count)
 }
-pub const READER_RAW: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u8]| {
+pub const READER_RAW: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u8]| {
     cursor.read_exact(arr)
 };
 
-pub const READER_BRAW16: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u8] | {
+pub const READER_BRAW16: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u8] | {
     cursor.read_exact(arr)?;
     for chunk in arr.chunks_exact_mut(2) {
         chunk.reverse();
@@ -94,7 +115,7 @@ pub const READER_BRAW16: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> =
     Ok(())
 };
 
-pub const READER_BRAW32: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u8] | {
+pub const READER_BRAW32: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u8] | {
     cursor.read_exact(arr)?;
     for chunk in arr.chunks_exact_mut(4) {
         chunk.reverse();
@@ -102,7 +123,7 @@ pub const READER_BRAW32: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> =
     Ok(())
 };
 
-pub const READER_BRAW64: fn(&mut Cursor<&Vec<u8>>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&Vec<u8>>, arr: &mut [u8] | {
+pub const READER_BRAW64: fn(&mut Cursor<&[u8]>, &mut [u8]) -> IOResult<()> = |cursor: &mut Cursor<&[u8]>, arr: &mut [u8] | {
     cursor.read_exact(arr)?;
     for chunk in arr.chunks_exact_mut(8) {
         chunk.reverse();
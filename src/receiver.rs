@@ -2,18 +2,27 @@ use crate::*;
 use crate::message::*;
 use crate::utils::*;
 use crate::sockets::*;
+use crate::capture::file_endpoint_path;
+use crate::compression::decompressed_size;
 use std::{io, thread};
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::ops::{Deref, DerefMut};
+use std::path::Path;
 use std::sync::{Arc, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread::JoinHandle;
 use zmq::{Context, PollItem, SocketEvent, SocketType};
 use std::time::{Duration, Instant};
+#[cfg(feature = "async")]
+use std::task::Poll;
 use uuid::Uuid;
 #[cfg(feature = "async")]
 use tokio::runtime::Handle;
+#[cfg(feature = "async")]
+use crate::stream::{ReceiverStream, StreamSource};
+#[cfg(feature = "metrics")]
+use crate::metrics::ReceiverMetrics;
 
 
 static RECEIVER_INDEX: Mutex<u32> = Mutex::new(0);
@@ -26,10 +35,12 @@ fn index() -> u32{
 }
 
 
-struct Stats {
-    counter_messages: u32,
-    counter_error: u32,
-    diagnostics: HashMap<String, HashMap<EndpointDiag, u32>>
+pub(crate) struct Stats {
+    pub(crate) counter_messages: u64,
+    pub(crate) counter_error: u64,
+    pub(crate) diagnostics: HashMap<String, HashMap<EndpointDiag, u64>>,
+    timing: HashMap<String, TimingStats>,
+    throughput: HashMap<String, Throughput>,
 }
 
 pub struct ReceivedMessage{
@@ -47,13 +58,41 @@ impl Stats{
         self.counter_error = self.counter_error + 1;
     }
 
+    fn increase_diag(& mut self, ep: &str, diag:EndpointDiag){
+        self.add_diag(ep, diag, 1);
+    }
+
+    fn diag_entry(& mut self, ep: &str, diag:EndpointDiag) -> &mut u64 {
+        //Only clone endpoint if entry is absent
+        if !self.diagnostics.contains_key(ep) {
+            self.diagnostics.insert(ep.to_string(), HashMap::new());
+        }
+        self.diagnostics.get_mut(ep).unwrap().entry(diag).or_insert(0)
+    }
+
+    fn add_diag(& mut self, ep: &str, diag:EndpointDiag, value:u64){
+        let entry = self.diag_entry(ep, diag);
+        *entry = entry.saturating_add(value);
+    }
+
+    fn max_diag(& mut self, ep: &str, diag:EndpointDiag, value:u64){
+        let entry = self.diag_entry(ep, diag);
+        *entry = (*entry).max(value);
+    }
+
     fn reset(& mut self){
         self.counter_messages = 0;
         self.counter_error = 0;
         self.diagnostics = HashMap::new();
+        self.timing = HashMap::new();
+        self.throughput = HashMap::new();
     }
 }
 
+fn timestamp_micros(timestamp: (u64, u64)) -> i64 {
+    (timestamp.0 as i64).saturating_mul(1_000_000).saturating_add((timestamp.1 / 1_000) as i64)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DeliveryMode {
     Inline,
@@ -108,12 +147,58 @@ impl ConnectionSockets {
     }
 }
 
+//Capture file read as an endpoint. The next record is read ahead, to merge several files by receive time.
+//Messages are reported under the file endpoint, but the ID and header state is kept per recorded endpoint.
+struct FileEndpoint {
+    endpoint: String,
+    reader: CaptureReader,
+    next: Option<BsreadResult<CapturedMessage>>,
+    //Recorded endpoint -> key of its state
+    sources: HashMap<String, String>,
+}
+
+impl FileEndpoint {
+    fn open(endpoint: &str, path: &Path) -> IOResult<Self> {
+        let mut reader = CaptureReader::open(path)?;
+        let next = reader.next();
+        Ok(Self { endpoint: endpoint.to_string(), reader, next, sources: HashMap::new() })
+    }
+
+    //Read errors are returned first
+    fn next_timestamp(&self) -> Option<(u64, u64)> {
+        match &self.next {
+            None => None,
+            Some(Ok(captured)) => Some(captured.timestamp),
+            Some(Err(_)) => Some((0, 0)),
+        }
+    }
+
+    //Returns the state key of the recorded endpoint, if any, and the frames
+    fn read(&mut self) -> IOResult<(Option<String>, Vec<SharedBuffer>)> {
+        let captured = self.next.take().ok_or_else(|| IOError::new(ErrorKind::UnexpectedEof, "End of capture file"))?;
+        self.next = self.reader.next();
+        let captured = captured?;
+        let source = captured.endpoint.map(|ep| {
+            //Only build the key if the source is new
+            if !self.sources.contains_key(&ep) {
+                let key = format!("{}#{}", self.endpoint, ep);
+                self.sources.insert(ep.clone(), key);
+            }
+            self.sources[&ep].clone()
+        });
+        Ok((source, captured.frames.into_iter().map(SharedBuffer::from_vec).collect()))
+    }
+}
+
 const VALID_ID_RANGE:u64 = 3600 * 24 * 100;
+//Time for the subscribers to connect to a forwarder
+const FORWARDER_STARTUP: Duration = Duration::from_millis(100);
 
 pub const CHECK_ID_POSITIVE:u64 = 1;
 pub const CHECK_ID_MONOTONIC:u64 = 2;
 pub const CHECK_ID_RANGE:u64 = 3;
 pub const CHECK_ID_PAST_RANGE:u64 = 4;
+pub const CHECK_ID_GAPS:u64 = 8;
 
 pub const CHECK_ALL:u64 = !0;
 
@@ -126,6 +211,7 @@ enum ReceiverCommand {
 
 pub struct Receiver {
     sockets: ConnectionSockets,
+    file_endpoints: Vec<FileEndpoint>,
     endpoints: Arc<RwLock<Vec<String>>>,
     connected: bool,
     socket_type: SocketType,
@@ -134,6 +220,8 @@ pub struct Receiver {
     check_mask: u64,
     bsread: Arc<Bsread>,
     fifo: Option<Arc<FifoQueue<ReceivedMessage>>>,
+    //Decoded size of the last received message, accounted in the buffer byte budget
+    last_message_size: usize,
     handle: Option<JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>>,
     #[cfg(feature = "async")]
     async_handle: Option<tokio::task::JoinHandle<Result<(), Box<dyn Error + Send + Sync>>>>,
@@ -144,10 +232,20 @@ pub struct Receiver {
     interrupted: Arc<AtomicBool>,
     delivery_mode: DeliveryMode,
     raw: bool,
+    zero_copy: bool,
+    lazy: bool,
+    channel_filter: Option<ChannelFilter>,
+    recorder: Option<Recorder>,
+    id_step: u64,
     connection_mode: ConnectionMode,
     socket_monitor: Option<SocketMonitor>,
+    #[cfg(feature = "metrics")]
+    monitor_states: Option<Arc<Mutex<HashMap<String, EndpointState>>>>,
     tx_cmd:crossbeam_channel::Sender<ReceiverCommand>,
     rx_cmd:crossbeam_channel::Receiver<ReceiverCommand>,
+    //Wakes the async listener on commands and interruptions
+    #[cfg(feature = "async")]
+    wakeup: Arc<tokio::sync::Notify>,
     tx_diag:crossbeam_channel::Sender<EndpointEvent>,
     rx_diag:crossbeam_channel::Receiver<EndpointEvent>,
     forked:bool,
@@ -171,21 +269,29 @@ impl Receiver{
             .into_iter()
             .map(str::to_string)
             .collect()));
-        let stats = Arc::new(RwLock::new(Stats{counter_messages:0, counter_error:0, diagnostics:HashMap::new()}));
+        let stats = Arc::new(RwLock::new(Stats{counter_messages:0, counter_error:0, diagnostics:HashMap::new(), timing:HashMap::new(), throughput:HashMap::new()}));
         let delivery_mode = DeliveryMode::Inline;
         let  interrupted = Arc::new(AtomicBool::new(false));
         let (tx_diag, rx_diag) = crossbeam_channel::unbounded();
         let (tx_cmd, rx_cmd) = crossbeam_channel::unbounded();
         let check_mask = CHECK_ALL;
         let socket_options = SocketOptions::new();
+        #[cfg(feature = "async")]
+        let wakeup = Arc::new(tokio::sync::Notify::new());
+        #[cfg(feature = "async")]
+        bsread.register_wakeup(&wakeup);
 
-        Ok(Self { sockets, endpoints, connected:false, socket_type, header_buffer: LimitedHashMap::void(), id_buffer: HashMap::new(), check_mask,
-            bsread, fifo:None, handle:None,
+        Ok(Self { sockets, file_endpoints: Vec::new(), endpoints, connected:false, socket_type, header_buffer: LimitedHashMap::void(), id_buffer: HashMap::new(), check_mask,
+            bsread, fifo:None, last_message_size: 0, handle:None,
             stats, index,
-            forwarder_config:None, forwarder:None,interrupted, delivery_mode , raw: false,connection_mode,
+            forwarder_config:None, forwarder:None,interrupted, delivery_mode , raw: false, zero_copy: false, lazy: false, channel_filter: None, recorder: None, id_step: 1, connection_mode,
             socket_monitor:None, tx_cmd, rx_cmd, tx_diag,rx_diag, forked: false, socket_options,
+            #[cfg(feature = "metrics")]
+            monitor_states: None,
             #[cfg(feature = "async")]
             async_handle:None,
+            #[cfg(feature = "async")]
+            wakeup,
         })
     }
 
@@ -198,6 +304,8 @@ impl Receiver{
         let (tx, rx) = crossbeam_channel::bounded(1);
         self.tx_cmd.send(command(tx))
             .map_err(|_| {IOError::new(std::io::ErrorKind::BrokenPipe,"Receiver thread is not running",)})?;
+        #[cfg(feature = "async")]
+        self.wakeup.notify_one();
         rx.recv().map_err(|_| {IOError::new(std::io::ErrorKind::BrokenPipe,"Receiver thread terminated",)})?
     }
 
@@ -286,6 +394,13 @@ impl Receiver{
     }
 
     fn connect_endpoint(&mut self, endpoint: &str) -> IOResult<()> {
+        if let Some(path) = file_endpoint_path(endpoint) {
+            if !self.file_endpoints.iter().any(|file| file.endpoint == endpoint) {
+                self.file_endpoints.push(FileEndpoint::open(endpoint, path)?);
+            }
+            self.set_header_buffer_size(self.header_buffer_size());
+            return Ok(());
+        }
         let context = self.bsread.context();
         let socket_type = self.socket_type();
         let index = self.index;
@@ -297,10 +412,11 @@ impl Receiver{
                 match sockets.get(endpoint){
                     None => {
                         let mut socket = TrackedSocket::new(context, socket_type, index)?;
-                        socket.connect(endpoint)?;
+                        //Monitored before connecting, so that no connection event is missed
                         if let Some(socket_monitor) = &self.socket_monitor {
                             socket.enable_monitoring(self.bsread.context(), &socket_monitor, Some(endpoint.to_string()))?;
                         }
+                        socket.connect(endpoint)?;
                         self.socket_options.set(socket.socket())?;
                         sockets.insert(endpoint.to_string(), socket);
                         self.sockets.update_poll_items();
@@ -309,12 +425,16 @@ impl Receiver{
                 }
             }
         }
-        self.set_header_buffer_size(self.connections());
+        self.set_header_buffer_size(self.header_buffer_size());
 
         Ok(())
     }
 
     fn disconnect_endpoint(&mut self, endpoint: &str)  {
+        if file_endpoint_path(endpoint).is_some() {
+            self.file_endpoints.retain(|file| file.endpoint != endpoint);
+            return;
+        }
         match &mut self.sockets {
             ConnectionSockets::Shared { socket } => {
                 socket.disconnect_endpoint(endpoint);
@@ -354,20 +474,68 @@ impl Receiver{
         self.raw
     }
 
-    fn process(&mut self, endpoint: &Option<String>, message_parts:Vec<Vec<u8>>) -> IOResult<Message> {
+    /// In zero-copy mode numeric arrays reference the received frames and are decoded on access.
+    pub fn set_zero_copy(&mut self, zero_copy:bool) {
+        self.zero_copy = zero_copy;
+    }
+    pub fn is_zero_copy(&self) -> bool{
+        self.zero_copy
+    }
+
+    /// In lazy mode messages keep the channel frames, and channels are decoded on first access.
+    pub fn set_lazy(&mut self, lazy:bool) {
+        self.lazy = lazy;
+    }
+    pub fn is_lazy(&self) -> bool{
+        self.lazy
+    }
+
+    /// Only the channels selected by the filter are decoded. The selection is evaluated on data header changes.
+    pub fn set_channel_filter(&mut self, channel_filter:Option<ChannelFilter>) {
+        self.channel_filter = channel_filter;
+        //Forces the evaluation of the new filter on the next message
+        self.header_buffer.clear();
+    }
+    pub fn channel_filter(&self) -> Option<&ChannelFilter>{
+        self.channel_filter.as_ref()
+    }
+
+    /// Received messages are written, as raw frames, to the recorder capture file.
+    pub fn set_recorder(&mut self, recorder:Option<Recorder>) {
+        self.recorder = recorder;
+    }
+    pub fn recorder(&self) -> Option<&Recorder>{
+        self.recorder.as_ref()
+    }
+
+    /// Expected increment of the pulse IDs, used for gap detection: e.g. the modulo of a dispatcher stream.
+    pub fn set_id_step(&mut self, id_step:u64) {
+        self.id_step = id_step.max(1);
+    }
+    pub fn id_step(&self) -> u64{
+        self.id_step
+    }
+
+    //For capture files, source is the state key of the recorded endpoint
+    fn process(&mut self, endpoint: &Option<String>, source: &Option<String>, message_parts:Vec<SharedBuffer>) -> BsreadResult<Message> {
         if let Some(sender) = self.forwarder.as_mut() {
             match sender.forward(&message_parts) {
                 Ok(_) => (),
                 Err(e) => log::warn!("Error forwarding message to {}: {}", sender.endpoint(), e),
             }
         }
-        let message =parse_message(message_parts, endpoint, &mut self.header_buffer, self.raw);
+        if let Some(recorder) = self.recorder.as_ref()
+            && let Err(e) = recorder.record(endpoint, &message_parts) {
+            log::warn!("Error recording message to {}: {}", recorder.path().display(), e);
+        }
+        let key = if source.is_some() {source} else {endpoint};
+        let message =parse_frames(message_parts, key, &mut self.header_buffer, self.raw, self.zero_copy, self.lazy, self.channel_filter.as_ref());
         match message {
             Ok(message) => {
-                self.check_message(message, endpoint)
+                self.check_message(message, endpoint, key)
             },
             Err(e) => {
-                if (e.kind() == DECOMPRESSION_ERROR){
+                if e.is_decompression() {
                     self.send_diag(&endpoint, EndpointDiag::DecompressionError);
                 } else {
                     self.send_diag(&endpoint, EndpointDiag::ParsingError);
@@ -378,16 +546,20 @@ impl Receiver{
     }
     //self.send_diag(endpoint, EndpointDiag::NonPositiveId);
 
-    fn check_message(&mut self, message:Message,  endpoint: &Option<String>) -> IOResult<(Message)> {
+    //Pulse IDs are tracked per key: the endpoint, or the recorded endpoint for capture files
+    fn check_message(&mut self, message:Message,  endpoint: &Option<String>, key: &Option<String>) -> BsreadResult<Message> {
         let id = message.id();
+        let invalid = |reason| BsreadError::validation(reason).with_endpoint(endpoint).with_pulse_id(id);
         if self.check_mask & CHECK_ID_POSITIVE != 0 {
             if id <=0 {
                 self.send_diag(&endpoint, EndpointDiag::NonPositiveId);
-                return Err(IOError::new(ErrorKind::InvalidData,"Non positive ID",));
+                return Err(invalid(ValidationError::NonPositiveId));
             }
         }
 
-        if self.check_mask & CHECK_ID_RANGE != 0 {
+        //Replayed pulse IDs are not compared to the current time
+        let replayed = endpoint.as_deref().is_some_and(|ep| file_endpoint_path(ep).is_some());
+        if self.check_mask & CHECK_ID_RANGE != 0 && !replayed {
             if let Ok(simulated_id) = current_id() {
                 let out_of_range = if self.check_mask & CHECK_ID_PAST_RANGE != 0 {
                     id.abs_diff(simulated_id) > VALID_ID_RANGE
@@ -396,27 +568,71 @@ impl Receiver{
                 };
                 if out_of_range {
                     self.send_diag(&endpoint, EndpointDiag::OutOfRangeId);
-                    return Err(IOError::new(ErrorKind::InvalidData, "Out of range ID", ));
+                    return Err(invalid(ValidationError::OutOfRangeId));
                 }
             }
         }
 
-        if self.check_mask & CHECK_ID_MONOTONIC != 0 {
-            if let Some(ep) = endpoint.clone() {
-                if let Some(last_id) = self.id_buffer.get(&ep){
-                    if *last_id > id{
-                        self.send_diag(&endpoint, EndpointDiag::DecreasingId);
-                        return Err(IOError::new(ErrorKind::InvalidData,"Decreasing ID"));
-                    } else if *last_id == id{
-                        self.send_diag(&endpoint, EndpointDiag::RepeatedId);
-                        return Err(IOError::new(ErrorKind::InvalidData,"Repeated ID"));
+        if self.check_mask & (CHECK_ID_MONOTONIC | CHECK_ID_GAPS) != 0 && let Some(key) = key.clone() {
+            if let Some(last_id) = self.id_buffer.get(&key){
+                let last_id = *last_id;
+                if self.check_mask & CHECK_ID_MONOTONIC != 0 {
+                    if last_id > id{
+                        self.send_diag(endpoint, EndpointDiag::DecreasingId);
+                        return Err(invalid(ValidationError::DecreasingId{last_id}));
+                    } else if last_id == id{
+                        self.send_diag(endpoint, EndpointDiag::RepeatedId);
+                        return Err(invalid(ValidationError::RepeatedId));
                     }
                 }
-                self.id_buffer.insert(ep, id);
+                if self.check_mask & CHECK_ID_GAPS != 0 && id > last_id && let Some(ep) = endpoint {
+                    self.check_gap(ep, last_id, id);
+                }
             }
+            self.id_buffer.insert(key, id);
         }
         if message.header_changed() {
             self.send_diag(&endpoint, EndpointDiag::HeaderChange);
+            for _ in message.channels().iter().filter(|ch| ch.config().is_opaque()) {
+                self.send_diag(&endpoint, EndpointDiag::UnknownType);
+            }
+        }
+        self.check_channels(message, endpoint)
+    }
+
+    //Pulse IDs skipped since the last message, given the expected ID step
+    fn check_gap(&mut self, ep: &str, last_id: u64, id: u64) {
+        let step = self.id_step;
+        let missing = (id - last_id - 1) / step;
+        if missing > 0 {
+            {
+                let mut stats = self.stats.write().unwrap();
+                stats.add_diag(ep, EndpointDiag::MissingPulses, missing);
+                stats.increase_diag(ep, EndpointDiag::Gaps);
+                stats.max_diag(ep, EndpointDiag::LargestGap, missing);
+            }
+            if self.socket_monitor.is_some() {
+                let _ = self.tx_diag.send(EndpointEvent::Gap(ep.to_string(), last_id + step, last_id + missing * step));
+            }
+        }
+    }
+
+    //Channel decoding errors are counted on reception, or on first access in lazy mode
+    fn check_channels(&mut self, mut message:Message,  endpoint: &Option<String>) -> BsreadResult<Message> {
+        if message.is_lazy() {
+            let stats = self.stats.clone();
+            let tx_diag = self.socket_monitor.is_some().then(|| self.tx_diag.clone());
+            let endpoint = endpoint.clone();
+            message.set_error_hook(Arc::new(move |_| {
+                stats.write().unwrap().increase_diag(endpoint.as_deref().unwrap_or(""), EndpointDiag::ChannelError);
+                if let (Some(tx_diag), Some(ep)) = (&tx_diag, &endpoint) {
+                    let _ = tx_diag.send(EndpointEvent::Diagnostic(ep.clone(), EndpointDiag::ChannelError));
+                }
+            }));
+        } else {
+            for _ in message.data().values().filter(|data| data.is_none()) {
+                self.send_diag(endpoint, EndpointDiag::ChannelError);
+            }
         }
         Ok(message)
     }
@@ -432,10 +648,27 @@ impl Receiver{
     }
 
 
-    fn _receive(&mut self) -> (Option<String>, IOResult<Vec<Vec<u8>>>) {
+    //Returns the endpoint, the state key of the recorded endpoint for capture files, and the frames
+    fn _receive(&mut self) -> (Option<String>, Option<String>, IOResult<Vec<SharedBuffer>>) {
+        //Capture files are read before the sockets, in order of receive time
+        if let Some(file) = self.file_endpoints.iter_mut().filter(|file| file.next.is_some()).min_by_key(|file| file.next_timestamp()) {
+            let endpoint = Some(file.endpoint.clone());
+            let sources = file.sources.len();
+            let (source, frames) = match file.read() {
+                Ok((source, frames)) => (source, Ok(frames)),
+                Err(e) => (None, Err(e)),
+            };
+            if file.sources.len() != sources {
+                self.set_header_buffer_size(self.header_buffer_size());
+            }
+            return (endpoint, source, frames);
+        }
+        if !self.file_endpoints.is_empty() && self.endpoints.read().unwrap().iter().all(|ep| file_endpoint_path(ep).is_some()) {
+            return (None, None, Err(IOError::new(ErrorKind::UnexpectedEof, "End of capture files")));
+        }
         match &mut self.sockets {
             ConnectionSockets::Shared { socket } => {
-                (None, socket.receive())
+                (None, None, socket.receive_frames())
             }
             ConnectionSockets::Individual { sockets, poll_endpoints, poll_ready_list }  => {
                 if poll_ready_list.is_empty(){
@@ -446,13 +679,13 @@ impl Receiver{
                         } else {
                             log::error!("Poll endpoint not found in sockets, updating: {}", endpoint);
                             self.sockets.update_poll_items();
-                            return (None,Err(IOError::new(ErrorKind::Other,"Poll endpoint not found")),);
+                            return (None, None, Err(IOError::new(ErrorKind::Other,"Poll endpoint not found")),);
                         }
                     }
                     //In same thread receive is blocking.When forked, must check commanfd
                     let timeout = if self.forked {10} else {-1};
                     if let Err(e) = zmq::poll(& mut poll_items, timeout) {
-                        return (None, Err(e.into()));
+                        return (None, None, Err(e.into()));
                     }
                     for (idx, item) in poll_items.iter().enumerate() {
                         if item.is_readable() {
@@ -463,48 +696,101 @@ impl Receiver{
 
                 if let Some(endpoint) = poll_ready_list.pop_front() {
                     if let Some(socket) = sockets.get(&endpoint) {
-                        return (Some(endpoint), socket.receive());
+                        return (Some(endpoint), None, socket.receive_frames());
                     };
                 }
 
-                (None,Err(IOError::new(ErrorKind::TimedOut,"No socket was readable")),)
+                (None, None, Err(IOError::new(ErrorKind::TimedOut,"No socket was readable")),)
             }
         }
     }
 
-    pub fn receive(&mut self) -> IOResult<ReceivedMessage> {
+    pub fn receive(&mut self) -> BsreadResult<ReceivedMessage> {
         if self.connections() ==0 {
-            return Err(IOError::new(ErrorKind::NotConnected,"No connected endpoint"));
+            return Err(BsreadError::transport(IOError::new(ErrorKind::NotConnected,"No connected endpoint")));
         }
-        let (endpoint, message_parts) = self._receive();
+        let (endpoint, source, message_parts) = self._receive();
+        let received = current_timestamp();
 
 
         let message_parts = message_parts.map_err(|e| {
-            if e.kind() != ErrorKind::TimedOut {
+            if e.kind() != ErrorKind::TimedOut && e.kind() != ErrorKind::UnexpectedEof {
                 //self.stats.lock().unwrap().increase_errors();
                 //TODO: Should we count socket errors?
                 self.send_diag(&endpoint, EndpointDiag::SocketError);
             }
-            e
+            BsreadError::transport(e).with_endpoint(&endpoint)
         })?;
 
-        let message = self.process(&endpoint, message_parts);
+        let frames = message_parts.clone();
+        let message = self.process(&endpoint, &source, message_parts);
         match message {
             Ok(msg) => {
                 self.stats.write().unwrap().increase_messages();
                 self.increse_stats(&endpoint,  EndpointDiag::Messages);
+                let key = if source.is_some() {&source} else {&endpoint};
+                self.update_timing(&endpoint, key, &msg, &frames, received);
+                self.last_message_size = self.update_throughput(&endpoint, key, &msg, &frames);
                 Ok(ReceivedMessage{endpoint, message:msg})
             }
             Err(e) => {
                 log::trace!("Receiver Error: {}", e);
                 self.stats.write().unwrap().increase_errors();
                 self.increse_stats(&endpoint,  EndpointDiag::Errors);
-                Err(IOError::new(e.kind(), e))
+                Err(e)
             }
         }
 
     }
 
+    /// Waits for a message without blocking the thread, registering the sockets in the tokio reactor.
+    /// Must be called within a tokio runtime.
+    #[cfg(feature = "async")]
+    pub async fn receive_async(&mut self) -> BsreadResult<ReceivedMessage> {
+        if self.connections() ==0 {
+            return Err(BsreadError::transport(IOError::new(ErrorKind::NotConnected,"No connected endpoint")));
+        }
+        self.wait_readable().await.map_err(BsreadError::transport)?;
+        self.receive()
+    }
+
+    //Only the wait is cancelled if the future is dropped: readable endpoints are kept in the ready list
+    #[cfg(feature = "async")]
+    async fn wait_readable(&mut self) -> IOResult<()> {
+        if self.file_endpoints.iter().any(|file| file.next.is_some()) {
+            return Ok(());
+        }
+        if !self.file_endpoints.is_empty() && self.endpoints.read().unwrap().iter().all(|ep| file_endpoint_path(ep).is_some()) {
+            return Ok(());
+        }
+        match &mut self.sockets {
+            ConnectionSockets::Shared { socket } => {
+                socket.register_async()?;
+                std::future::poll_fn(|cx| socket.poll_readable(cx)).await
+            }
+            ConnectionSockets::Individual { sockets, poll_endpoints, poll_ready_list } => {
+                if !poll_ready_list.is_empty() {
+                    return Ok(());
+                }
+                for socket in sockets.values_mut() {
+                    socket.register_async()?;
+                }
+                std::future::poll_fn(|cx| {
+                    for endpoint in poll_endpoints.iter() {
+                        if let Some(socket) = sockets.get_mut(endpoint) {
+                            match socket.poll_readable(cx) {
+                                Poll::Ready(Ok(())) => poll_ready_list.push_back(endpoint.clone()),
+                                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                                Poll::Pending => {}
+                            }
+                        }
+                    }
+                    if poll_ready_list.is_empty() { Poll::Pending } else { Poll::Ready(Ok(())) }
+                }).await
+            }
+        }
+    }
+
     //Synchronous Mode: blocking, callback in same thread
     pub fn listen<F>(&mut self, callback: F, num_messages: Option<u32>) -> IOResult<()>
     where
@@ -514,68 +800,118 @@ impl Receiver{
             self.delivery_mode = DeliveryMode::Inline;
         }
         self.reset_counters();
-        if let Some(cfg) = self.forwarder_config.as_mut() {
-            match Sender::new(self.bsread.clone(), cfg.socket_type, cfg.transport.clone(), None, None, None,) {
-                Ok(mut sender) => {
-                    if let Err(e) = sender.start() {
-                        log::warn!("Error binding forwarder endpoint {}: {}",cfg.transport.endpoint(), e);
-                    } else {
-                        if let Some(hwm) = cfg.sndhwm {
-                            if let Err(e) = sender.set_sndhwm(hwm) {
-                                log::warn!("Error setting forwarder sndhwm to {}: {}", hwm, e);
-                            }
-                        }
-                        thread::sleep(Duration::from_millis(100));
-                        self.forwarder = Some(sender);
-                    }
-                }
-                Err(e) => {
-                    log::warn!("Error creating forwarder endpoint {}: {}",cfg.transport.endpoint(),e);
-                }
-            }
+        if self.start_forwarder() {
+            thread::sleep(FORWARDER_STARTUP);
         }
         self.connect()?;
         loop {
             let message= self.receive();
+            //Only file endpoints end: the listener returns once they have been entirely read
+            let end_of_stream = matches!(&message, Err(e) if e.kind() == ErrorKind::UnexpectedEof);
             if let Ok(msg) = message {
                 match &self.fifo {
                     None => {
                         callback(msg)
                     }
                     Some(fifo) => {
-                        fifo.add(msg)
+                        fifo.add_sized(msg, self.last_message_size);
                     }
                 }
             };
-            if num_messages.map_or(false, |m| self.message_count() >= m) {
+            if num_messages.is_some_and(|m| self.message_count() >= m as u64) {
                 break;
             }
-            if self.is_interrupted() {
+            if self.is_interrupted() || end_of_stream {
                 break;
             }
-            while let Ok(command) = self.rx_cmd.try_recv() {
-                match command {
-                    ReceiverCommand::Connect { response } => {
-                        let result = self.connect();
-                        let _ = response.send(result);
-                    }
-                    ReceiverCommand::Disconnect {response } => {
-                        self.disconnect();
-                        let _ = response.send(Ok(()));
-                    }
-                    ReceiverCommand::AddEndpoint { endpoint, response } => {
-                        let result = self.add_endpoint(&endpoint);
-                        let _ = response.send(result);
-                    }
-                    ReceiverCommand::RemoveEndpoint { endpoint, response } => {
-                        self.remove_endpoint(&endpoint);
-                        let _ = response.send(Ok(()));
+            self.process_commands();
+        }
+        self.stop_forwarder();
+        Ok(())
+    }
+
+    //Async Mode: waits for the sockets in the tokio reactor, callback awaited in the same task
+    #[cfg(feature = "async")]
+    async fn listen_async<F, Fut>(&mut self, mut callback: F, num_messages: Option<u32>) -> IOResult<()>
+    where
+        F: FnMut(ReceivedMessage) -> Fut,
+        Fut: Future<Output = ()>,
+    {
+        self.reset_counters();
+        if self.start_forwarder() {
+            tokio::time::sleep(FORWARDER_STARTUP).await;
+        }
+        self.connect()?;
+        let wakeup = self.wakeup.clone();
+        loop {
+            //Reception is cancelled on commands and interruptions. Notifications are kept until waited for.
+            let message = tokio::select! {
+                message = self.receive_async() => message,
+                _ = wakeup.notified() => Err(BsreadError::transport(IOError::new(ErrorKind::Interrupted, "Listener woken up"))),
+            };
+            //Without endpoints, waits for a command
+            if matches!(&message, Err(e) if e.kind() == ErrorKind::NotConnected) {
+                wakeup.notified().await;
+            }
+            let end_of_stream = matches!(&message, Err(e) if e.kind() == ErrorKind::UnexpectedEof);
+            if let Ok(msg) = message {
+                callback(msg).await;
+            };
+            if num_messages.is_some_and(|m| self.message_count() >= m as u64) {
+                break;
+            }
+            if self.is_interrupted() || end_of_stream {
+                break;
+            }
+            self.process_commands();
+        }
+        let _ = self.stop_forwarder();
+        Ok(())
+    }
+
+    fn start_forwarder(&mut self) -> bool {
+        if let Some(cfg) = self.forwarder_config.as_mut() {
+            match Sender::new(self.bsread.clone(), cfg.socket_type, cfg.transport.clone(), None, None, None,) {
+                Ok(mut sender) => {
+                    if let Err(e) = sender.start() {
+                        log::warn!("Error binding forwarder endpoint {}: {}",cfg.transport.endpoint(), e);
+                    } else {
+                        if let Some(hwm) = cfg.sndhwm && let Err(e) = sender.set_sndhwm(hwm) {
+                            log::warn!("Error setting forwarder sndhwm to {}: {}", hwm, e);
+                        }
+                        self.forwarder = Some(sender);
+                        return true;
                     }
                 }
+                Err(e) => {
+                    log::warn!("Error creating forwarder endpoint {}: {}",cfg.transport.endpoint(),e);
+                }
+            }
+        }
+        false
+    }
+
+    fn process_commands(&mut self) {
+        while let Ok(command) = self.rx_cmd.try_recv() {
+            match command {
+                ReceiverCommand::Connect { response } => {
+                    let result = self.connect();
+                    let _ = response.send(result);
+                }
+                ReceiverCommand::Disconnect {response } => {
+                    self.disconnect();
+                    let _ = response.send(Ok(()));
+                }
+                ReceiverCommand::AddEndpoint { endpoint, response } => {
+                    let result = self.add_endpoint(&endpoint);
+                    let _ = response.send(result);
+                }
+                ReceiverCommand::RemoveEndpoint { endpoint, response } => {
+                    self.remove_endpoint(&endpoint);
+                    let _ = response.send(Ok(()));
+                }
             }
         }
-        self.stop_forwarder();
-        Ok(())
     }
 
     //Threaded Mode: non-blocking, callback in another thread
@@ -592,6 +928,11 @@ impl Receiver{
         let producer_fifo = self.fifo.clone();
         let producer_stats = Arc::clone(&self.stats);
         let raw = self.raw;
+        let zero_copy = self.zero_copy;
+        let lazy = self.lazy;
+        let channel_filter = self.channel_filter.clone();
+        let recorder = self.recorder.clone();
+        let id_step = self.id_step;
         let thread_name = self.to_string();
         let socket_monitor = self.socket_monitor.take();
         let tx_diag = self.tx_diag.clone();
@@ -601,7 +942,7 @@ impl Receiver{
             .name(thread_name)
             .spawn(move || -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
                 listen_task(endpoints, socket_type, connection_mode, callback, num_messages, producer_fifo, producer_stats,
-                            forwarder_config, interrupted_context, interrupted_self, raw, socket_monitor, tx_diag, rx_cmd)
+                            forwarder_config, interrupted_context, interrupted_self, raw, zero_copy, lazy, channel_filter, recorder, id_step, socket_monitor, tx_diag, rx_cmd)
             })
             .expect("Failed to spawn thread");
 
@@ -609,6 +950,32 @@ impl Receiver{
         self.delivery_mode = DeliveryMode::Threaded;
     }
 
+    //Async mode, feeding a stream: the listener task waits while the stream buffer is full
+    #[cfg(feature = "async")]
+    pub(crate) fn fork_stream(&mut self, tx: tokio::sync::mpsc::Sender<ReceivedMessage>) {
+        let interrupted = Arc::clone(&self.interrupted);
+        let callback = move |msg| {
+            let tx = tx.clone();
+            let interrupted = interrupted.clone();
+            async move {
+                //Stops if the stream was dropped
+                if tx.send(msg).await.is_err() {
+                    interrupted.store(true, Ordering::Relaxed);
+                }
+            }
+        };
+        self.spawn_listener(callback, None, &Handle::current());
+    }
+
+    /// Starts receiving in a task of the current tokio runtime, returning the messages as an asynchronous stream,
+    /// with up to buffer_size messages queued. Must be called within a tokio runtime.
+    #[cfg(feature = "async")]
+    pub fn into_stream(mut self, buffer_size: usize) -> ReceiverStream {
+        let (tx, rx) = tokio::sync::mpsc::channel(buffer_size.max(1));
+        self.fork_stream(tx);
+        ReceiverStream::new(rx, StreamSource::Receiver(Box::new(self)))
+    }
+
     pub fn join(& mut self) -> IOResult<()> {
         if let Some(handle) = self.handle.take() { // Take ownership of the handle
             self.handle = None;
@@ -635,6 +1002,51 @@ impl Receiver{
     where
         F: Fn(ReceivedMessage) -> Fut + Send + Sync + 'static,
         Fut: Future<Output = ()> + Send + 'static,
+    {
+        let handle  =  match handle{
+            None => {tokio::runtime::Handle::current()}
+            Some(handle) => {handle}
+        };
+        let callback_handle = handle.clone();
+
+        if concurrent {
+            let cb = move |msg: ReceivedMessage| {
+                callback_handle.spawn(callback(msg));
+                std::future::ready(())
+            };
+            self.spawn_listener(cb, num_messages, &handle);
+        } else {
+            //Messages of each endpoint are processed in order, in a dedicated task
+            let mut senders: HashMap<String, tokio::sync::mpsc::Sender<ReceivedMessage>> = HashMap::new();
+            let callback = Arc::new(callback);
+            let cb = move |msg: ReceivedMessage| {
+                let endpoint = msg.endpoint.clone().unwrap_or_default();
+                let sender = senders.entry(endpoint).or_insert_with(|| {
+                        let (tx, mut rx) =
+                            tokio::sync::mpsc::channel::<ReceivedMessage>(1000);
+                        let callback = callback.clone();
+                        callback_handle.spawn(async move {
+                            while let Some(msg) = rx.recv().await {
+                                callback(msg).await;
+                            }
+                        });
+                        tx
+                    })
+                    .clone();
+                async move {
+                    let _ = sender.send(msg).await;
+                }
+            };
+            self.spawn_listener(cb, num_messages, &handle);
+        }
+    }
+
+    //Listeners are tasks waiting on the sockets in the reactor, not threads
+    #[cfg(feature = "async")]
+    fn spawn_listener<F, Fut>(&mut self, callback: F, num_messages: Option<u32>, handle: &tokio::runtime::Handle)
+    where
+        F: FnMut(ReceivedMessage) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
     {
         self.reset_counters();
         let endpoints = self.endpoints.clone();
@@ -643,83 +1055,50 @@ impl Receiver{
         let interrupted_context = Arc::clone(self.bsread.interrupted());
         let interrupted_self = Arc::clone(&self.interrupted);
         let forwarder_config = self.forwarder_config.clone();
-        let producer_fifo =None;
         let producer_stats =self.stats.clone();
         let raw = self.raw;
+        let zero_copy = self.zero_copy;
+        let lazy = self.lazy;
+        let channel_filter = self.channel_filter.clone();
+        let recorder = self.recorder.clone();
+        let id_step = self.id_step;
         let socket_monitor = self.socket_monitor.take();
         let tx_diag = self.tx_diag.clone();
         let rx_cmd = self.rx_cmd.clone();
-
-        let handle  =  match handle{
-            None => {tokio::runtime::Handle::current()}
-            Some(handle) => {handle}
-        };
-        let callback_handle = handle.clone();
-
-        let join_handle = if concurrent {
-             handle.spawn_blocking(move || {
-                let cb = move |msg: ReceivedMessage| {
-                    let callback = callback(msg);
-                    callback_handle.spawn(callback);
-                };
-
-                listen_task(endpoints, socket_type, connection_mode, cb,
-                            num_messages, producer_fifo, producer_stats,
-                            forwarder_config, interrupted_context, interrupted_self, raw,
-                            socket_monitor, tx_diag, rx_cmd)
-            })
-        } else {
-                //let shared_callback = Arc::new(Mutex::new(callback));
-                handle.spawn_blocking(move || {
-                    let senders:Arc<Mutex<HashMap<String, tokio::sync::mpsc::Sender<ReceivedMessage>>>>
-                        = Arc::new(Mutex::new(HashMap::new()));
-                    let senders_cb = senders.clone();
-                    let runtime = callback_handle.clone();
-                    let callback = Arc::new(callback);
-                    let cb = move |msg: ReceivedMessage| {
-                        let endpoint = msg.endpoint.clone().unwrap_or_default();
-                        let sender = {
-                            let mut senders = senders_cb.lock().unwrap();
-                            senders.entry(endpoint).or_insert_with(|| {
-                                    let (tx, mut rx) =
-                                        tokio::sync::mpsc::channel::<ReceivedMessage>(1000);
-                                    let callback = callback.clone();
-                                    runtime.spawn(async move {
-                                        while let Some(msg) = rx.recv().await {
-                                            callback(msg).await;
-                                        }
-                                    });
-                                    tx
-                                })
-                                .clone()
-                        };
-                        // ZMQ receiver thread is blocking, so use blocking_send
-                        sender.blocking_send(msg).unwrap();
-                    };
-                    listen_task(endpoints, socket_type, connection_mode, cb,
-                                num_messages, producer_fifo, producer_stats,
-                                forwarder_config, interrupted_context, interrupted_self,
-                                raw, socket_monitor, tx_diag, rx_cmd)
-            })
-        };
+        let wakeup = self.wakeup.clone();
+
+        let join_handle = handle.spawn(async move {
+            listen_task_async(endpoints, socket_type, connection_mode, callback,
+                              num_messages, producer_stats,
+                              forwarder_config, interrupted_context, interrupted_self, raw, zero_copy, lazy, channel_filter, recorder, id_step,
+                              socket_monitor, tx_diag, rx_cmd, wakeup).await
+        });
         self.delivery_mode = DeliveryMode::Async;
         self.async_handle = Some(join_handle);
     }
 
     #[cfg(feature = "async")]
     pub async fn join_async(&mut self) -> IOResult<()> {
-        if let Some(handle) = self.async_handle.take() {
-            match handle.await {
-                Ok(result) => result.map_err(|e| {
-                    io::Error::new(io::ErrorKind::Other, e.to_string())
-                }),
-                Err(e) => Err(io::Error::new(
-                    io::ErrorKind::Other,format!("Tokio join error: {}", e),
-                )),
-            }
-        } else {
-            Ok(())
-        }
+        std::future::poll_fn(|cx| self.poll_join_async(cx)).await
+    }
+
+    //Completion of the async listener, Ready(Ok) if there is none
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_join_async(&mut self, cx: &mut std::task::Context<'_>) -> Poll<IOResult<()>> {
+        let Some(handle) = self.async_handle.as_mut() else {
+            return Poll::Ready(Ok(()));
+        };
+        let result = match std::pin::Pin::new(handle).poll(cx) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Ok(result)) => result.map_err(|e| {
+                io::Error::new(io::ErrorKind::Other, e.to_string())
+            }),
+            Poll::Ready(Err(e)) => Err(io::Error::new(
+                io::ErrorKind::Other,format!("Tokio join error: {}", e),
+            )),
+        };
+        self.async_handle = None;
+        Poll::Ready(result)
     }
 
     pub fn is_running(&self) -> bool {
@@ -731,10 +1110,16 @@ impl Receiver{
 
     //Buffered mode: non-blocking, messages buffered ibn another thread
     pub fn start(&mut self, buffer_size:usize) -> IOResult<()> {
+        self.start_with_policy(buffer_size, OverflowPolicy::DropOldest, None)
+    }
+
+    /// Buffered mode, with the policy applied when the buffer is full: either buffer_size messages are buffered,
+    /// or their decoded size exceeds max_bytes. With OverflowPolicy::Wait reception stops until messages are consumed.
+    pub fn start_with_policy(&mut self, buffer_size:usize, policy: OverflowPolicy, max_bytes: Option<usize>) -> IOResult<()> {
         if self.fifo.is_some(){
             return Err(IOError::new(ErrorKind::AlreadyExists, "Receiver already started"));
         }
-        self.fifo = Some(Arc::new(FifoQueue::new(buffer_size)));
+        self.fifo = Some(Arc::new(FifoQueue::with_policy(buffer_size, policy, max_bytes)));
         self.reset_counters();
 
         fn callback(_: ReceivedMessage) -> () {}
@@ -745,6 +1130,12 @@ impl Receiver{
 
     pub fn interrupt(&self) {
         self.interrupted.store(true, Ordering::Relaxed);
+        #[cfg(feature = "async")]
+        self.wakeup.notify_one();
+        //Releases the listener if waiting for space in the buffer
+        if let Some(fifo) = &self.fifo {
+            fifo.close();
+        }
     }
 
     pub fn is_interrupted(&self) ->bool {
@@ -813,15 +1204,15 @@ impl Receiver{
     pub fn connections(&self) -> usize {
         self.endpoints.read().unwrap().len()
     }
-    pub fn available(&self) -> u32 {
+    pub fn available(&self) -> u64 {
         if let Some(fifo) = &self.fifo {
-            fifo.available_count() as u32
+            fifo.available_count() as u64
         } else {
             0
         }
     }
 
-    pub fn dropped(&self) -> u32 {
+    pub fn dropped(&self) -> u64 {
         if let Some(fifo) = &self.fifo {
             fifo.dropped_count()
         } else {
@@ -829,40 +1220,129 @@ impl Receiver{
         }
     }
 
+    #[cfg(feature = "metrics")]
+    pub(crate) fn metrics(&self) -> ReceiverMetrics {
+        ReceiverMetrics { index: self.index, endpoints: self.endpoints.clone(), stats: self.stats.clone(), fifo: self.fifo.clone(),
+            endpoint_states: self.monitor_states.clone(), shared_monitor: false }
+    }
+
     fn increse_stats(& mut self, endpoint: &Option<String>, diag:EndpointDiag){
         let ep: &str = endpoint.as_deref().unwrap_or("");
         //*self.stats.lock().unwrap().diagnostics.entry(ep.clone()).or_insert( HashMap::new()).entry(diag).or_insert(0) += 1;
-        //Only clone endpoint if entry is absent
-        let mut stats = self.stats.write().unwrap();
-        let map = if let Some(map) = stats.diagnostics.get_mut(ep) {
-            map
-        } else {
-            stats.diagnostics.entry(ep.to_string()).or_insert_with(HashMap::new)
-        };
-        *map.entry(diag).or_insert(0) += 1;
+        self.stats.write().unwrap().increase_diag(ep, diag);
     }
 
     fn remove_stats(& mut self, endpoint: &str){
         let mut stats = self.stats.write().unwrap();
         stats.diagnostics.remove(endpoint);
+        stats.timing.remove(endpoint);
+        stats.throughput.remove(endpoint);
+    }
+
+    //Messages without global timestamp are not timed, and only channels with their own timestamp frame have a skew.
+    fn update_timing(& mut self, endpoint: &Option<String>, key: &Option<String>, message: &Message, frames: &[SharedBuffer], received: (u64, u64)){
+        if message.timestamp() == TIMESTAMP_NOW {
+            return;
+        }
+        let global = timestamp_micros(message.timestamp());
+        let channel_frames: Vec<&[SharedBuffer]> = frames.get(2..).unwrap_or_default().chunks_exact(2).collect();
+        let selected: Vec<&[SharedBuffer]> = match self.header_info(key, &message.hash()).and_then(|info| info.selection.as_ref()) {
+            Some(selection) => selection.iter().filter_map(|&index| channel_frames.get(index).copied()).collect(),
+            None => channel_frames,
+        };
+        let channels: Vec<i64> = selected.into_iter()
+            .filter(|frames| !frames[0].is_empty())
+            .filter_map(|frames| frame_timestamp(frames[1].as_slice()).ok().flatten())
+            .map(|timestamp| timestamp_micros(timestamp) - global)
+            .collect();
+        let ep: &str = endpoint.as_deref().unwrap_or("");
+        let mut stats = self.stats.write().unwrap();
+        //Only clone endpoint if entry is absent
+        if !stats.timing.contains_key(ep) {
+            stats.timing.insert(ep.to_string(), TimingStats::default());
+        }
+        let timing = stats.timing.get_mut(ep).unwrap();
+        timing.latency.record(timestamp_micros(received) - global);
+        for skew in channels {
+            timing.skew.record(skew);
+        }
     }
 
-    pub fn diagnostics(&self) -> HashMap<String, HashMap<EndpointDiag, u32>>{
+    /// Latency and skew of the channel timestamps, per endpoint.
+    pub fn timing_stats(&self) -> HashMap<String, TimingStats>{
+        self.stats.read().unwrap().timing.clone()
+    }
+
+    pub fn endpoint_timing_stats(& self,  endpoint: &str) -> Option<TimingStats> {
+        self.stats.read().unwrap().timing.get(endpoint).cloned()
+    }
+
+    //Cached data header of the message
+    fn header_info(&self, key: &Option<String>, hash: &String) -> Option<&DataHeaderInfo> {
+        match key {
+            Some(ep) => self.header_buffer.get(ep),
+            None => self.header_buffer.get(hash),
+        }.filter(|info| &info.hash == hash)
+    }
+
+    //Channel values are matched to the channels of the cached data header to get their decompressed sizes.
+    //Returns the decompressed size of the message.
+    fn update_throughput(& mut self, endpoint: &Option<String>, key: &Option<String>, message: &Message, frames: &[SharedBuffer]) -> usize {
+        let hash = message.hash();
+        let info = self.header_info(key, &hash);
+        let compressed: usize = frames.iter().map(SharedBuffer::len).sum();
+        let mut decompressed = compressed;
+        let mut channels = Vec::new();
+        if let Some(info) = info {
+            for (channel, value) in info.channels.iter().zip(frames.iter().skip(2).step_by(2)) {
+                if value.is_empty() {
+                    continue;
+                }
+                let config = channel.config();
+                let size = decompressed_size(value, config.compression(), config.is_little_endian()).unwrap_or(value.len());
+                decompressed = decompressed + size - value.len();
+                channels.push((config.name_str(), value.len(), size));
+            }
+        }
+        let ep: &str = endpoint.as_deref().unwrap_or("");
+        let mut stats = self.stats.write().unwrap();
+        //Only clone endpoint if entry is absent
+        if !stats.throughput.contains_key(ep) {
+            stats.throughput.insert(ep.to_string(), Throughput::new());
+        }
+        let throughput = stats.throughput.get_mut(ep).unwrap();
+        throughput.update(frames.len(), compressed, decompressed);
+        for (name, compressed, decompressed) in channels {
+            throughput.update_channel(name, compressed, decompressed);
+        }
+        decompressed
+    }
+
+    /// Received messages and bytes, and their rates, per endpoint.
+    pub fn throughput(&self) -> HashMap<String, Throughput>{
+        self.stats.read().unwrap().throughput.clone()
+    }
+
+    pub fn endpoint_throughput(& self,  endpoint: &str) -> Option<Throughput> {
+        self.stats.read().unwrap().throughput.get(endpoint).cloned()
+    }
+
+    pub fn diagnostics(&self) -> HashMap<String, HashMap<EndpointDiag, u64>>{
         self.stats.read().unwrap().diagnostics.clone()
     }
     pub fn diagnostics_endpoints(&self) -> Vec<String> {
         self.stats.read().unwrap().diagnostics.keys().cloned().collect()
     }
 
-    pub fn endpoint_diagnostics(& self,  endpoint: &str) -> Option<HashMap<EndpointDiag, u32>> {
+    pub fn endpoint_diagnostics(& self,  endpoint: &str) -> Option<HashMap<EndpointDiag, u64>> {
         self.stats.read().unwrap().diagnostics.get(endpoint).cloned()
     }
 
-    pub fn endpoint_diagnostic(& self,  endpoint: &str, diag:EndpointDiag) -> Option<u32> {
+    pub fn endpoint_diagnostic(& self,  endpoint: &str, diag:EndpointDiag) -> Option<u64> {
         self.stats.read().unwrap().diagnostics.get(endpoint)?.get(&diag).copied()
     }
 
-    pub fn header_changes(& self,  endpoint:  &str) -> u32 {
+    pub fn header_changes(& self,  endpoint:  &str) -> u64 {
         self.endpoint_diagnostic(endpoint, EndpointDiag::HeaderChange).unwrap_or(
             if let Some (x) =  self.endpoint_diagnostic(endpoint, EndpointDiag::Messages) {
                 1
@@ -872,11 +1352,11 @@ impl Receiver{
         )
     }
 
-    pub fn message_count(&self) -> u32 {
+    pub fn message_count(&self) -> u64 {
         self.stats.read().unwrap().counter_messages
     }
 
-    pub fn error_count(&self) -> u32 {
+    pub fn error_count(&self) -> u64 {
         self.stats.read().unwrap().counter_error
     }
 
@@ -884,6 +1364,11 @@ impl Receiver{
         self.stats.write().unwrap().reset()
     }
 
+    //One data header per endpoint, and per recorded endpoint of the capture files
+    fn header_buffer_size(&self) -> usize {
+        self.connections() + self.file_endpoints.iter().map(|file| file.sources.len()).sum::<usize>()
+    }
+
     fn set_header_buffer_size(&mut self, size:usize) {
         if self.header_buffer.is_void() {
             self.header_buffer = LimitedHashMap::new(size);
@@ -918,6 +1403,10 @@ impl Receiver{
                     }
                 }
             }
+            #[cfg(feature = "metrics")]
+            {
+                self.monitor_states = Some(socket_monitor.shared_endpoint_states());
+            }
             self.socket_monitor =Some(socket_monitor);
         }
         Ok(self.rx_diag.clone())
@@ -1038,25 +1527,27 @@ impl Receiver{
     }
 }
 
-fn listen_task<F>(
+//Receiver owned by a listener thread or task
+#[allow(clippy::too_many_arguments)]
+fn task_receiver(
     endpoints: Arc<RwLock<Vec<String>>>,
     socket_type: SocketType,
     connection_mode: ConnectionMode,
-    callback: F,
-    num_messages: Option<u32>,
     producer_fifo: Option<Arc<FifoQueue<ReceivedMessage>>>,
     producer_stats: Arc<RwLock<Stats>>,
     forwarder_config: Option<ForwarderConfig>,
     interrupted_context: Arc<AtomicBool>,
     interrupted_self: Arc<AtomicBool>,
     raw: bool,
+    zero_copy: bool,
+    lazy: bool,
+    channel_filter: Option<ChannelFilter>,
+    recorder: Option<Recorder>,
+    id_step: u64,
     socket_monitor: Option<SocketMonitor>,
     tx_diag: crossbeam_channel::Sender<EndpointEvent>,
     rx_cmd: crossbeam_channel::Receiver<ReceiverCommand>,
-) -> Result<(), Box<dyn Error + Send + Sync>>
-where
-    F: Fn(ReceivedMessage) + Send + 'static,
-{
+) -> IOResult<Receiver> {
     //let endpoints = endpoints.read().unwrap();
     //let endpoints = (!endpoints.is_empty())
     //    .then(|| endpoints.iter().map(String::as_str).collect());
@@ -1068,16 +1559,87 @@ where
     receiver.interrupted = interrupted_self;
     receiver.forwarder_config = forwarder_config;
     receiver.raw = raw;
+    receiver.zero_copy = zero_copy;
+    receiver.lazy = lazy;
+    receiver.channel_filter = channel_filter;
+    receiver.recorder = recorder;
+    receiver.id_step = id_step;
     receiver.socket_monitor = socket_monitor;
     receiver.tx_diag = tx_diag;
     receiver.rx_cmd = rx_cmd;
     receiver.forked = true;
     receiver.endpoints = endpoints;
+    Ok(receiver)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn listen_task<F>(
+    endpoints: Arc<RwLock<Vec<String>>>,
+    socket_type: SocketType,
+    connection_mode: ConnectionMode,
+    callback: F,
+    num_messages: Option<u32>,
+    producer_fifo: Option<Arc<FifoQueue<ReceivedMessage>>>,
+    producer_stats: Arc<RwLock<Stats>>,
+    forwarder_config: Option<ForwarderConfig>,
+    interrupted_context: Arc<AtomicBool>,
+    interrupted_self: Arc<AtomicBool>,
+    raw: bool,
+    zero_copy: bool,
+    lazy: bool,
+    channel_filter: Option<ChannelFilter>,
+    recorder: Option<Recorder>,
+    id_step: u64,
+    socket_monitor: Option<SocketMonitor>,
+    tx_diag: crossbeam_channel::Sender<EndpointEvent>,
+    rx_cmd: crossbeam_channel::Receiver<ReceiverCommand>,
+) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    F: Fn(ReceivedMessage) + Send + 'static,
+{
+    let mut receiver = task_receiver(endpoints, socket_type, connection_mode, producer_fifo, producer_stats, forwarder_config,
+                                     interrupted_context, interrupted_self, raw, zero_copy, lazy, channel_filter, recorder, id_step, socket_monitor, tx_diag, rx_cmd)?;
     receiver
         .listen(callback, num_messages)
         .map_err(|e| format!("{}|{}", e.kind(), e).into())
 }
 
+#[cfg(feature = "async")]
+#[allow(clippy::too_many_arguments)]
+async fn listen_task_async<F, Fut>(
+    endpoints: Arc<RwLock<Vec<String>>>,
+    socket_type: SocketType,
+    connection_mode: ConnectionMode,
+    callback: F,
+    num_messages: Option<u32>,
+    producer_stats: Arc<RwLock<Stats>>,
+    forwarder_config: Option<ForwarderConfig>,
+    interrupted_context: Arc<AtomicBool>,
+    interrupted_self: Arc<AtomicBool>,
+    raw: bool,
+    zero_copy: bool,
+    lazy: bool,
+    channel_filter: Option<ChannelFilter>,
+    recorder: Option<Recorder>,
+    id_step: u64,
+    socket_monitor: Option<SocketMonitor>,
+    tx_diag: crossbeam_channel::Sender<EndpointEvent>,
+    rx_cmd: crossbeam_channel::Receiver<ReceiverCommand>,
+    wakeup: Arc<tokio::sync::Notify>,
+) -> Result<(), Box<dyn Error + Send + Sync>>
+where
+    F: FnMut(ReceivedMessage) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut receiver = task_receiver(endpoints, socket_type, connection_mode, None, producer_stats, forwarder_config,
+                                     interrupted_context, interrupted_self, raw, zero_copy, lazy, channel_filter, recorder, id_step, socket_monitor, tx_diag, rx_cmd)?;
+    receiver.wakeup = wakeup;
+    receiver
+        .listen_async(callback, num_messages)
+        .await
+        .map_err(|e| format!("{}|{}", e.kind(), e).into())
+}
+
 impl SocketConfig for Receiver {
     fn zmq_sockets(&self) -> Vec<&zmq::Socket> {
         match &self.sockets {
@@ -0,0 +1,18 @@
+use crate::*;
+
+/// Mapping of a struct to a set of bsread channels, usually implemented with `#[derive(BsreadRecord)]`
+/// (feature "derive").
+pub trait BsreadRecord: Sized {
+    /// Channel definitions. Array shapes not given explicitly are taken from the current field values.
+    fn channels(&self) -> BsreadResult<Vec<Box<dyn ChannelTrait>>>;
+
+    /// Channel data, in the same order as channels().
+    fn channel_data(&self, timestamp: (u64, u64)) -> Vec<Option<ChannelData>>;
+
+    /// Reads the fields from the message channels, converting the values with Message::get.
+    fn from_message(message: &Message) -> BsreadResult<Self>;
+
+    fn to_message(&self, pulse_id: u64, timestamp: (u64, u64)) -> BsreadResult<Message> {
+        Message::new_from_channel_vec(pulse_id, timestamp, &self.channels()?, self.channel_data(timestamp))
+    }
+}
@@ -0,0 +1,205 @@
+use crate::*;
+use crate::utils::{current_id, current_timestamp};
+use byteorder::{ByteOrder, LittleEndian};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+//Maximum sleep between checks of the interrupted flag
+const SLEEP_STEP: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplayTiming {
+    /// Same intervals between messages as when they were received.
+    Original,
+    /// Original intervals divided by the factor: 2.0 replays twice as fast.
+    Speed(f64),
+    /// No waiting between messages.
+    AsFastAsPossible,
+}
+
+/// Re-emits the messages of a capture file through a Sender.
+pub struct Replayer {
+    path: PathBuf,
+    timing: ReplayTiming,
+    rewrite_pulse_ids: bool,
+    rewrite_timestamps: bool,
+    looping: bool,
+    start_id: Option<u64>,
+    stop_id: Option<u64>,
+    max_messages: Option<u64>,
+}
+
+fn main_header(frames: &[Vec<u8>]) -> BsreadResult<HashMap<String, JsonValue>> {
+    let frame = frames.first().ok_or_else(|| BsreadError::parsing(ParsingError::FrameCount { expected: 2, actual: 0 }))?;
+    Ok(serde_json::from_slice(frame)?)
+}
+
+fn pulse_id(main_header: &HashMap<String, JsonValue>) -> BsreadResult<u64> {
+    main_header.get("pulse_id").and_then(JsonValue::as_u64).ok_or_else(|| BsreadError::parsing(ParsingError::InvalidField("pulse_id")))
+}
+
+impl Replayer {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            timing: ReplayTiming::Original,
+            rewrite_pulse_ids: false,
+            rewrite_timestamps: false,
+            looping: false,
+            start_id: None,
+            stop_id: None,
+            max_messages: None,
+        }
+    }
+
+    pub fn set_timing(&mut self, timing: ReplayTiming) {
+        self.timing = timing;
+    }
+    pub fn timing(&self) -> ReplayTiming {
+        self.timing
+    }
+
+    /// Replaces the pulse IDs by the current ones (the original ones if the ID t0 is not initialized),
+    /// always increasing, also when looping.
+    pub fn set_rewrite_pulse_ids(&mut self, rewrite_pulse_ids: bool) {
+        self.rewrite_pulse_ids = rewrite_pulse_ids;
+    }
+    pub fn is_rewrite_pulse_ids(&self) -> bool {
+        self.rewrite_pulse_ids
+    }
+
+    /// Replaces the global and channel timestamps by the current time.
+    pub fn set_rewrite_timestamps(&mut self, rewrite_timestamps: bool) {
+        self.rewrite_timestamps = rewrite_timestamps;
+    }
+    pub fn is_rewrite_timestamps(&self) -> bool {
+        self.rewrite_timestamps
+    }
+
+    /// Restarts from the beginning (or from the start ID) when the end of the replay is reached.
+    pub fn set_loop(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+    pub fn is_loop(&self) -> bool {
+        self.looping
+    }
+
+    /// Messages with an original pulse ID lower than the start ID are skipped.
+    pub fn set_start_id(&mut self, start_id: Option<u64>) {
+        self.start_id = start_id;
+    }
+    pub fn start_id(&self) -> Option<u64> {
+        self.start_id
+    }
+
+    /// The replay ends after the message with this original pulse ID (or the first higher one).
+    pub fn set_stop_id(&mut self, stop_id: Option<u64>) {
+        self.stop_id = stop_id;
+    }
+    pub fn stop_id(&self) -> Option<u64> {
+        self.stop_id
+    }
+
+    /// Maximum number of messages to send, including loops.
+    pub fn set_max_messages(&mut self, max_messages: Option<u64>) {
+        self.max_messages = max_messages;
+    }
+    pub fn max_messages(&self) -> Option<u64> {
+        self.max_messages
+    }
+
+    fn rewrite(&self, frames: &mut [Vec<u8>], mut main_header: HashMap<String, JsonValue>, id: u64) -> BsreadResult<()> {
+        if self.rewrite_pulse_ids {
+            main_header.insert("pulse_id".to_string(), JsonValue::from(id));
+        }
+        if self.rewrite_timestamps {
+            let (secs, nanos) = current_timestamp();
+            main_header.insert("global_timestamp".to_string(), serde_json::json!({"sec": secs, "ns": nanos}));
+            //Channel timestamps: the second frame of each channel
+            for frame in frames.iter_mut().skip(3).step_by(2).filter(|frame| frame.len() == 16) {
+                LittleEndian::write_u64(&mut frame[..8], secs);
+                LittleEndian::write_u64(&mut frame[8..], nanos);
+            }
+        }
+        frames[0] = serde_json::to_vec(&main_header)?;
+        Ok(())
+    }
+
+    /// Replays the capture file, returning the number of messages sent.
+    /// Stops early if the Bsread context of the sender is interrupted.
+    pub fn replay(&self, sender: &mut Sender) -> BsreadResult<u64> {
+        let mut sent = 0u64;
+        let mut last_id = 0u64;
+        loop {
+            let mut sent_pass = 0u64;
+            //Reference times of the first message of the pass
+            let mut reference: Option<(Instant, (u64, u64))> = None;
+            for captured in CaptureReader::open(&self.path)? {
+                if self.max_messages.is_some_and(|max| sent >= max) || sender.is_interrupted() {
+                    return Ok(sent);
+                }
+                let CapturedMessage { timestamp, endpoint, mut frames } = match captured {
+                    Ok(captured) => captured,
+                    Err(e) => {
+                        log::warn!("Error reading {}: {}", self.path.display(), e);
+                        continue;
+                    }
+                };
+                let main_header = match main_header(&frames) {
+                    Ok(main_header) => main_header,
+                    Err(e) => {
+                        log::warn!("Skipping invalid message from {:?}: {}", endpoint, e);
+                        continue;
+                    }
+                };
+                let original_id = match pulse_id(&main_header) {
+                    Ok(id) => id,
+                    Err(e) => {
+                        log::warn!("Skipping message without pulse ID from {:?}: {}", endpoint, e);
+                        continue;
+                    }
+                };
+                if self.start_id.is_some_and(|start_id| original_id < start_id) {
+                    continue;
+                }
+                if self.stop_id.is_some_and(|stop_id| original_id > stop_id) {
+                    break;
+                }
+                self.wait(&mut reference, timestamp, sender);
+                let id = if self.rewrite_pulse_ids { current_id().unwrap_or(original_id).max(last_id + 1) } else { original_id };
+                if self.rewrite_pulse_ids || self.rewrite_timestamps {
+                    self.rewrite(&mut frames, main_header, id)?;
+                }
+                sender.forward(&frames).map_err(|e| e.with_pulse_id(id))?;
+                last_id = id;
+                sent += 1;
+                sent_pass += 1;
+            }
+            //Also stops if the pass is empty, to not loop forever
+            if !self.looping || sent_pass == 0 {
+                return Ok(sent);
+            }
+        }
+    }
+
+    fn wait(&self, reference: &mut Option<(Instant, (u64, u64))>, timestamp: (u64, u64), sender: &Sender) {
+        let speed = match self.timing {
+            ReplayTiming::Original => 1.0,
+            ReplayTiming::Speed(speed) if speed > 0.0 => speed,
+            _ => return,
+        };
+        let (start, first) = *reference.get_or_insert((Instant::now(), timestamp));
+        let elapsed = Duration::new(timestamp.0, timestamp.1 as u32).saturating_sub(Duration::new(first.0, first.1 as u32));
+        let target = start + elapsed.div_f64(speed);
+        while !sender.is_interrupted() {
+            let remaining = target.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            thread::sleep(remaining.min(SLEEP_STEP));
+        }
+    }
+}
@@ -13,11 +13,23 @@ use env_logger::fmt::Timestamp;
 use zmq::SocketType;
 use serde_json::Value as JsonValue;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use serde_json::Map as JsonMap;
 use serde_json::Number as JsonNumber;
 
 
+//Shared with metrics exporters, so updated atomically
+#[derive(Debug, Default)]
+pub(crate) struct SenderCounters {
+    pub(crate) messages: AtomicU64,
+    pub(crate) bytes: AtomicU64,
+    pub(crate) errors: AtomicU64,
+}
+
 pub struct Sender {
+    //Set while owned by an AsyncSender. Declared first, so that it is deregistered before the socket is closed
+    #[cfg(feature = "async")]
+    async_fd: Option<tokio::io::unix::AsyncFd<ZmqFd>>,
     socket: zmq::Socket,
     socket_type: SocketType,
     main_header: HashMap<String, JsonValue>,
@@ -29,6 +41,7 @@ pub struct Sender {
     pulse_id: u64,
     header_compression: Compression,
     started: bool,
+    counters: Arc<SenderCounters>,
 }
 
 impl Sender {
@@ -53,12 +66,14 @@ impl Sender {
             main_header.insert("dh_compression".to_string(), JsonValue::String(header_compression.to_string()));
         }
         let mut _self = Self { socket, socket_type, main_header:main_header, data_header: HashMap::new(), data_header_buffer: vec![],
-                bsread, transport, block,pulse_id:start_id, header_compression, started:false};
+                bsread, transport, block,pulse_id:start_id, header_compression, started:false, counters: Arc::new(SenderCounters::default()),
+                #[cfg(feature = "async")]
+                async_fd: None};
         _self.set_sndhwm(10); //By default only 10 messages queued
         Ok(_self)
     }
 
-    pub fn create_data_header(&mut self, channels: &Vec<Box<dyn ChannelTrait>>,)-> IOResult<()> {
+    pub fn create_data_header(&mut self, channels: &Vec<Box<dyn ChannelTrait>>,)-> BsreadResult<()> {
         self.data_header = create_data_header(channels)?;
         // Convert the HashMap to a BTreeMap to enforce key order
         let ordered_data_header: BTreeMap<_, _> = self.data_header.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
@@ -108,12 +123,17 @@ impl Sender {
     }
 
 
-    pub fn send(&mut self,  id:u64, timestamp: (u64,u64), channels: &Vec<Box<dyn ChannelTrait>>, channel_data: &Vec<Option<&ChannelData>>) -> IOResult<u64> {
+    pub fn send(&mut self,  id:u64, timestamp: (u64,u64), channels: &Vec<Box<dyn ChannelTrait>>, channel_data: &Vec<Option<&ChannelData>>) -> BsreadResult<u64> {
+        let result = self._send(id, timestamp, channels, channel_data);
+        self.count(result)
+    }
+
+    fn _send(&mut self,  id:u64, timestamp: (u64,u64), channels: &[Box<dyn ChannelTrait>], channel_data: &[Option<&ChannelData>]) -> BsreadResult<(u64, usize)> {
         if channel_data.len() ==0 {
-            return Err(IOError::new(ErrorKind::InvalidInput, "Empty channel data list"));
+            return Err(BsreadError::validation(ValidationError::EmptyChannelData));
         }
         if channel_data.len() != channels.len(){
-            return Err(IOError::new(ErrorKind::InvalidInput, "Invalid size of channel data list"));
+            return Err(BsreadError::validation(ValidationError::ChannelCount{expected: channels.len(), actual: channel_data.len()}));
         }
 
         let sent_id = self.update_main_header(id, timestamp);
@@ -126,36 +146,61 @@ impl Sender {
         let main_header_json = serde_json::to_string(&self.main_header)?;
         let blob = main_header_json.as_bytes();
         let main_header_buffer = (*blob).to_vec();
+        let mut bytes = main_header_buffer.len() + self.data_header_buffer.len();
 
-        self.socket.send(main_header_buffer, flags_more)?;
-        self.socket.send(&self.data_header_buffer, if valid_channels>0 {flags_more} else {flags_last} )?;
+        self.socket.send(main_header_buffer, flags_more).map_err(|e| self.transport_error(e, sent_id))?;
+        self.socket.send(&self.data_header_buffer, if valid_channels>0 {flags_more} else {flags_last} ).map_err(|e| self.transport_error(e, sent_id))?;
 
         let mut channel_index = 0;
         for i in 0..channels.len(){
             let ch = &channels[i];
             if let Some(channel_data) = &channel_data[i] {
                 let last = channel_index >= (valid_channels- 1);
-                let (data,tm) =  serialize_channel(&ch, &channel_data)?;
-                self.socket.send(data, flags_more)?;
-                self.socket.send(tm, if last {flags_last} else {flags_more})?;
+                let (data,tm) =  serialize_channel(&ch, &channel_data).map_err(|e| e.with_pulse_id(sent_id))?;
+                bytes += data.len() + tm.len();
+                self.socket.send(data, flags_more).map_err(|e| self.transport_error(e, sent_id))?;
+                self.socket.send(tm, if last {flags_last} else {flags_more}).map_err(|e| self.transport_error(e, sent_id))?;
                 channel_index = channel_index + 1;
             } ;
         }
-        Ok(sent_id)
+        Ok((sent_id, bytes))
+    }
+
+    pub fn forward<T: AsRef<[u8]>> (&mut self,  message_parts:&[T]) -> BsreadResult<()> {
+        let result = self._forward(message_parts);
+        self.count(result).map(|_| ())
     }
 
-    pub fn forward (&mut self,  message_parts:&Vec<Vec<u8>>) -> IOResult<()> {
+    fn _forward<T: AsRef<[u8]>> (&mut self,  message_parts:&[T]) -> BsreadResult<((), usize)> {
         let flags_last = if self.block {0} else {zmq::DONTWAIT};
         let flags_more = flags_last | zmq::SNDMORE;
         for (index, msg) in message_parts.iter().enumerate() {
             let is_last = index == message_parts.len() - 1;
-            self.socket.send(msg, if is_last {flags_last} else {flags_more})?;
+            self.socket.send(msg.as_ref(), if is_last {flags_last} else {flags_more})
+                .map_err(|e| BsreadError::transport(e).with_endpoint(&Some(self.endpoint())))?;
+        }
+        Ok(((), message_parts.iter().map(|msg| msg.as_ref().len()).sum()))
+    }
+
+    fn count<T>(&self, result: BsreadResult<(T, usize)>) -> BsreadResult<T> {
+        match result {
+            Ok((ret, bytes)) => {
+                self.counters.messages.fetch_add(1, Ordering::Relaxed);
+                self.counters.bytes.fetch_add(bytes as u64, Ordering::Relaxed);
+                Ok(ret)
+            }
+            Err(e) => {
+                self.counters.errors.fetch_add(1, Ordering::Relaxed);
+                Err(e)
+            }
         }
-        Ok(())
     }
 
+    fn transport_error(&self, e: zmq::Error, pulse_id: u64) -> BsreadError {
+        BsreadError::transport(e).with_endpoint(&Some(self.endpoint())).with_pulse_id(pulse_id)
+    }
 
-    pub fn send_message(&mut self,  message: &Message, create_data_header:bool) -> IOResult<u64> {
+    pub fn send_message(&mut self,  message: &Message, create_data_header:bool) -> BsreadResult<u64> {
         let empty_data_header = self.data_header_buffer.len() == 0;
         if create_data_header || empty_data_header {
             self.create_data_header(message.channels())?;
@@ -189,10 +234,48 @@ impl Sender {
         self.main_header.insert("global_timestamp".to_string(), JsonValue::Object(global_timestamp));
         id
     }
+
+    pub fn is_interrupted(&self) -> bool{
+        self.bsread.is_interrupted()
+    }
+
     pub fn is_started(&self) -> bool{
         self.started
     }
 
+    #[cfg(feature = "async")]
+    pub(crate) fn is_blocking(&self) -> bool {
+        self.block
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn set_blocking(&mut self, block: bool) {
+        self.block = block;
+    }
+
+    //Must be called within a tokio runtime
+    #[cfg(feature = "async")]
+    pub(crate) fn register_async(&mut self) -> IOResult<()> {
+        if self.async_fd.is_none() {
+            self.async_fd = Some(register_async(&self.socket)?);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn deregister_async(&mut self) {
+        self.async_fd = None;
+    }
+
+    //Ready when the socket accepts a message
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_writable(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<IOResult<()>> {
+        match &self.async_fd {
+            Some(async_fd) => poll_events(&self.socket, async_fd, zmq::POLLOUT, cx),
+            None => std::task::Poll::Ready(Err(IOError::new(ErrorKind::NotConnected, "Socket not registered"))),
+        }
+    }
+
     pub fn endpoint(&self) -> String {
         self.transport.endpoint()
     }
@@ -204,6 +287,30 @@ impl Sender {
     pub fn transport(&self) -> Transport {
         self.transport.clone()
     }
+
+    pub fn message_count(&self) -> u64 {
+        self.counters.messages.load(Ordering::Relaxed)
+    }
+
+    /// Total size of the sent frames.
+    pub fn byte_count(&self) -> u64 {
+        self.counters.bytes.load(Ordering::Relaxed)
+    }
+
+    pub fn error_count(&self) -> u64 {
+        self.counters.errors.load(Ordering::Relaxed)
+    }
+
+    pub fn reset_counters(&mut self) {
+        self.counters.messages.store(0, Ordering::Relaxed);
+        self.counters.bytes.store(0, Ordering::Relaxed);
+        self.counters.errors.store(0, Ordering::Relaxed);
+    }
+
+    #[cfg(any(feature = "metrics", feature = "async"))]
+    pub(crate) fn counters(&self) -> Arc<SenderCounters> {
+        self.counters.clone()
+    }
 }
 
 impl SocketConfig for Sender {
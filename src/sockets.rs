@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use std::thread;
 use serde::Serialize;
 use uuid::Uuid;
-use crate::IOResult;
+use crate::{IOResult, SharedBuffer};
 use crate::utils::app_name;
 
 #[derive(Clone, Debug)]
@@ -225,7 +225,12 @@ pub enum EndpointDiag {
     SocketError,
     ParsingError,
     DecompressionError,
-    HeaderChange
+    HeaderChange,
+    UnknownType,
+    ChannelError,
+    MissingPulses,  //Total number of skipped pulse IDs
+    Gaps,           //Number of discontinuities
+    LargestGap      //Maximum number of pulse IDs skipped at once
 }
 
 impl EndpointDiag {
@@ -239,14 +244,21 @@ impl EndpointDiag {
         EndpointDiag::SocketError,
         EndpointDiag::ParsingError,
         EndpointDiag::DecompressionError,
-        EndpointDiag::HeaderChange
+        EndpointDiag::HeaderChange,
+        EndpointDiag::UnknownType,
+        EndpointDiag::ChannelError,
+        EndpointDiag::MissingPulses,
+        EndpointDiag::Gaps,
+        EndpointDiag::LargestGap
     ];
 }
 
 #[derive(Clone, Debug)]
 pub enum EndpointEvent {
     State(String, EndpointState),
-    Diagnostic(String, EndpointDiag)
+    Diagnostic(String, EndpointDiag),
+    /// Skipped pulse IDs: first and last missing ID.
+    Gap(String, u64, u64)
 }
 
 
@@ -254,7 +266,8 @@ impl EndpointEvent {
     pub fn endpoint(&self) -> String {
         match self {
             EndpointEvent::State(endpoint, _)
-            | EndpointEvent::Diagnostic(endpoint, _) => endpoint.clone()
+            | EndpointEvent::Diagnostic(endpoint, _)
+            | EndpointEvent::Gap(endpoint, _, _) => endpoint.clone()
         }
     }
 }
@@ -416,6 +429,12 @@ impl SocketMonitor {
         let mut map = self.endpoint_states.lock().unwrap();
         map.clone()
     }
+
+    //Stays valid when the monitor is moved to a listening thread
+    #[cfg(feature = "metrics")]
+    pub(crate) fn shared_endpoint_states(&self) -> Arc<Mutex<HashMap<String, EndpointState>>> {
+        self.endpoint_states.clone()
+    }
 }
 
 
@@ -428,7 +447,49 @@ fn index() -> u32{
     }
 }
 
+//ZMQ_FD of a socket, registered in the tokio reactor
+#[cfg(feature = "async")]
+pub(crate) struct ZmqFd(std::os::fd::RawFd);
+
+#[cfg(feature = "async")]
+impl std::os::fd::AsRawFd for ZmqFd {
+    fn as_raw_fd(&self) -> std::os::fd::RawFd {
+        self.0
+    }
+}
+
+//Must be called within a tokio runtime. The AsyncFd must be dropped before the socket is closed.
+#[cfg(feature = "async")]
+pub(crate) fn register_async(socket: &zmq::Socket) -> IOResult<tokio::io::unix::AsyncFd<ZmqFd>> {
+    let fd = ZmqFd(socket.get_fd()?);
+    //SAFETY: the descriptor belongs to the socket, which outlives the AsyncFd
+    let async_fd = unsafe { tokio::io::unix::AsyncFd::register_with_interest(fd, tokio::io::Interest::READABLE) };
+    async_fd.map_err(|e| e.into_parts().1)
+}
+
+//ZMQ_FD is edge-triggered and signals any event: ZMQ_EVENTS tells if the socket is ready for the given events.
+#[cfg(feature = "async")]
+pub(crate) fn poll_events(socket: &zmq::Socket, async_fd: &tokio::io::unix::AsyncFd<ZmqFd>, events: zmq::PollEvents,
+                          cx: &mut std::task::Context<'_>) -> std::task::Poll<IOResult<()>> {
+    use std::task::Poll;
+    loop {
+        match socket.get_events() {
+            Ok(ready) if ready.intersects(events) => return Poll::Ready(Ok(())),
+            Ok(_) => {}
+            Err(e) => return Poll::Ready(Err(e.into())),
+        }
+        match async_fd.poll_read_ready(cx) {
+            Poll::Ready(Ok(mut guard)) => guard.clear_ready(),
+            Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+            Poll::Pending => return Poll::Pending,
+        }
+    }
+}
+
 pub struct TrackedSocket {
+    //Declared first, so that it is deregistered before the socket is closed
+    #[cfg(feature = "async")]
+    async_fd: Option<tokio::io::unix::AsyncFd<ZmqFd>>,
     socket: zmq::Socket,
     endpoints: Vec<String>,
     rec_index: u32,
@@ -441,7 +502,27 @@ impl TrackedSocket {
     pub fn new(context: &Context, socket_type: zmq::SocketType, rec_index: u32) -> IOResult<TrackedSocket> {
         let socket = context.socket(socket_type)?;
         let index =  index();
-        Ok (Self {socket, rec_index, endpoints: Vec::new(),topics: Vec::new(),monitoring: false, index })
+        Ok (Self {socket, rec_index, endpoints: Vec::new(),topics: Vec::new(),monitoring: false, index,
+            #[cfg(feature = "async")]
+            async_fd: None })
+    }
+
+    //Must be called within a tokio runtime
+    #[cfg(feature = "async")]
+    pub(crate) fn register_async(&mut self) -> IOResult<()> {
+        if self.async_fd.is_none() {
+            self.async_fd = Some(register_async(&self.socket)?);
+        }
+        Ok(())
+    }
+
+    //Takes &mut as zmq sockets are not Sync, so that waiting futures are Send.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_readable(&mut self, cx: &mut std::task::Context<'_>) -> std::task::Poll<IOResult<()>> {
+        match &self.async_fd {
+            Some(async_fd) => poll_events(&self.socket, async_fd, zmq::POLLIN, cx),
+            None => std::task::Poll::Ready(Err(std::io::Error::new(std::io::ErrorKind::NotConnected, "Socket not registered"))),
+        }
     }
 
     pub fn enable_monitoring(&mut self, context: &Context, monitor: &SocketMonitor, endpoint: Option<String>) -> IOResult<()> {
@@ -549,6 +630,19 @@ impl TrackedSocket {
         }
     }
 
+    /// Receives a multipart message keeping the zmq frames, without copying their contents.
+    pub fn receive_frames(&self) -> IOResult<Vec<SharedBuffer>> {
+        let mut frames = Vec::new();
+        loop {
+            let frame = self.socket.recv_msg(0)?;
+            frames.push(SharedBuffer::from_frame(frame));
+            if !self.socket.get_rcvmore()? {
+                break;
+            }
+        }
+        Ok(frames)
+    }
+
     pub fn socket(&self) -> &zmq::Socket{
         &self.socket
     }
@@ -0,0 +1,106 @@
+use crate::*;
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+pub(crate) enum StreamSource {
+    Receiver(Box<Receiver>),
+    Pool(Pool),
+}
+
+/// Asynchronous stream of the messages of a receiver or pool, created by `Receiver::into_stream` or `Pool::into_stream`.
+///
+/// Messages are received in tasks of the tokio runtime, and queued up to the buffer size: when it is full, reception
+/// waits for the stream to be polled. The stream ends when reception stops, yielding the listener error if any.
+/// Dropping the stream stops reception.
+pub struct ReceiverStream {
+    //Dropped first, releasing listeners waiting on a full buffer
+    rx: mpsc::Receiver<ReceivedMessage>,
+    source: StreamSource,
+    finished: bool,
+    //First listener error, while waiting for the other listeners of a pool
+    error: Option<IOError>,
+}
+
+impl ReceiverStream {
+    pub(crate) fn new(rx: mpsc::Receiver<ReceivedMessage>, source: StreamSource) -> Self {
+        Self { rx, source, finished: false, error: None }
+    }
+
+    pub fn message_count(&self) -> u64 {
+        match &self.source {
+            StreamSource::Receiver(receiver) => receiver.message_count(),
+            StreamSource::Pool(pool) => pool.message_count(),
+        }
+    }
+
+    pub fn error_count(&self) -> u64 {
+        match &self.source {
+            StreamSource::Receiver(receiver) => receiver.error_count(),
+            StreamSource::Pool(pool) => pool.error_count(),
+        }
+    }
+
+    fn interrupt(&self) {
+        match &self.source {
+            StreamSource::Receiver(receiver) => receiver.interrupt(),
+            StreamSource::Pool(pool) => {
+                for receiver in pool.receivers() {
+                    receiver.interrupt();
+                }
+            }
+        }
+    }
+
+    //Called once all listeners have released the buffer, so their tasks are about to complete
+    fn poll_join(&mut self, cx: &mut Context<'_>) -> Poll<IOResult<()>> {
+        let receivers: Vec<&mut Receiver> = match &mut self.source {
+            StreamSource::Receiver(receiver) => vec![receiver],
+            StreamSource::Pool(pool) => pool.receivers_mut().iter_mut().collect(),
+        };
+        let mut pending = false;
+        for receiver in receivers {
+            match receiver.poll_join_async(cx) {
+                Poll::Pending => pending = true,
+                Poll::Ready(Err(e)) if self.error.is_none() => self.error = Some(e),
+                Poll::Ready(_) => {}
+            }
+        }
+        if pending {
+            return Poll::Pending;
+        }
+        Poll::Ready(self.error.take().map_or(Ok(()), Err))
+    }
+}
+
+impl Stream for ReceiverStream {
+    type Item = IOResult<ReceivedMessage>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.finished {
+            return Poll::Ready(None);
+        }
+        match this.rx.poll_recv(cx) {
+            Poll::Ready(Some(msg)) => Poll::Ready(Some(Ok(msg))),
+            Poll::Ready(None) => match this.poll_join(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(result) => {
+                    this.finished = true;
+                    match result {
+                        Ok(()) => Poll::Ready(None),
+                        Err(e) => Poll::Ready(Some(Err(e))),
+                    }
+                }
+            },
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for ReceiverStream {
+    fn drop(&mut self) {
+        self.interrupt();
+    }
+}
@@ -0,0 +1,204 @@
+use crate::*;
+use crate::utils::FifoQueue;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+//Maximum wait on the event queue between checks of the pulse timeouts
+const EXPIRY_CHECK_INTERVAL_MS: u64 = 10;
+
+/// Messages of one pulse ID, one per source of the Synchronizer.
+pub struct SynchronizedEvent {
+    pub pulse_id: u64,
+    /// Messages indexed by source endpoint.
+    pub messages: HashMap<String, Message>,
+    /// Sources that did not report the pulse ID, in the order of the synchronizer sources.
+    pub missing: Vec<String>,
+}
+
+impl SynchronizedEvent {
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty()
+    }
+
+    pub fn message(&self, source: &str) -> Option<&Message> {
+        self.messages.get(source)
+    }
+
+    /// Data of a channel, from any of the messages containing it.
+    pub fn channel_data(&self, channel_name: &str) -> Option<&ChannelData> {
+        self.messages.values().find_map(|message| message.channel_data(channel_name))
+    }
+}
+
+struct PendingPulse {
+    created: Instant,
+    messages: HashMap<String, Message>,
+}
+
+struct SyncState {
+    pending: BTreeMap<u64, PendingPulse>,
+    last_emitted: Option<u64>,
+    complete: u64,
+    incomplete: u64,
+    late: u64,
+    evicted: u64,
+}
+
+/// Merges the messages of several sources into one event per pulse ID.
+///
+/// Events are emitted in increasing pulse ID order, once all the sources reported the pulse ID or
+/// the timeout, counted from the first message of the pulse, expired. A complete pulse therefore waits
+/// for older incomplete ones. At most max_pending pulses are buffered: beyond that the oldest are
+/// emitted incomplete. Messages of pulse IDs older than the last emitted one are discarded.
+pub struct Synchronizer {
+    sources: Vec<String>,
+    timeout: Duration,
+    max_pending: usize,
+    state: Mutex<SyncState>,
+    events: FifoQueue<SynchronizedEvent>,
+}
+
+impl Synchronizer {
+    /// Up to buffer_size events are kept until retrieved, dropping the oldest.
+    pub fn new(sources: Vec<&str>, timeout_ms: u64, max_pending: usize, buffer_size: usize) -> Self {
+        Self {
+            sources: sources.into_iter().map(str::to_string).collect(),
+            timeout: Duration::from_millis(timeout_ms),
+            max_pending: max_pending.max(1),
+            state: Mutex::new(SyncState { pending: BTreeMap::new(), last_emitted: None, complete: 0, incomplete: 0, late: 0, evicted: 0 }),
+            events: FifoQueue::new(buffer_size),
+        }
+    }
+
+    pub fn sources(&self) -> &Vec<String> {
+        &self.sources
+    }
+
+    pub fn timeout_ms(&self) -> u64 {
+        self.timeout.as_millis() as u64
+    }
+
+    pub fn max_pending(&self) -> usize {
+        self.max_pending
+    }
+
+    /// Adds a received message. Returns false if it is discarded, because its endpoint is not
+    /// one of the sources or because its pulse ID was already emitted.
+    pub fn add(&self, msg: ReceivedMessage) -> bool {
+        let Some(source) = msg.endpoint.filter(|endpoint| self.sources.contains(endpoint)) else {
+            return false;
+        };
+        let id = msg.message.id();
+        let mut state = self.state.lock().unwrap();
+        if state.last_emitted.is_some_and(|last| id <= last) {
+            state.late += 1;
+            return false;
+        }
+        state.pending.entry(id)
+            .or_insert_with(|| PendingPulse { created: Instant::now(), messages: HashMap::new() })
+            .messages.entry(source).or_insert(msg.message);
+        while state.pending.len() > self.max_pending {
+            state.evicted += 1;
+            self.emit_first(&mut state);
+        }
+        self.flush(&mut state);
+        true
+    }
+
+    //Emits the pulses in order while the oldest one is complete or expired
+    fn flush(&self, state: &mut SyncState) {
+        while let Some((_, pulse)) = state.pending.first_key_value()
+            && (pulse.messages.len() == self.sources.len() || pulse.created.elapsed() >= self.timeout) {
+            self.emit_first(state);
+        }
+    }
+
+    fn emit_first(&self, state: &mut SyncState) {
+        if let Some((pulse_id, pulse)) = state.pending.pop_first() {
+            let missing: Vec<String> = self.sources.iter().filter(|source| !pulse.messages.contains_key(*source)).cloned().collect();
+            if missing.is_empty() {
+                state.complete += 1;
+            } else {
+                state.incomplete += 1;
+            }
+            state.last_emitted = Some(pulse_id);
+            self.events.add(SynchronizedEvent { pulse_id, messages: pulse.messages, missing });
+        }
+    }
+
+    /// Emits the pulses whose timeout expired. Called by add, get and wait.
+    pub fn expire(&self) {
+        self.flush(&mut self.state.lock().unwrap());
+    }
+
+    /// Emits all the pending pulses, complete or not, e.g. at the end of the streams.
+    pub fn flush_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        while !state.pending.is_empty() {
+            self.emit_first(&mut state);
+        }
+    }
+
+    pub fn get(&self) -> Option<SynchronizedEvent> {
+        self.expire();
+        self.events.get()
+    }
+
+    pub fn wait(&self, timeout_ms: u64) -> IOResult<SynchronizedEvent> {
+        let start = Instant::now();
+        loop {
+            if let Some(event) = self.get() {
+                return Ok(event);
+            }
+            let remaining = timeout_ms.saturating_sub(start.elapsed().as_millis() as u64);
+            if remaining == 0 {
+                return Err(IOError::new(ErrorKind::TimedOut, "Timeout waiting for event"));
+            }
+            if let Some(event) = self.events.wait(remaining.min(EXPIRY_CHECK_INTERVAL_MS)) {
+                return Ok(event);
+            }
+        }
+    }
+
+    pub fn wait_events(&self, count: usize, timeout_ms: u64) -> IOResult<Vec<SynchronizedEvent>> {
+        let mut ret = Vec::new();
+        for _ in 0..count {
+            ret.push(self.wait(timeout_ms)?);
+        }
+        Ok(ret)
+    }
+
+    /// Number of pulses waiting for sources.
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+
+    /// Number of events waiting to be retrieved.
+    pub fn available(&self) -> u64 {
+        self.events.available_count() as u64
+    }
+
+    /// Number of events dropped because they were not retrieved.
+    pub fn dropped(&self) -> u64 {
+        self.events.dropped_count()
+    }
+
+    pub fn complete_count(&self) -> u64 {
+        self.state.lock().unwrap().complete
+    }
+
+    pub fn incomplete_count(&self) -> u64 {
+        self.state.lock().unwrap().incomplete
+    }
+
+    /// Number of messages discarded because their pulse ID had already been emitted.
+    pub fn late_count(&self) -> u64 {
+        self.state.lock().unwrap().late
+    }
+
+    /// Number of pulses emitted before completion or timeout, to keep at most max_pending.
+    pub fn evicted_count(&self) -> u64 {
+        self.state.lock().unwrap().evicted
+    }
+}
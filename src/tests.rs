@@ -14,7 +14,7 @@ use std::time::{Duration, Instant};
 use indexmap::IndexMap;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicBool, Ordering, AtomicI32, AtomicUsize};
+use std::sync::atomic::{AtomicBool, Ordering, AtomicI32, AtomicU32, AtomicUsize};
 use chrono::Local;
 use crossbeam_channel::RecvTimeoutError;
 use rand::RngExt;
@@ -118,10 +118,10 @@ fn assert_rec(rec : &Receiver, min_msg_count: Option<u32>, connections: Option<u
     assert_eq!(rec.dropped(),0);
     match min_msg_count{
         None => {
-            assert_eq!(rec.message_count(),MESSAGE_COUNT);
+            assert_eq!(rec.message_count(),MESSAGE_COUNT as u64);
         }
         Some(count) => {
-            assert!(rec.message_count() >= count);
+            assert!(rec.message_count() >= count as u64);
         }
     }
     assert_eq!(rec.error_count(),0);
@@ -129,7 +129,7 @@ fn assert_rec(rec : &Receiver, min_msg_count: Option<u32>, connections: Option<u
     for endpoint in rec.diagnostics_endpoints() {
         match CONNECTION_MODE{
             ConnectionMode::Shared => {
-                assert_eq!(rec.header_changes(&endpoint),rec.connections() as u32);
+                assert_eq!(rec.header_changes(&endpoint),rec.connections() as u64);
             }
             ConnectionMode::Individual => {
                 assert_eq!(rec.header_changes(&endpoint), 1);
@@ -199,6 +199,18 @@ fn multi() -> IOResult<()> {
 }
 
 
+//The monitor reports the endpoint states asynchronously
+fn wait_connected(endpoint_states: impl Fn() -> HashMap<String, EndpointState>, endpoints: &[String]) -> HashMap<String, EndpointState> {
+    let start = Instant::now();
+    loop {
+        let states = endpoint_states();
+        if endpoints.iter().all(|endpoint| states.get(endpoint) == Some(&EndpointState::Connected)) || start.elapsed() > Duration::from_secs(2) {
+            return states;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
 #[test]
 fn late() ->  IOResult<()> {
     let env = TestEnvironment::new()?;
@@ -209,7 +221,7 @@ fn late() ->  IOResult<()> {
     rec.connect()?;
     rec.listen(on_message, Some(MESSAGE_COUNT))?;
     if CONNECTION_MODE == ConnectionMode::Individual {
-        let ess = rec.endpoint_states();
+        let ess = wait_connected(|| rec.endpoint_states(), &[TXP_PUB.endpoint(), TXP_CMP.endpoint()]);
         println!("Endpoint states : {:?}", ess);
         assert_eq!(ess.get(&TXP_PUB.endpoint()).unwrap().clone(), EndpointState::Connected);
         assert_eq!(ess.get(&TXP_CMP.endpoint()).unwrap().clone(), EndpointState::Connected);
@@ -229,7 +241,7 @@ fn dynamic() ->  IOResult<()> {
     rec.add_endpoint(&TXP_CMP.endpoint())?;
     rec.listen(on_message, Some(MESSAGE_COUNT))?;
     if CONNECTION_MODE == ConnectionMode::Individual {
-        let ess = rec.endpoint_states();
+        let ess = wait_connected(|| rec.endpoint_states(), &[TXP_PUB.endpoint(), TXP_CMP.endpoint()]);
         println!("Endpoint states : {:?}", ess);
         assert_eq!(ess.get(&TXP_PUB.endpoint()).unwrap().clone(), EndpointState::Connected);
         assert_eq!(ess.get(&TXP_CMP.endpoint()).unwrap().clone(), EndpointState::Connected);
@@ -320,7 +332,7 @@ fn run_async() -> IOResult<()> {
         rec.join_async().await.unwrap();
     });
     let messages = rec.message_count();
-    if messages==0 || messages >= MESSAGE_COUNT{
+    if messages==0 || messages >= MESSAGE_COUNT as u64{
         panic!("Interrupted receiver received {} messages", messages);
     }
     println!("Interrupted receiver received {} messages", messages);
@@ -356,7 +368,7 @@ fn run_async_() -> IOResult<()> {
         rec.join_async().await.unwrap();
     });
     let messages = rec.message_count();
-    if messages==0 || messages >= MESSAGE_COUNT{
+    if messages==0 || messages >= MESSAGE_COUNT as u64{
         panic!("Interrupted receiver received {} messages", messages);
     }
     println!("Interrupted receiver received {} messages", messages);
@@ -505,6 +517,44 @@ fn limited_hashmap() {
     assert_eq!(limited_map.keys(), vec!["e", "f", "d"]);
 }
 
+#[test]
+fn fifo_overflow_policies() {
+    let fifo = utils::FifoQueue::with_policy(3, OverflowPolicy::DropOldest, None);
+    for i in 0..5 {
+        assert!(fifo.add(i));
+    }
+    assert_eq!((fifo.available_count(), fifo.dropped_count()), (3, 2));
+    assert_eq!(fifo.get(), Some(2));
+
+    let fifo = utils::FifoQueue::with_policy(3, OverflowPolicy::DropNewest, Some(100));
+    assert!(fifo.add_sized(0, 40));
+    assert!(fifo.add_sized(1, 40));
+    assert!(!fifo.add_sized(2, 40));
+    assert!(fifo.add_sized(3, 20));
+    assert_eq!((fifo.available_bytes(), fifo.dropped_count()), (100, 1));
+    assert_eq!(fifo.get(), Some(0));
+    assert_eq!(fifo.available_bytes(), 60);
+    //A message larger than the budget fits in an empty FIFO
+    let fifo = utils::FifoQueue::with_policy(3, OverflowPolicy::DropOldest, Some(100));
+    assert!(fifo.add_sized(0, 40));
+    assert!(fifo.add_sized(1, 500));
+    assert_eq!((fifo.available_count(), fifo.dropped_count()), (1, 1));
+
+    //The producer waits for the consumer, until closed
+    let fifo = Arc::new(utils::FifoQueue::with_policy(1, OverflowPolicy::Wait, None));
+    let producer_fifo = fifo.clone();
+    let producer = thread::spawn(move || (0..3).map(|i| producer_fifo.add(i)).collect::<Vec<_>>());
+    assert_eq!(fifo.wait(1000), Some(0));
+    assert_eq!(fifo.wait(1000), Some(1));
+    thread::sleep(Duration::from_millis(50));
+    fifo.close();
+    assert_eq!(producer.join().unwrap(), vec![true, true, true]);
+    //Once closed, messages that don't fit are dropped
+    assert!(!fifo.add(3));
+    assert_eq!(fifo.dropped_count(), 1);
+    assert_eq!(fifo.get(), Some(2));
+}
+
 #[test]
 fn pool_threaded() -> IOResult<()> {
     let env = TestEnvironment::new()?;
@@ -522,7 +572,11 @@ fn pool_grouped() -> IOResult<()> {
     let env = TestEnvironment::new()?;
     let mut pool = env.bsread.pool_grouped(vec![vec![&TXP_PUB.endpoint(),], vec![&TXP_CMP.endpoint()]], SocketType::SUB, CONNECTION_MODE)?;
     pool.fork(on_message)?;
-    thread::sleep(Duration::from_millis(100));
+    //Subscribers may miss the first messages while connecting
+    let start = Instant::now();
+    while pool.receivers().iter().any(|rec| rec.message_count() == 0) && start.elapsed() < Duration::from_secs(2) {
+        thread::sleep(Duration::from_millis(10));
+    }
     pool.stop()?;
     print_stats_pool(&pool);
     assert_pool(&pool);
@@ -650,14 +704,14 @@ fn pool_monitoring() ->  IOResult<()> {
 fn pool_dynamic() ->  IOResult<()> {
     let env = TestEnvironment::new()?;
     let mut pool = env.bsread.pool(vec![], SocketType::SUB, CONNECTION_MODE, 2)?;
-    //pool.enable_monitoring()?;
+    //Enabled before connecting, so that no connection event is missed
+    pool.enable_monitoring()?;
     pool.connect()?;
     pool.add_endpoint(&TXP_PUB.endpoint(),Some(0))?;
     pool.add_endpoint(&TXP_CMP.endpoint(),Some(1))?;
-    pool.enable_monitoring()?;
     pool.listen(on_message, Some(MESSAGE_COUNT))?;
     if CONNECTION_MODE == ConnectionMode::Individual {
-        let ess = pool.endpoint_states();
+        let ess = wait_connected(|| pool.endpoint_states(), &[TXP_PUB.endpoint(), TXP_CMP.endpoint()]);
         println!("Endpoint states : {:?}", ess);
         assert_eq!(ess.get(&TXP_PUB.endpoint()).unwrap().clone(), EndpointState::Connected);
         assert_eq!(ess.get(&TXP_CMP.endpoint()).unwrap().clone(), EndpointState::Connected);
@@ -680,7 +734,7 @@ fn pool_dynamic() ->  IOResult<()> {
     }
     print_stats_pool(&pool);
     assert_rec(&pool.receivers()[0], None, Some(1));
-    assert_eq!(pool.receivers()[0].message_count(), MESSAGE_COUNT);
+    assert_eq!(pool.receivers()[0].message_count(), MESSAGE_COUNT as u64);
     assert_eq!(pool.receivers()[1].message_count(), 0);
     Ok(())
 }
@@ -847,7 +901,7 @@ fn bitshuffle_lz4() ->  IOResult<()> {
     let decompressed = decompress_bitshuffle_lz4(&compressed, elem_size)?;
     assert_eq!(&buffer, &decompressed);
     let mut out = vec![0u32; 128];
-    let mut cursor = Cursor::new(&decompressed);
+    let mut cursor = Cursor::new(decompressed.as_slice());
     reader::READER_ABU32(& mut cursor, out.as_mut_slice())?;
     assert_eq!(&data, &out);
     Ok(())
@@ -863,7 +917,7 @@ fn serializer() ->  IOResult<()> {
             let ch = channel::new(value.kind().to_string(), value.kind().to_string(), shape, little_endian, Compression::None, false)?;
             let mut cursor = Cursor::new(&mut buf);
             ch.write(&mut cursor, &value)?;
-            let mut cursor = Cursor::new(&buf);
+            let mut cursor = Cursor::new(buf.as_slice());
             let ret = ch.read(&mut cursor)?;
             assert_eq!(&value, &ret);
         }
@@ -1014,10 +1068,10 @@ fn forwarder() ->  IOResult<()> {
     let mut rxtx = env.bsread.receiver(Some(vec![&TXP_PUB.endpoint()]), SocketType::SUB, CONNECTION_MODE)?;
     rxtx.set_forwarder_config(ForwarderConfig::new(SocketType::PUB, Transport::Tcp{port:10600, host:None}, None));
     let mut rec = env.bsread.receiver(Some(vec!["tcp://127.0.0.1:10600"]), SocketType::SUB, CONNECTION_MODE)?;
-    rec.fork(on_message, None);
 
-    //Asynchronous
+    //Asynchronous. The subscriber connects once the forwarder is bound, within its startup time.
     rxtx.fork(|rx| {log::info!("RTX Msg {}", rx.message.id())}, Some(MESSAGE_COUNT));
+    rec.fork(on_message, None);
     rxtx.join()?;
     thread::sleep(Duration::from_millis(1000));
     print_stats_rec(&rec);
@@ -1075,10 +1129,11 @@ fn closure() ->  IOResult<()> {
 fn closure_interrupt() ->  IOResult<()> {
     let env = TestEnvironment::new()?;
     let mut rec = env.bsread.receiver(Some(vec![&TXP_PUB.endpoint()]), SocketType::SUB, CONNECTION_MODE)?;
-
+    //The test senders may have been started by another test: counts the messages instead of checking IDs
+    let received = AtomicU32::new(0);
     rec.listen(|rx| {
         env.on_message(&rx);
-        if rx.message.id() > 15{
+        if received.fetch_add(1, Ordering::SeqCst) + 1 > 15{
             env.bsread.interrupt();
         }
         assert_message_contents_ok(&rx.message);
@@ -1265,6 +1320,1439 @@ fn compression_enum() ->  IOResult<()> {
     assert_eq!(comp, Compression::None);
     let comp = Compression::None.to_string();
     assert_eq!(comp, "none");
+    let err = Compression::from_str("zip").unwrap_err();
+    assert!(err.is_parsing());
+    Ok(())
+}
+
+#[test]
+fn typed_errors() ->  IOResult<()> {
+    let mut headers = utils::LimitedHashMap::new(10);
+    let endpoint = Some("tcp://localhost:9999".to_string());
+
+    let err = message::parse_message(vec![vec![]], &endpoint, &mut headers, false).err().unwrap();
+    assert!(matches!(err, BsreadError::Parsing{reason: ParsingError::FrameCount{expected:2, actual:1}, ..}));
+    assert_eq!(err.context().unwrap().endpoint, endpoint);
+
+    let main_header = br#"{"htype":"bsr_m-1.1","pulse_id":7,"hash":"abc","dh_compression":"lz4"}"#.to_vec();
+    let err = message::parse_message(vec![main_header, vec![0, 0, 0, 16, 0xff, 0xff, 0xff, 0xff]], &endpoint, &mut headers, false).err().unwrap();
+    assert!(err.is_decompression());
+    assert_eq!(err.context().unwrap().pulse_id, Some(7));
+
+    let dh = br#"{"htype":"bsr_d-1.1","channels":[{"name":"ch","type":"int8","compression":"zip"}]}"#.to_vec();
+    let main_header = format!(r#"{{"htype":"bsr_m-1.1","pulse_id":8,"hash":"{}"}}"#, utils::hash_md5(&dh)).into_bytes();
+    let err = message::parse_message(vec![main_header, dh, vec![1], vec![]], &endpoint, &mut headers, false).err().unwrap();
+    assert!(matches!(err, BsreadError::Parsing{reason: ParsingError::InvalidCompression(_), ..}));
+    assert_eq!(err.context().unwrap().channel.as_deref(), Some("ch"));
+    assert_eq!(err.context().unwrap().pulse_id, Some(8));
+
+    let bsread = Bsread::new().unwrap();
+    let mut sender = Sender::new(bsread,  SocketType::PUB, Transport::Tcp{port:10420, host:None}, None, None, None)?;
+    let err = sender.send(ID_SIMULATED, TIMESTAMP_NOW, &vec![], &vec![]).unwrap_err();
+    assert!(matches!(err, BsreadError::Validation{reason: ValidationError::EmptyChannelData, ..}));
+    let io_err: IOError = err.into();
+    assert_eq!(io_err.kind(), ErrorKind::InvalidData);
+    assert!(BsreadError::from(io_err).is_validation());
+    Ok(())
+}
+
+//Builds the frames of a valid message, as produced by Sender::send
+fn encode_frames(id: u64, header_compression: Compression) -> IOResult<Vec<Vec<u8>>> {
+    let channels = vec![
+        channel::new("scalar".to_string(), "float64".to_string(), None, true, Compression::None, false)?,
+        channel::new("string".to_string(), "string".to_string(), None, true, Compression::None, false)?,
+        channel::new("bshuf".to_string(), "uint16".to_string(), Some(vec![100]), false, Compression::BitshuffleLz4, false)?,
+        channel::new("lz4".to_string(), "int32".to_string(), Some(vec![10, 5]), true, Compression::Lz4, false)?,
+    ];
+    let data = vec![
+        ChannelData::new(Value::F64(1.5), (1, 2)),
+        ChannelData::new(Value::STR("text".to_string()), (1, 2)),
+        ChannelData::new(Value::AU16((0..100).collect()), (1, 2)),
+        ChannelData::new(Value::AI32((0..50).collect()), (1, 2)),
+    ];
+    let data_header = serde_json::to_vec(&message::create_data_header(&channels)?)?;
+    let data_header = match header_compression {
+        Compression::BitshuffleLz4 => compress_bitshuffle_lz4(&data_header, 1)?,
+        Compression::Lz4 => compress_lz4(&data_header, false)?,
+        Compression::None => data_header,
+    };
+    let main_header = format!(r#"{{"htype":"bsr_m-1.1","pulse_id":{},"hash":"{}","global_timestamp":{{"sec":1,"ns":2}},"dh_compression":"{}"}}"#,
+                              id, utils::hash_md5(&data_header), header_compression);
+    let mut frames = vec![main_header.into_bytes(), data_header];
+    for (channel, channel_data) in channels.iter().zip(data.iter()) {
+        let (v, t) = message::serialize_channel(channel, channel_data)?;
+        frames.push(v);
+        frames.push(t);
+    }
+    Ok(frames)
+}
+
+fn parse_frames(frames: Vec<Vec<u8>>) -> BsreadResult<Message> {
+    let mut headers = utils::LimitedHashMap::new(10);
+    message::parse_message(frames, &None, &mut headers, false)
+}
+
+//Frames of a message, as produced by Sender::send_message
+fn message_frames(message: &Message) -> BsreadResult<Vec<Vec<u8>>> {
+    //Ordered keys, so that the hash only changes with the channels
+    let data_header: std::collections::BTreeMap<_, _> = message::create_data_header(message.channels())?.into_iter().collect();
+    let data_header = serde_json::to_vec(&data_header)?;
+    let (sec, ns) = message.timestamp();
+    let main_header = format!(r#"{{"htype":"bsr_m-1.1","pulse_id":{},"hash":"{}","global_timestamp":{{"sec":{},"ns":{}}}}}"#,
+                              message.id(), utils::hash_md5(&data_header), sec, ns);
+    let mut frames = vec![main_header.into_bytes(), data_header];
+    for (channel, channel_data) in message.channels().iter().zip(message.data().values()) {
+        if let Some(channel_data) = channel_data {
+            let (v, t) = message::serialize_channel(channel, channel_data)?;
+            frames.push(v);
+            frames.push(t);
+        }
+    }
+    Ok(frames)
+}
+
+//Encodes and decodes a message, without sockets
+fn transfer(message: &Message) -> BsreadResult<Message> {
+    parse_frames(message_frames(message)?)
+}
+
+static NEXT_PORT: AtomicU32 = AtomicU32::new(10710);
+
+//PUSH sender bound to the next free test port, and the endpoint to connect to it
+fn push_sender(env: &TestEnvironment) -> IOResult<(Sender, String)> {
+    let port = NEXT_PORT.fetch_add(1, Ordering::SeqCst);
+    let mut sender = env.bsread.sender(SocketType::PUSH, Transport::Tcp{port, host:None}, Some(true), None, None)?;
+    sender.start()?;
+    Ok((sender, format!("tcp://127.0.0.1:{}", port)))
+}
+
+//Sends through a PUSH sender and waits for count messages in a buffered PULL receiver.
+//The receiver is stopped and returned, for checking its counters.
+fn roundtrip_with(env: &TestEnvironment, configure: impl FnOnce(&mut Receiver) -> IOResult<()>, count: usize,
+                  send: impl FnOnce(&mut Sender) -> IOResult<()>) -> IOResult<(Receiver, Vec<ReceivedMessage>)> {
+    let (mut sender, endpoint) = push_sender(env)?;
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    configure(&mut rec)?;
+    rec.start(100)?;
+    let received = send(&mut sender).and_then(|_| rec.wait_messages(count, 2000));
+    rec.stop()?;
+    sender.stop();
+    Ok((rec, received?))
+}
+
+fn roundtrip(env: &TestEnvironment, configure: impl FnOnce(&mut Receiver) -> IOResult<()>, messages: Vec<Message>) -> IOResult<(Receiver, Vec<ReceivedMessage>)> {
+    roundtrip_with(env, configure, messages.len(), |sender| {
+        for message in messages.iter() {
+            sender.send_message(message, true)?;
+        }
+        Ok(())
+    })
+}
+
+#[test]
+fn parsing_corpus() ->  IOResult<()> {
+    let valid = encode_frames(10, Compression::None)?;
+    let message = parse_frames(valid.clone())?;
+    assert_eq!(message.data().values().filter(|v| v.is_some()).count(), 4);
+    assert_eq!(message.channel_value("bshuf"), Some(&Value::AU16((0..100).collect())));
+    for compression in [Compression::Lz4, Compression::BitshuffleLz4] {
+        assert!(parse_frames(encode_frames(10, compression)?).is_ok());
+    }
+
+    let main_header = |s: &str| s.as_bytes().to_vec();
+    let data_header = |channels: &str| format!(r#"{{"htype":"bsr_d-1.1","channels":[{}]}}"#, channels).into_bytes();
+    let header_for = |dh: &Vec<u8>| main_header(&format!(r#"{{"htype":"bsr_m-1.1","pulse_id":1,"hash":"{}"}}"#, utils::hash_md5(dh)));
+    let frames_for = |channels: &str, value: Vec<u8>| {
+        let dh = data_header(channels);
+        vec![header_for(&dh), dh, value, vec![]]
+    };
+    let mut corpus: Vec<(&str, Vec<Vec<u8>>)> = vec![
+        ("no frames", vec![]),
+        ("single frame", vec![valid[0].clone()]),
+        ("invalid json", vec![main_header("{not json"), valid[1].clone()]),
+        ("json array", vec![main_header("[1,2]"), valid[1].clone()]),
+        ("missing pulse_id", vec![main_header(r#"{"htype":"bsr_m-1.1","hash":"x"}"#), valid[1].clone()]),
+        ("negative pulse_id", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":-1,"hash":"x"}"#), valid[1].clone()]),
+        ("invalid htype", vec![main_header(r#"{"htype":"x","pulse_id":1,"hash":"x"}"#), valid[1].clone()]),
+        ("timestamp not object", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","global_timestamp":3}"#), valid[1].clone()]),
+        ("timestamp missing ns", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","global_timestamp":{"sec":1}}"#), valid[1].clone()]),
+        ("invalid dh_compression", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"zip"}"#), valid[1].clone()]),
+        ("empty lz4 data header", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"lz4"}"#), vec![]]),
+        ("negative lz4 size", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"lz4"}"#), vec![0xff, 0, 0, 0, 1]]),
+        ("empty bitshuffle data header", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"bitshuffle_lz4"}"#), vec![0; 11]]),
+        ("huge bitshuffle size", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"bitshuffle_lz4"}"#),
+                                      vec![0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0x20, 0, 0, 0, 0, 4, 1, 2, 3, 4]]),
+        ("bitshuffle block past end", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"bitshuffle_lz4"}"#),
+                                           vec![0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0, 0x7f, 0xff, 0xff, 0xff, 1, 2, 3, 4]]),
+        ("bitshuffle invalid block size", vec![main_header(r#"{"htype":"bsr_m-1.1","pulse_id":1,"hash":"x","dh_compression":"bitshuffle_lz4"}"#),
+                                               vec![0, 0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 3, 0, 0, 0, 1, 0]]),
+        ("missing channels", vec![header_for(&b"{}".to_vec()), b"{}".to_vec()]),
+        ("channels not array", { let dh = br#"{"channels":3}"#.to_vec(); vec![header_for(&dh), dh] }),
+        ("channel not object", { let dh = br#"{"channels":[3]}"#.to_vec(); vec![header_for(&dh), dh] }),
+        ("channel without name", frames_for(r#"{"type":"int8"}"#, vec![1])),
+        ("invalid compression", frames_for(r#"{"name":"x","type":"int8","compression":"zip"}"#, vec![1])),
+        ("negative shape", frames_for(r#"{"name":"x","type":"int8","shape":[-1]}"#, vec![1])),
+        ("shape not integer", frames_for(r#"{"name":"x","type":"int8","shape":["a"]}"#, vec![1])),
+        ("missing frames", { let mut f = frames_for(r#"{"name":"x","type":"int8"}"#, vec![1]); f.pop(); f }),
+    ];
+    let mut headers = utils::LimitedHashMap::new(10);
+    for (name, frames) in corpus.drain(..) {
+        let result = message::parse_message(frames, &None, &mut headers, false);
+        assert!(result.is_err(), "Corpus entry '{}' should fail", name);
+    }
+
+    //Malformed channel values only invalidate the channel, not the message
+    let channel_corpus: Vec<(&str, &str, Vec<u8>)> = vec![
+        ("truncated scalar", r#"{"name":"x","type":"float64"}"#, vec![1, 2, 3]),
+        ("truncated array", r#"{"name":"x","type":"uint32","shape":[10]}"#, vec![0; 39]),
+        ("huge array", r#"{"name":"x","type":"float64","shape":[4294967295,4294967295]}"#, vec![0; 8]),
+        ("truncated bool array", r#"{"name":"x","type":"bool","shape":[10]}"#, vec![1; 9]),
+        ("invalid utf8", r#"{"name":"x","type":"string"}"#, vec![0xff, 0xfe]),
+        ("truncated lz4", r#"{"name":"x","type":"int32","shape":[10],"compression":"lz4"}"#, vec![0, 0, 0]),
+        ("corrupt lz4", r#"{"name":"x","type":"int32","shape":[10],"compression":"lz4"}"#, vec![40, 0, 0, 0, 0xf0, 1, 2]),
+        ("truncated bitshuffle", r#"{"name":"x","type":"int32","shape":[10],"compression":"bitshuffle_lz4"}"#, vec![0, 0, 0, 0, 0, 0, 0, 40]),
+        ("corrupt bitshuffle", r#"{"name":"x","type":"int32","shape":[16],"compression":"bitshuffle_lz4"}"#,
+         vec![0, 0, 0, 0, 0, 0, 0, 64, 0, 0, 0x20, 0, 0, 0, 0, 4, 0xf0, 1, 2, 3]),
+    ];
+    for (name, channels, value) in channel_corpus {
+        for raw in [false, true] {
+            let mut headers = utils::LimitedHashMap::new(10);
+            let message = message::parse_message(frames_for(channels, value.clone()), &None, &mut headers, raw)
+                .map_err(|e| IOError::other(format!("Corpus entry '{}' failed: {}", name, e)))?;
+            if !raw || channels.contains("compression") {
+                assert!(message.channel_data("x").is_none(), "Corpus entry '{}' should have no value", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn opaque_channels() ->  IOResult<()> {
+    let env = TestEnvironment::new()?;
+    let channels = vec![
+        channel::new("known".to_string(), "int32".to_string(), None, true, Compression::None, false)?,
+        channel::new("half".to_string(), "float16".to_string(), Some(vec![4]), true, Compression::None, false)?,
+        channel::new("vendor".to_string(), "acme_blob".to_string(), None, false, Compression::Lz4, false)?,
+    ];
+    assert!(!channels[0].config().is_opaque());
+    assert!(channels[1].config().is_opaque());
+    assert_eq!(channels[1].config().element_size(), 2);
+    assert_eq!(channels[2].config().element_size(), 1);
+
+    let messages = (0..MESSAGE_COUNT).map(|i| {
+        let data = vec![
+            Some(ChannelData::new(Value::I32(i as i32), TIMESTAMP_NOW)),
+            Some(ChannelData::new(Value::AU8(vec![i as u8; 8]), TIMESTAMP_NOW)),
+            Some(ChannelData::new(Value::AU8(vec![1, 2, 3, i as u8]), TIMESTAMP_NOW)),
+        ];
+        Message::new_from_channel_vec(ID_SIMULATED, TIMESTAMP_NOW, &channels, data)
+    }).collect::<BsreadResult<Vec<Message>>>()?;
+    let (rec, rx) = roundtrip(&env, |_| Ok(()), messages)?;
+    for (i, msg) in rx.iter().enumerate() {
+        let message = &msg.message;
+        assert_eq!(message.channel_value("known"), Some(&Value::I32(i as i32)));
+        assert_eq!(message.channel_value("half"), Some(&Value::AU8(vec![i as u8; 8])));
+        assert_eq!(message.channel_value("vendor"), Some(&Value::AU8(vec![1, 2, 3, i as u8])));
+        let half = &message.channels()[1];
+        assert_eq!(half.config().kind(), "float16");
+        assert_eq!(half.config().shape(), Some(vec![4]));
+    }
+    assert_eq!(rec.endpoint_diagnostic(&rec.endpoints()[0], EndpointDiag::UnknownType), Some(2));
+    assert_eq!(rec.error_count(), 0);
+    Ok(())
+}
+
+#[test]
+fn string_arrays() ->  IOResult<()> {
+    let ch = channel::new("labels".to_string(), "string".to_string(), Some(vec![3]), true, Compression::None, false)?;
+    let expected = Value::ASTR(vec!["ON".to_string(), "".to_string(), "STANDBY".to_string()]);
+    let encodings: Vec<&[u8]> = vec![
+        b"ON\0\0\0\0\0\0\0\0\0\0\0\0STANDBY",  //Fixed width
+        b"ON\0\0STANDBY",                           //Null separated
+    ];
+    for data in encodings {
+        let data = data.to_vec();
+        assert_eq!(ch.read(&mut Cursor::new(data.as_slice()))?, expected);
+    }
+    //Fixed width whenever the size is a multiple of the element count and every element is null padded
+    let astr = |strings: &[&str]| Value::ASTR(strings.iter().map(|s| s.to_string()).collect());
+    let pair = channel::new("pair".to_string(), "string".to_string(), Some(vec![2]), true, Compression::None, false)?;
+    assert_eq!(pair.read(&mut Cursor::new(&b"ON\0STANDBY\0"[..]))?, astr(&["ON", "STANDBY"]));    //Null terminated
+    assert_eq!(ch.read(&mut Cursor::new(&b"ABCD\0\0EF\0"[..]))?, astr(&["ABC", "D", "EF"]));
+    assert_eq!(ch.read(&mut Cursor::new(&b"ON\0\0STANDBY\0"[..]))?, astr(&["ON", "STAN", "DBY"]));
+    assert_eq!(ch.read(&mut Cursor::new(&b"\0\0\0"[..]))?, astr(&["", "", ""]));
+    for invalid in [&b"ON\0X"[..], b"A\0B\0C\0D", b"A\0B\0C\0D\0"] {
+        assert!(ch.read(&mut Cursor::new(invalid)).is_err());
+    }
+
+    //Arrays written by the crate are read back unchanged, whatever the element lengths
+    for strings in [vec!["AAA", "B", "CC"], vec!["ABC", "D", "EF"], vec!["A", "BC", ""], vec!["ABC", "D", "E", ""], vec!["", "XY", "", ""], vec!["", ""]] {
+        let strings: Vec<String> = strings.into_iter().map(String::from).collect();
+        let message = transfer(&MessageBuilder::new().pulse_id(1).channel("labels", strings.clone()).build()?)?;
+        assert_eq!(message.get::<Vec<String>>("labels")?, strings);
+    }
+
+    let channels = vec![
+        ch,
+        channel::new("names".to_string(), "string".to_string(), Some(vec![2]), true, Compression::BitshuffleLz4, false)?,
+        channel::new("state".to_string(), "string".to_string(), None, true, Compression::None, false)?,
+    ];
+    for i in 1..=3u64 {
+        let data = vec![
+            Some(ChannelData::new(expected.clone(), (1, 2))),
+            Some(ChannelData::new(Value::ASTR(vec![format!("bunch{}", i), "ä".to_string()]), (1, 2))),
+            Some(ChannelData::new(Value::STR("RUNNING".to_string()), (1, 2))),
+        ];
+        let message = transfer(&Message::new_from_channel_vec(i, (1, 2), &channels, data)?)?;
+        assert_eq!(message.channel_value("labels"), Some(&expected));
+        assert_eq!(message.channel_value("names"), Some(&Value::ASTR(vec![format!("bunch{}", i), "ä".to_string()])));
+        assert_eq!(message.channel_value("state"), Some(&Value::STR("RUNNING".to_string())));
+    }
+    Ok(())
+}
+
+#[test]
+fn zero_copy() ->  IOResult<()> {
+    let env = TestEnvironment::new()?;
+    let channels = vec![
+        channel::new("waveform".to_string(), "float32".to_string(), Some(vec![1000]), true, Compression::None, false)?,
+        channel::new("image".to_string(), "uint16".to_string(), Some(vec![20, 10]), true, Compression::BitshuffleLz4, false)?,
+        channel::new("big".to_string(), "int32".to_string(), Some(vec![50]), false, Compression::None, false)?,
+        channel::new("scalar".to_string(), "float64".to_string(), None, true, Compression::None, false)?,
+    ];
+    let waveform: Vec<f32> = (0..1000).map(|i| i as f32 * 0.5).collect();
+    let image: Vec<u16> = (0..200).collect();
+    let big: Vec<i32> = (0..50).map(|i| -i).collect();
+    let messages = (0..MESSAGE_COUNT).map(|_| {
+        let data = vec![
+            Some(ChannelData::new(Value::AF32(waveform.clone()), TIMESTAMP_NOW)),
+            Some(ChannelData::new(Value::AU16(image.clone()), TIMESTAMP_NOW)),
+            Some(ChannelData::new(Value::AI32(big.clone()), TIMESTAMP_NOW)),
+            Some(ChannelData::new(Value::F64(1.0), TIMESTAMP_NOW)),
+        ];
+        Message::new_from_channel_vec(ID_SIMULATED, TIMESTAMP_NOW, &channels, data)
+    }).collect::<BsreadResult<Vec<Message>>>()?;
+    let (_, rx) = roundtrip(&env, |rec| Ok(rec.set_zero_copy(true)), messages)?;
+    for msg in rx.iter() {
+        let data = msg.message.data();
+        let waveform_data = data.get("waveform").unwrap().as_ref().unwrap();
+        assert!(waveform_data.is_shared());
+        let slice = waveform_data.as_slice::<f32>().unwrap();
+        assert_eq!(slice, waveform.as_slice());
+        //Native-endian uncompressed arrays are views into the received frame, if it is aligned
+        let buffer = waveform_data.buffer().unwrap();
+        if cfg!(target_endian = "little") && buffer.as_typed::<f32>().is_some() {
+            assert_eq!(slice.as_ptr() as *const u8, buffer.as_ptr());
+        }
+        assert_eq!(data.get("image").unwrap().as_ref().unwrap().as_slice::<u16>(), Some(image.as_slice()));
+        assert_eq!(data.get("big").unwrap().as_ref().unwrap().as_slice::<i32>(), Some(big.as_slice()));
+        assert_eq!(data.get("big").unwrap().as_ref().unwrap().as_slice::<u32>(), None);
+        assert_eq!(msg.message.channel_value("waveform"), Some(&Value::AF32(waveform.clone())));
+        assert_eq!(msg.message.channel_value("big"), Some(&Value::AI32(big.clone())));
+        let scalar = data.get("scalar").unwrap().as_ref().unwrap();
+        assert!(!scalar.is_shared());
+        assert_eq!(scalar.value(), &Value::F64(1.0));
+    }
+
+    let frames: Vec<SharedBuffer> = encode_frames(1, Compression::None)?.into_iter().map(SharedBuffer::from_vec).collect();
+    let mut headers = utils::LimitedHashMap::new(10);
+    let message = message::parse_frames(frames, &None, &mut headers, false, true, false, None)?;
+    let lz4 = message.data().get("lz4").unwrap().as_ref().unwrap();
+    assert_eq!(lz4.as_slice::<i32>(), Some((0..50).collect::<Vec<i32>>().as_slice()));
+    let bshuf = message.data().get("bshuf").unwrap().as_ref().unwrap();
+    assert_eq!(bshuf.as_slice::<u16>(), Some((0..100).collect::<Vec<u16>>().as_slice()));
+    assert_eq!(message.channel_value("scalar"), Some(&Value::F64(1.5)));
+    Ok(())
+}
+
+#[test]
+fn lazy_decoding() ->  IOResult<()> {
+    let mut frames = encode_frames(10, Compression::None)?;
+    frames[8].truncate(3);
+    let mut headers = utils::LimitedHashMap::new(10);
+    let message = message::parse_message(frames.clone(), &None, &mut headers, false)?;
+    assert!(message.channel_data("lz4").is_none());
+    assert!(message.try_channel_data("lz4")?.is_none());
+
+    let shared = frames.iter().cloned().map(SharedBuffer::from_vec).collect();
+    let message = message::parse_frames(shared, &None, &mut headers, false, false, true, None)?;
+    assert!(message.is_lazy());
+    assert_eq!(message.channel_value("scalar"), Some(&Value::F64(1.5)));
+    assert_eq!(message.channel_value("bshuf"), Some(&Value::AU16((0..100).collect())));
+    let error = message.try_channel_data("lz4").err().unwrap();
+    assert!(error.is_decompression());
+    assert_eq!(error.context().unwrap().channel.as_deref(), Some("lz4"));
+    assert_eq!(error.context().unwrap().pulse_id, Some(10));
+    assert!(message.try_channel_data("lz4").err().unwrap().is_decompression());
+    assert!(message.try_channel_data("missing")?.is_none());
+    //data() shares the values already decoded instead of copying them
+    let bshuf = message.channel_value("bshuf").unwrap() as *const Value;
+    assert_eq!(message.data().len(), 4);
+    assert!(std::ptr::eq(message.data().get("bshuf").unwrap().as_ref().unwrap().value(), bshuf));
+    assert!(message.data().get("lz4").unwrap().is_none());
+    assert_eq!(message.channel_value("string"), Some(&Value::STR("text".to_string())));
+
+    let env = TestEnvironment::new()?;
+    let first_id = utils::current_id().unwrap_or(0) + 1;
+    let (rec, rx) = roundtrip_with(&env, |rec| Ok(rec.set_lazy(true)), MESSAGE_COUNT as usize, |sender| {
+        for i in 0..MESSAGE_COUNT {
+            let mut frames = encode_frames(first_id + i as u64, Compression::None)?;
+            frames[8].truncate(3);
+            sender.forward(&frames)?;
+        }
+        Ok(())
+    })?;
+    assert_eq!(rec.error_count(), 0);
+    let channel_errors = || rec.endpoint_diagnostic(&rec.endpoints()[0], EndpointDiag::ChannelError).unwrap_or(0);
+    assert_eq!(channel_errors(), 0);
+    for msg in rx.iter() {
+        assert_eq!(msg.message.channel_value("scalar"), Some(&Value::F64(1.5)));
+        assert!(msg.message.try_channel_data("lz4").is_err());
+        assert!(msg.message.try_channel_data("lz4").is_err());
+    }
+    assert_eq!(channel_errors(), MESSAGE_COUNT as u64);
+    Ok(())
+}
+
+#[test]
+fn channel_filter() ->  IOResult<()> {
+    let filter = ChannelFilter::new().name("bshuf").glob("s?al*")?.regex("^lz")?;
+    assert!(filter.matches("scalar") && filter.matches("lz4") && filter.matches("bshuf"));
+    assert!(!filter.matches("string") && !filter.matches("xlz4"));
+    assert!(ChannelFilter::new().regex("(").is_err());
+    assert!(ChannelFilter::new().glob("a.b")?.matches("a.b"));
+    assert!(!ChannelFilter::new().glob("a.b")?.matches("axb"));
+
+    let mut frames = encode_frames(10, Compression::None)?;
+    frames[8].truncate(3);
+    let endpoint = Some("endpoint".to_string());
+    let mut headers = utils::LimitedHashMap::new(10);
+    let filter = ChannelFilter::from_names(&["scalar", "bshuf"]);
+    for lazy in [false, true] {
+        for changed in [true, false] {
+            let shared = frames.iter().cloned().map(SharedBuffer::from_vec).collect();
+            let message = message::parse_frames(shared, &endpoint, &mut headers, false, false, lazy, Some(&filter))?;
+            assert_eq!(message.header_changed(), changed);
+            assert_eq!(message.channels().len(), 2);
+            assert_eq!(message.data().keys().collect::<Vec<_>>(), vec!["scalar", "bshuf"]);
+            assert_eq!(message.channel_value("bshuf"), Some(&Value::AU16((0..100).collect())));
+            assert!(message.try_channel_data("lz4")?.is_none());
+        }
+        headers.clear();
+    }
+
+    let env = TestEnvironment::new()?;
+    let channel = |name: &str| channel::new(name.to_string(), "int32".to_string(), None, true, Compression::None, false);
+    let headers = vec![vec![channel("wave1")?, channel("other")?], vec![channel("other")?, channel("wave2")?, channel("wave1")?]];
+    let mut messages = Vec::new();
+    for channels in headers.iter() {
+        for i in 0..MESSAGE_COUNT {
+            let data = channels.iter().map(|_| Some(ChannelData::new(Value::I32(i as i32), TIMESTAMP_NOW))).collect();
+            messages.push(Message::new_from_channel_vec(ID_SIMULATED, TIMESTAMP_NOW, channels, data)?);
+        }
+    }
+    let filter = ChannelFilter::new().glob("wave*")?;
+    let (_, rx) = roundtrip(&env, |rec| Ok(rec.set_channel_filter(Some(filter))), messages)?;
+    for (i, msg) in rx.iter().enumerate() {
+        let expected = if i < MESSAGE_COUNT as usize {vec!["wave1"]} else {vec!["wave2", "wave1"]};
+        assert_eq!(msg.message.data().keys().collect::<Vec<_>>(), expected);
+        assert!(msg.message.channel_value("other").is_none());
+    }
+    Ok(())
+}
+
+#[test]
+fn message_builder() ->  IOResult<()> {
+    let invalid = |result: BsreadResult<Message>, channel: &str| {
+        let error = result.err().unwrap();
+        assert!(error.is_validation());
+        assert_eq!(error.context().unwrap().channel.as_deref(), Some(channel));
+    };
+    invalid(MessageBuilder::new().channel("a", 1).channel("a", 2).build(), "a");
+    invalid(MessageBuilder::new().channel("single", vec![1.0]).build(), "single");
+    invalid(MessageBuilder::new().channel_with_shape("image", vec![0u16; 10], vec![3, 3], Compression::None).build(), "image");
+    invalid(MessageBuilder::new().channel_with_shape("scalar", 1u16, vec![3, 3], Compression::None).build(), "scalar");
+    assert!(MessageBuilder::new().build().is_err());
+
+    let image: Vec<u16> = (0..200).collect();
+    let message = MessageBuilder::new()
+        .timestamp((1, 2))
+        .channel("current", 1.5)
+        .channel("state", "RUNNING")
+        .channel("labels", vec!["A".to_string(), "B".to_string()])
+        .channel_with("waveform", (0..100).map(|i| i as f32).collect::<Vec<f32>>(), Compression::BitshuffleLz4)
+        .channel_with_shape("image", image.clone(), vec![20, 10], Compression::Lz4)
+        .build()?;
+    let config = message.channels()[4].config();
+    assert_eq!((config.kind().as_str(), config.shape(), config.compression()), ("uint16", Some(vec![20, 10]), Compression::Lz4));
+    assert_eq!(message.channels()[0].config().kind(), "float64");
+    assert_eq!(message.channels()[1].config().shape(), None);
+
+    for i in 1..=3u64 {
+        let message = MessageBuilder::new()
+            .pulse_id(i)
+            .channel("counter", i)
+            .channel_with_shape("image", image.clone(), vec![20, 10], Compression::BitshuffleLz4)
+            .little_endian(false)
+            .build()?;
+        let message = transfer(&message)?;
+        assert_eq!(message.channel_value("counter"), Some(&Value::U64(i)));
+        assert_eq!(message.channel_value("image"), Some(&Value::AU16(image.clone())));
+        assert!(!message.channels()[1].config().is_little_endian());
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "ndarray")]
+fn ndarray_channels() ->  IOResult<()> {
+    use ndarray::{Array2, Array3};
+    //20 columns (fastest varying) x 10 rows
+    let image = Array2::from_shape_fn((10, 20), |(row, col)| (row * 100 + col) as u16);
+    let cube = Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 12 + j * 4 + k) as f64);
+    let message = MessageBuilder::new()
+        .pulse_id(100)
+        .array("image", &image)
+        .array_with("cube", &cube, Compression::BitshuffleLz4)
+        .array("transposed", &image.t())
+        .channel("scalar", 1.0)
+        .build()?;
+    assert_eq!(message.channels()[0].config().shape(), Some(vec![20, 10]));
+    assert_eq!(message.channels()[1].config().shape(), Some(vec![4, 3, 2]));
+    assert_eq!(message.channels()[2].config().shape(), Some(vec![10, 20]));
+    assert_eq!(message.array2::<u16>("image")?, image);
+    assert_eq!(message.array2::<u16>("transposed")?, image.t());
+    assert_eq!(message.array_view::<f64>("cube")?, cube.into_dyn().view());
+    assert_eq!(array::to_ndarray_shape(&[20, 10]), vec![10, 20]);
+    assert!(message.array_view::<f32>("image").err().unwrap().is_conversion());
+    assert!(message.array2::<f64>("cube").err().unwrap().is_conversion());
+    assert!(message.array_view::<f64>("scalar").is_err());
+    assert!(message.array_view::<u16>("missing").err().unwrap().is_validation());
+
+    let message = MessageBuilder::new().pulse_id(1).channel("counter", 1u64).array_with("image", &image, Compression::Lz4).build()?;
+    let frames = message_frames(&message)?.into_iter().map(SharedBuffer::from_vec).collect();
+    let mut headers = utils::LimitedHashMap::new(10);
+    let message = message::parse_frames(frames, &None, &mut headers, false, true, false, None)?;
+    let view = message.array_view::<u16>("image")?;
+    assert_eq!(view.shape(), &[10, 20]);
+    assert_eq!(view[[3, 7]], 307);
+    Ok(())
+}
+
+#[test]
+fn serde_round_trip() ->  IOResult<()> {
+    for value in [Value::I16(-3), Value::U64(u64::MAX), Value::AF32(vec![0.1, -2.5]), Value::STR("text".to_string()),
+                  Value::ABOOL(vec![true, false]), Value::ASTR(vec!["A".to_string(), "B".to_string()])] {
+        let json = serde_json::to_string(&value)?;
+        assert_eq!(serde_json::from_str::<Value>(&json)?, value);
+    }
+    assert_eq!(serde_json::to_string(&Value::I16(5))?, r#"{"type":"int16","value":5}"#);
+    assert!(serde_json::from_str::<Value>(&serde_json::to_string(&Value::F64(f64::NAN))?)?.to_f64().unwrap().is_nan());
+    assert!(serde_json::from_str::<Value>(r#"{"type":"int8","value":300}"#).is_err());
+    assert!(serde_json::from_str::<Value>(r#"{"type":"int8","value":1.5}"#).is_err());
+    assert!(serde_json::from_str::<Value>(r#"{"type":"complex","value":1}"#).is_err());
+
+    let channels = vec![
+        channel::new("counter".to_string(), "uint64".to_string(), None, true, Compression::None, false)?,
+        channel::new("temperature".to_string(), "int16".to_string(), None, false, Compression::None, false)?,
+        channel::new("image".to_string(), "uint16".to_string(), Some(vec![4, 2]), false, Compression::BitshuffleLz4, false)?,
+        channel::new("state".to_string(), "string".to_string(), None, true, Compression::None, false)?,
+    ];
+    let config = channels[2].config();
+    let json = serde_json::to_string(config)?;
+    assert_eq!(json, r#"{"name":"image","type":"uint16","shape":[4,2],"encoding":"big","compression":"bitshuffle_lz4"}"#);
+    assert_eq!(&serde_json::from_str::<ChannelConfig>(&json)?, config);
+    let message = Message::new_from_channel_vec(1, (2, 3), &channels[..2].to_vec(), vec![Some(ChannelData::new(Value::U64(1), (2, 3))), None])?;
+    let json = serde_json::to_string(&message)?;
+    assert!(json.contains(r#""data":[{"type":"uint64","value":1,"timestamp":[2,3]},null]"#));
+    let deserialized: Message = serde_json::from_str(&json)?;
+    assert!(deserialized == message);
+    assert_eq!(deserialized.channel_data("temperature"), None);
+    assert!(serde_json::from_str::<Message>(&json.replace(",null]", "]")).is_err());
+
+    //Deserialized messages can be sent
+    for i in 1..=3u64 {
+        let data = vec![
+            Some(ChannelData::new(Value::U64(i), (10, i))),
+            Some(ChannelData::new(Value::I16(-(i as i16)), (11, 0))),
+            Some(ChannelData::new(Value::AU16((0..8).map(|x| x * i as u16).collect()), (12, 0))),
+            Some(ChannelData::new(Value::STR(format!("state {}", i)), (13, 0))),
+        ];
+        let message = Message::new_from_channel_vec(i, (1000, i), &channels, data)?;
+        let json = serde_json::to_string(&message)?;
+        assert!(json.contains(r#""type":"int16""#));
+        let sent: Message = serde_json::from_str(&json)?;
+        let received = transfer(&sent)?;
+        assert_eq!((received.id(), received.timestamp()), (sent.id(), sent.timestamp()));
+        for channel in ["counter", "temperature", "image", "state"] {
+            assert_eq!(received.channel_data(channel), sent.channel_data(channel));
+        }
+        let json = serde_json::to_string(&received)?;
+        assert!(serde_json::from_str::<Message>(&json)? == received);
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "arrow")]
+fn arrow_record_batches() ->  IOResult<()> {
+    use arrow_array::{Array, FixedSizeListArray, Float64Array, StringArray, TimestampNanosecondArray, UInt16Array, UInt64Array};
+    let channels = vec![
+        channel::new("current".to_string(), "float64".to_string(), None, true, Compression::None, false)?,
+        channel::new("image".to_string(), "uint16".to_string(), Some(vec![4, 2]), true, Compression::None, false)?,
+        channel::new("state".to_string(), "string".to_string(), None, true, Compression::None, false)?,
+    ];
+    let mut messages = Vec::new();
+    for i in 0..3u64 {
+        let image = if i == 1 { None } else { Some(ChannelData::new(Value::AU16((0..8).map(|x| x + i as u16).collect()), (5, i))) };
+        let data = vec![Some(ChannelData::new(Value::F64(i as f64), (4, i))), image, Some(ChannelData::new(Value::STR(format!("S{}", i)), (6, 0)))];
+        messages.push(Message::new_from_channel_vec(100 + i, (1, i), &channels, data)?);
+    }
+    let other = vec![channel::new("current".to_string(), "float64".to_string(), None, true, Compression::None, false)?];
+    messages.push(Message::new_from_channel_vec(103, (1, 3), &other, vec![Some(ChannelData::new(Value::F64(3.0), (4, 3)))])?);
+
+    let batches = arrow::to_record_batches(&messages)?;
+    assert_eq!(batches.len(), 2);
+    let batch = &batches[0];
+    assert_eq!((batch.num_rows(), batch.num_columns()), (3, 8));
+    let schema = batch.schema();
+    let names: Vec<&str> = schema.fields().iter().map(|field| field.name().as_str()).collect();
+    assert_eq!(names, vec!["pulse_id", "global_timestamp", "current", "current.timestamp", "image", "image.timestamp", "state", "state.timestamp"]);
+    assert_eq!(schema.field(4).metadata().get("shape").map(String::as_str), Some("[4,2]"));
+    let ids = batch.column(0).as_any().downcast_ref::<UInt64Array>().unwrap();
+    assert_eq!(ids.values().to_vec(), vec![100, 101, 102]);
+    let timestamps = batch.column(1).as_any().downcast_ref::<TimestampNanosecondArray>().unwrap();
+    assert_eq!(timestamps.value(2), 1_000_000_002);
+    let current = batch.column(2).as_any().downcast_ref::<Float64Array>().unwrap();
+    assert_eq!(current.value(1), 1.0);
+    let image = batch.column(4).as_any().downcast_ref::<FixedSizeListArray>().unwrap();
+    assert_eq!(image.value_length(), 8);
+    assert!(image.is_null(1));
+    assert_eq!(image.value(2).as_any().downcast_ref::<UInt16Array>().unwrap().values().to_vec(), (2..10).collect::<Vec<u16>>());
+    let image_timestamps = batch.column(5).as_any().downcast_ref::<TimestampNanosecondArray>().unwrap();
+    assert!(image_timestamps.is_null(1));
+    assert_eq!(image_timestamps.value(2), 5_000_000_002);
+    assert_eq!(batch.column(6).as_any().downcast_ref::<StringArray>().unwrap().value(0), "S0");
+    assert_eq!((batches[1].num_rows(), batches[1].num_columns()), (1, 4));
+
+    assert!(arrow::to_record_batch(&messages).err().unwrap().is_validation());
+    let empty = arrow::to_record_batch(&[]).err().unwrap();
+    assert!(matches!(empty, BsreadError::Validation{reason: ValidationError::EmptyMessageList, ..}));
+    let invalid = Message::new_from_channel_vec(104, (1, 4), &channels,
+        vec![Some(ChannelData::new(Value::I32(1), (0, 0))), None, None])?;
+    let error = arrow::to_record_batch(&[invalid]).err().unwrap();
+    assert_eq!(error.context().unwrap().channel.as_deref(), Some("current"));
+    Ok(())
+}
+
+#[test]
+fn capture_recorder() ->  IOResult<()> {
+    let path = std::env::temp_dir().join(format!("bsread_capture_{}.bscap", std::process::id()));
+    let recorder = Recorder::create(&path)?;
+    let env = TestEnvironment::new()?;
+    let messages = (0..MESSAGE_COUNT).map(|i| MessageBuilder::new()
+        .channel("counter", i as u64)
+        .channel_with("waveform", vec![i as f32; 100], Compression::BitshuffleLz4)
+        .build()).collect::<BsreadResult<Vec<Message>>>()?;
+    let (rec, rx) = roundtrip(&env, |rec| Ok(rec.set_recorder(Some(recorder.clone()))), messages)?;
+    assert_eq!(recorder.count(), MESSAGE_COUNT as u64);
+
+    let captured = CaptureReader::open(&path)?.collect::<BsreadResult<Vec<CapturedMessage>>>()?;
+    assert_eq!(captured.len(), MESSAGE_COUNT as usize);
+    let mut headers = utils::LimitedHashMap::new(10);
+    for (capture, received) in captured.iter().zip(rx.iter()) {
+        assert_eq!(capture.endpoint.as_ref(), Some(&rec.endpoints()[0]));
+        assert!(capture.timestamp.0 > 0);
+        let message = message::parse_message(capture.frames.clone(), &capture.endpoint, &mut headers, false)?;
+        assert!(message == received.message);
+    }
+
+    //Truncated files are readable up to the last complete record
+    let size = std::fs::metadata(&path)?.len();
+    std::fs::OpenOptions::new().write(true).open(&path)?.set_len(size - 10)?;
+    let mut reader = CaptureReader::open(&path)?;
+    assert_eq!(reader.by_ref().filter(Result::is_ok).count(), MESSAGE_COUNT as usize - 1);
+    assert!(reader.is_truncated());
+
+    //Appending discards the truncated record
+    let recorder = Recorder::append(&path)?;
+    assert_eq!(recorder.count(), MESSAGE_COUNT as u64 - 1);
+    recorder.record_at((1, 2), &None, &[b"frame".to_vec(), Vec::new()])?;
+    let captured = CaptureReader::open(&path)?.collect::<BsreadResult<Vec<CapturedMessage>>>()?;
+    assert_eq!(captured.len(), MESSAGE_COUNT as usize);
+    assert_eq!(captured.last().unwrap(), &CapturedMessage{timestamp: (1, 2), endpoint: None, frames: vec![b"frame".to_vec(), Vec::new()]});
+
+    //Corrupted records fail the checksum, the following ones are still read
+    let mut bytes = std::fs::read(&path)?;
+    bytes[40] ^= 0xFF;
+    let results: Vec<_> = CaptureReader::new(bytes.as_slice())?.collect();
+    assert_eq!(results.len(), MESSAGE_COUNT as usize);
+    assert!(results[0].as_ref().err().unwrap().to_string().contains("checksum"));
+    assert!(results[1..].iter().all(Result::is_ok));
+
+    //Corrupted lengths are not trusted: the reader resyncs on the next record
+    let mut corrupted = bytes.clone();
+    corrupted[20..24].copy_from_slice(&u32::MAX.to_le_bytes());
+    let results: Vec<_> = CaptureReader::new(corrupted.as_slice())?.collect();
+    assert_eq!(results.len(), MESSAGE_COUNT as usize);
+    assert!(results[0].as_ref().err().unwrap().to_string().contains("length"));
+    assert!(results[1..].iter().all(Result::is_ok));
+    let mut corrupted = bytes.clone();
+    corrupted.splice(16..16, b"garbage".iter().copied());
+    let results: Vec<_> = CaptureReader::new(corrupted.as_slice())?.collect();
+    assert_eq!(results.len(), MESSAGE_COUNT as usize + 1);
+    assert!(results[0].is_err() && results[2..].iter().all(Result::is_ok));
+    corrupted.truncate(bytes.len() - 5);
+    corrupted.extend_from_slice(b"garbage");
+    let mut reader = CaptureReader::new(corrupted.as_slice())?;
+    assert_eq!(reader.by_ref().filter(Result::is_ok).count(), MESSAGE_COUNT as usize - 2);
+    assert!(reader.is_truncated());
+    assert!(CaptureReader::new(&b"not a capture file"[..]).is_err());
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn capture_replayer() ->  IOResult<()> {
+    let path = std::env::temp_dir().join(format!("bsread_replay_{}.bscap", std::process::id()));
+    let env = TestEnvironment::new()?;
+    let received = std::env::temp_dir().join(format!("bsread_replay_{}_received.bscap", std::process::id()));
+    let messages = (0..MESSAGE_COUNT).map(|i| MessageBuilder::new().timestamp((100, 0)).channel("counter", i as u64).build())
+        .collect::<BsreadResult<Vec<Message>>>()?;
+    let recorder = Recorder::create(&received)?;
+    roundtrip(&env, |rec| Ok(rec.set_recorder(Some(recorder))), messages)?;
+    //Same capture with receive times 20ms apart
+    let recorder = Recorder::create(&path)?;
+    for (i, captured) in CaptureReader::open(&received)?.enumerate() {
+        let captured = captured?;
+        recorder.record_at((1000, i as u64 * 20_000_000), &captured.endpoint, &captured.frames)?;
+    }
+    std::fs::remove_file(&received)?;
+    let ids: Vec<u64> = CaptureReader::open(&path)?.map(|captured| {
+        let header: serde_json::Value = serde_json::from_slice(&captured.unwrap().frames[0]).unwrap();
+        header["pulse_id"].as_u64().unwrap()
+    }).collect();
+
+    //Original ids between start and stop, at twice the speed
+    let mut replayer = Replayer::new(&path);
+    replayer.set_timing(ReplayTiming::Speed(2.0));
+    replayer.set_start_id(Some(ids[2]));
+    replayer.set_stop_id(Some(ids[7]));
+    let start = Instant::now();
+    let (_, rx) = roundtrip_with(&env, |_| Ok(()), 6, |sender| {
+        assert_eq!(replayer.replay(sender)?, 6);
+        Ok(())
+    })?;
+    assert!(start.elapsed() >= Duration::from_millis(50));
+    assert_eq!(rx.iter().map(|msg| msg.message.id()).collect::<Vec<u64>>(), ids[2..8].to_vec());
+    assert_eq!(rx[0].message.timestamp(), (100, 0));
+    assert_eq!(rx[0].message.channel_value("counter"), Some(&Value::U64(2)));
+
+    //Looping with rewritten ids and timestamps
+    let mut replayer = Replayer::new(&path);
+    replayer.set_timing(ReplayTiming::AsFastAsPossible);
+    replayer.set_loop(true);
+    replayer.set_rewrite_pulse_ids(true);
+    replayer.set_rewrite_timestamps(true);
+    replayer.set_max_messages(Some(2 * MESSAGE_COUNT as u64));
+    let (_, rx) = roundtrip_with(&env, |_| Ok(()), 2 * MESSAGE_COUNT as usize, |sender| {
+        assert_eq!(replayer.replay(sender)?, 2 * MESSAGE_COUNT as u64);
+        Ok(())
+    })?;
+    let now = utils::current_timestamp().0;
+    for (i, msg) in rx.iter().enumerate() {
+        assert!(i == 0 || msg.message.id() > rx[i - 1].message.id());
+        assert!(msg.message.timestamp().0 + 10 >= now);
+        let data = msg.message.channel_data("counter").unwrap();
+        assert!(data.timestamp().0 + 10 >= now);
+        assert_eq!(data.value(), &Value::U64((i % MESSAGE_COUNT as usize) as u64));
+    }
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn file_endpoint() ->  IOResult<()> {
+    let path = std::env::temp_dir().join(format!("bsread_file_endpoint_{}.bscap", std::process::id()));
+    let endpoint = format!("file://{}", path.display());
+    let env = TestEnvironment::new()?;
+    let messages = (0..MESSAGE_COUNT).map(|i| MessageBuilder::new()
+        .channel("counter", i as u64)
+        .channel_with("waveform", vec![i as f32; 100], Compression::BitshuffleLz4)
+        .build()).collect::<BsreadResult<Vec<Message>>>()?;
+    let recorder = Recorder::create(&path)?;
+    let (_, live) = roundtrip(&env, |rec| Ok(rec.set_recorder(Some(recorder))), messages)?;
+
+    //Inline: the listener returns at the end of the file
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let received_cb = received.clone();
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    rec.listen(move |msg| received_cb.lock().unwrap().push(msg), None)?;
+    let received = received.lock().unwrap();
+    assert_eq!(received.len(), MESSAGE_COUNT as usize);
+    for (msg, live) in received.iter().zip(live.iter()) {
+        assert_eq!(msg.endpoint.as_deref(), Some(endpoint.as_str()));
+        assert!(msg.message == live.message);
+    }
+    //Data header is parsed once
+    assert_eq!(rec.header_changes(&endpoint), 1);
+    assert_eq!(rec.error_count(), 0);
+    assert!(rec.receive().is_err_and(|e| e.kind() == ErrorKind::UnexpectedEof));
+
+    //Buffered: the receiver thread ends at the end of the file
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Shared)?;
+    rec.start(100)?;
+    let rx = rec.wait_messages(MESSAGE_COUNT as usize, 2000)?;
+    assert_eq!(rx.iter().map(|msg| msg.message.id()).collect::<Vec<u64>>(), live.iter().map(|msg| msg.message.id()).collect::<Vec<u64>>());
+    assert!(rec.wait(100).is_err());
+    assert!(!rec.is_running());
+    rec.stop()?;
+
+    let mut rec = env.bsread.receiver(Some(vec!["file:///nonexistent/capture.bscap"]), SocketType::PULL, CONNECTION_MODE)?;
+    assert_eq!(rec.connect().unwrap_err().kind(), ErrorKind::NotFound);
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn file_endpoint_sources() ->  IOResult<()> {
+    let path = std::env::temp_dir().join(format!("bsread_file_endpoint_sources_{}.bscap", std::process::id()));
+    let endpoint = format!("file://{}", path.display());
+    let env = TestEnvironment::new()?;
+    //Two sources with the same pulse IDs, far from the current ID, and a gap in the second one
+    let sources = [Some("tcp://source1:9999".to_string()), Some("tcp://source2:9999".to_string())];
+    let first_id = 1_000_000_000;
+    let recorder = Recorder::create(&path)?;
+    for i in 0..MESSAGE_COUNT {
+        for (index, source) in sources.iter().enumerate() {
+            if index == 1 && i == 2 {
+                continue;
+            }
+            let message = MessageBuilder::new().pulse_id(first_id + i as u64).channel(&format!("counter{}", index), i as u64).build()?;
+            recorder.record_at((1_000 + i as u64, index as u64), source, &message_frames(&message)?)?;
+        }
+    }
+    drop(recorder);
+
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    let received = Mutex::new(Vec::new());
+    rec.listen(|msg| received.lock().unwrap().push(msg.message.id()), None)?;
+    assert_eq!(received.lock().unwrap().len(), 2 * MESSAGE_COUNT as usize - 1);
+    assert_eq!(rec.error_count(), 0);
+    //One data header per source
+    assert_eq!(rec.header_changes(&endpoint), 2);
+    assert_eq!(rec.endpoint_diagnostic(&endpoint, EndpointDiag::MissingPulses), Some(1));
+    std::fs::remove_file(&path)?;
+    Ok(())
+}
+
+#[test]
+fn gap_detection() ->  IOResult<()> {
+    let env = TestEnvironment::new()?;
+    for (id_step, ids, gaps) in [(1, vec![1, 2, 3, 6, 7, 10], vec![(4, 5), (8, 9)]), (2, vec![2, 4, 8, 10, 16], vec![(6, 6), (12, 14)])] {
+        let messages = ids.iter().map(|id| MessageBuilder::new().pulse_id(*id).channel("counter", *id).build())
+            .collect::<BsreadResult<Vec<Message>>>()?;
+        let mut events = None;
+        let (rec, _) = roundtrip(&env, |rec| {
+            rec.set_id_step(id_step);
+            events = Some(rec.enable_monitoring()?);
+            Ok(())
+        }, messages)?;
+        let events = events.unwrap();
+        let endpoint = rec.endpoints()[0].clone();
+        let endpoint = endpoint.as_str();
+        let missing: u64 = gaps.iter().map(|(first, last)| (last - first) / id_step + 1).sum();
+        assert_eq!(rec.endpoint_diagnostic(endpoint, EndpointDiag::MissingPulses), Some(missing));
+        assert_eq!(rec.endpoint_diagnostic(endpoint, EndpointDiag::Gaps), Some(gaps.len() as u64));
+        assert_eq!(rec.endpoint_diagnostic(endpoint, EndpointDiag::LargestGap), Some((gaps[1].1 - gaps[1].0) / id_step + 1));
+        let received_gaps: Vec<(u64, u64)> = events.try_iter()
+            .filter_map(|event| match event {
+                EndpointEvent::Gap(ep, first, last) if ep == endpoint => Some((first, last)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(received_gaps, gaps);
+    }
+    Ok(())
+}
+
+#[test]
+fn timing_stats() ->  IOResult<()> {
+    let mut histogram = Histogram::new();
+    assert_eq!(histogram.percentile(50.0), None);
+    for value in 1..=1000 {
+        histogram.record(value);
+    }
+    histogram.record(-100);
+    assert_eq!((histogram.count(), histogram.min(), histogram.max()), (1001, Some(-100), Some(1000)));
+    assert_eq!(histogram.percentile(0.0), Some(-100));
+    assert_eq!(histogram.percentile(100.0), Some(1000));
+    let median = histogram.percentile(50.0).unwrap();
+    assert!((470..=530).contains(&median), "{}", median);
+    assert!((histogram.mean().unwrap() - 500.0).abs() < 1.0);
+    histogram.reset();
+    assert_eq!(histogram.count(), 0);
+
+    //Messages timestamped 1s in the past, with channel timestamps 2ms after the global timestamp
+    let env = TestEnvironment::new()?;
+    let message = MessageBuilder::new().channel("counter", 0u64).build()?;
+    let (mut rec, _) = roundtrip_with(&env, |rec| Ok(rec.set_lazy(true)), MESSAGE_COUNT as usize, |sender| {
+        sender.create_data_header(message.channels())?;
+        for i in 0..MESSAGE_COUNT as u64 {
+            let now = utils::current_timestamp();
+            let global = (now.0 - 1, now.1);
+            let data = ChannelData::new(Value::U64(i), (global.0, global.1 + 2_000_000));
+            sender.send(i + 1, global, message.channels(), &vec![Some(&data)])?;
+        }
+        Ok(())
+    })?;
+    let timing = rec.endpoint_timing_stats(&rec.endpoints()[0]).unwrap();
+    assert_eq!(timing.latency.count(), MESSAGE_COUNT as u64);
+    assert!(timing.latency.min().unwrap() >= 1_000_000);
+    assert!(timing.latency.max().unwrap() < 1_500_000);
+    assert_eq!((timing.skew.min(), timing.skew.max(), timing.skew.percentile(50.0)), (Some(2000), Some(2000), Some(2000)));
+    assert_eq!(rec.timing_stats().len(), 1);
+    rec.reset_counters();
+    assert!(rec.timing_stats().is_empty());
+
+    //Channels without timestamp frame have no skew, and messages without global timestamp are not timed
+    let (rec, _) = roundtrip_with(&env, |_| Ok(()), 2, |sender| {
+        let now = utils::current_timestamp();
+        let message = MessageBuilder::new().pulse_id(1).timestamp((now.0 - 1, now.1)).channel("counter", 0u64).channel("value", 1.0).build()?;
+        let mut frames = message_frames(&message)?;
+        frames[5].clear();
+        sender.forward(&frames)?;
+        let message = MessageBuilder::new().pulse_id(2).channel("counter", 1u64).build()?;
+        sender.forward(&message_frames(&message)?)?;
+        Ok(())
+    })?;
+    let timing = rec.endpoint_timing_stats(&rec.endpoints()[0]).unwrap();
+    assert_eq!((timing.latency.count(), timing.skew.count()), (1, 1));
+    assert_eq!(timing.skew.max(), Some(0));
+    Ok(())
+}
+
+#[test]
+fn throughput() ->  IOResult<()> {
+    let env = TestEnvironment::new()?;
+    let channels = vec![
+        channel::new("waveform".to_string(), "float32".to_string(), Some(vec![1000]), true, Compression::BitshuffleLz4, false)?,
+        channel::new("scalar".to_string(), "float64".to_string(), None, true, Compression::None, false)?,
+    ];
+    let messages = (0..MESSAGE_COUNT).map(|i| {
+        let data = vec![
+            Some(ChannelData::new(Value::AF32(vec![i as f32; 1000]), TIMESTAMP_NOW)),
+            Some(ChannelData::new(Value::F64(i as f64), TIMESTAMP_NOW)),
+        ];
+        Message::new_from_channel_vec(ID_SIMULATED, TIMESTAMP_NOW, &channels, data)
+    }).collect::<BsreadResult<Vec<Message>>>()?;
+    let (mut rec, _) = roundtrip(&env, |_| Ok(()), messages)?;
+    let throughput = rec.endpoint_throughput(&rec.endpoints()[0]).unwrap();
+    assert_eq!(throughput.messages, MESSAGE_COUNT as u64);
+    assert_eq!(throughput.frames, 6 * MESSAGE_COUNT as u64);
+    assert!(throughput.decompressed_bytes > throughput.compressed_bytes);
+    assert!(throughput.compression_ratio().unwrap() > 1.0);
+    let waveform = throughput.channels.get("waveform").unwrap();
+    assert_eq!((waveform.messages, waveform.decompressed_bytes), (MESSAGE_COUNT as u64, 4000 * MESSAGE_COUNT as u64));
+    assert!(waveform.compressed_bytes < waveform.decompressed_bytes);
+    let scalar = throughput.channels.get("scalar").unwrap();
+    assert_eq!((scalar.compressed_bytes, scalar.decompressed_bytes), (8 * MESSAGE_COUNT as u64, 8 * MESSAGE_COUNT as u64));
+    let rates = throughput.rates();
+    assert!(rates.messages_per_second > 0.0);
+    assert!(rates.decompressed_bytes_per_second > rates.bytes_per_second);
+    rec.reset_counters();
+    assert!(rec.throughput().is_empty());
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn receiver_stream() ->  IOResult<()> {
+    use futures::StreamExt;
+    let env = TestEnvironment::new()?;
+    let (mut senders, endpoints): (Vec<Sender>, Vec<String>) = (0..3).map(|_| push_sender(&env)).collect::<IOResult<Vec<_>>>()?.into_iter().unzip();
+    let send =|sender: &mut Sender, count: u64| -> IOResult<()> {
+        for id in 1..=count {
+            let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build()?;
+            sender.send_message(&message, id == 1)?;
+        }
+        Ok(())
+    };
+    //Streams are fed by tasks: a single worker and blocking thread are enough
+    let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).max_blocking_threads(1).enable_all().build().unwrap();
+    runtime.block_on(async {
+        //Reception waits while the buffer is full
+        let rec = env.bsread.receiver(Some(vec![endpoints[0].as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+        let mut stream = rec.into_stream(2);
+        send(&mut senders[0], MESSAGE_COUNT as u64)?;
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        assert!(stream.message_count() < MESSAGE_COUNT as u64);
+        for id in 1..=MESSAGE_COUNT as u64 {
+            let msg = tokio::time::timeout(Duration::from_secs(2), stream.next()).await.unwrap().unwrap()?;
+            assert_eq!(msg.message.id(), id);
+        }
+        drop(stream);
+
+        let pool = env.bsread.pool(vec![endpoints[1].as_str(), endpoints[2].as_str()], SocketType::PULL, ConnectionMode::Individual, 2)?;
+        let stream = pool.into_stream(100);
+        send(&mut senders[1], MESSAGE_COUNT as u64)?;
+        send(&mut senders[2], MESSAGE_COUNT as u64)?;
+        let messages: Vec<IOResult<ReceivedMessage>> = tokio::time::timeout(Duration::from_secs(2), stream.take(2 * MESSAGE_COUNT as usize).collect()).await.unwrap();
+        let messages = messages.into_iter().collect::<IOResult<Vec<_>>>()?;
+        for endpoint in endpoints[1..].iter() {
+            assert_eq!(messages.iter().filter(|msg| msg.endpoint.as_ref() == Some(endpoint)).count(), MESSAGE_COUNT as usize);
+        }
+
+        //The stream ends with the capture file
+        let path = std::env::temp_dir().join(format!("bsread_receiver_stream_{}.bscap", std::process::id()));
+        let recorder = Recorder::create(&path)?;
+        for id in 1..=MESSAGE_COUNT as u64 {
+            let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build()?;
+            recorder.record(&None, &message_frames(&message)?)?;
+        }
+        let endpoint = format!("file://{}", path.display());
+        let rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+        let messages: Vec<IOResult<ReceivedMessage>> = tokio::time::timeout(Duration::from_secs(2), rec.into_stream(2).collect()).await.unwrap();
+        assert_eq!(messages.into_iter().collect::<IOResult<Vec<_>>>()?.len(), MESSAGE_COUNT as usize);
+        std::fs::remove_file(&path)?;
+        Ok::<(), IOError>(())
+    })?;
+    for sender in senders.iter_mut() {
+        sender.stop();
+    }
+    Ok(())
+}
+
+#[test]
+#[cfg(feature = "async")]
+fn async_receivers_share_runtime() ->  IOResult<()> {
+    const RECEIVERS: u32 = 8;
+    let env = TestEnvironment::new()?;
+    //A single worker and blocking thread: listeners must not hold threads
+    let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(1).max_blocking_threads(1).enable_all().build().unwrap();
+    let received = Arc::new(AtomicUsize::new(0));
+    let mut senders = Vec::new();
+    let mut receivers = Vec::new();
+    for _ in 0..RECEIVERS {
+        let (sender, endpoint) = push_sender(&env)?;
+        senders.push(sender);
+        receivers.push(env.bsread.receiver(Some(vec![&endpoint]), SocketType::PULL, ConnectionMode::Individual)?);
+    }
+    runtime.block_on(async {
+        for rec in receivers.iter_mut() {
+            let received = received.clone();
+            let callback = move |_msg| {
+                let received = received.clone();
+                async move {
+                    received.fetch_add(1, Ordering::SeqCst);
+                }
+            };
+            rec.start_async(callback, None, false, None);
+        }
+        for sender in senders.iter_mut() {
+            for id in 1..=MESSAGE_COUNT as u64 {
+                let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build().unwrap();
+                sender.send_message(&message, id == 1).unwrap();
+            }
+        }
+        //All listeners run at the same time
+        let expected = (RECEIVERS * MESSAGE_COUNT) as usize;
+        let start = Instant::now();
+        while received.load(Ordering::SeqCst) < expected && start.elapsed() < Duration::from_secs(2) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(received.load(Ordering::SeqCst), expected);
+        //Listeners are woken by the interruption
+        for rec in receivers.iter_mut() {
+            rec.interrupt();
+            tokio::time::timeout(Duration::from_millis(50), rec.join_async()).await.unwrap().unwrap();
+            assert_eq!(rec.message_count(), MESSAGE_COUNT as u64);
+        }
+    });
+
+    //Direct async reception
+    let mut rec = env.bsread.receiver(Some(vec![&receivers[0].endpoints()[0]]), SocketType::PULL, ConnectionMode::Individual)?;
+    rec.connect()?;
+    runtime.block_on(async {
+        assert!(tokio::time::timeout(Duration::from_millis(200), rec.receive_async()).await.is_err());
+        let message = MessageBuilder::new().pulse_id(100).channel("counter", 100u64).build().unwrap();
+        senders[0].send_message(&message, false).unwrap();
+        let msg = tokio::time::timeout(Duration::from_secs(2), rec.receive_async()).await.unwrap().unwrap();
+        assert_eq!(msg.message.id(), 100);
+    });
+    for sender in senders.iter_mut() {
+        sender.stop();
+    }
+    Ok(())
+}
+
+#[test]
+fn buffer_overflow_policies() ->  IOResult<()> {
+    let env = TestEnvironment::new()?;
+    let (mut sender, endpoint) = push_sender(&env)?;
+    let send = |sender: &mut Sender, first: u64| {
+        for id in first..first + MESSAGE_COUNT as u64 {
+            let message = MessageBuilder::new().pulse_id(id).channel("image", vec![id as u8; 1000]).build().unwrap();
+            sender.send_message(&message, id == first).unwrap();
+        }
+    };
+
+    //Reception waits for the buffer to be consumed: nothing is dropped
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    rec.start_with_policy(2, OverflowPolicy::Wait, None)?;
+    send(&mut sender, 1);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(rec.available(), 2);
+    let messages = rec.wait_messages(MESSAGE_COUNT as usize, 2000)?;
+    assert_eq!(messages.iter().map(|msg| msg.message.id()).collect::<Vec<_>>(), (1..=MESSAGE_COUNT as u64).collect::<Vec<_>>());
+    assert_eq!(rec.dropped(), 0);
+    //Stopping releases the waiting listener
+    send(&mut sender, 101);
+    thread::sleep(Duration::from_millis(200));
+    rec.stop()?;
+
+    //The byte budget holds 2 messages: the next are dropped
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    rec.start_with_policy(100, OverflowPolicy::DropNewest, Some(2500))?;
+    send(&mut sender, 201);
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(rec.available(), 2);
+    assert_eq!(rec.dropped(), (MESSAGE_COUNT - 2) as u64);
+    assert_eq!(rec.get().unwrap().message.id(), 201);
+    let fifo = rec.fifo().unwrap();
+    assert!(fifo.available_bytes() > 1000 && fifo.available_bytes() <= 2500);
+    rec.stop()?;
+    sender.stop();
+    Ok(())
+}
+
+#[cfg(feature = "async")]
+#[test]
+fn async_sender() ->  IOResult<()> {
+    let env = TestEnvironment::new()?;
+    let runtime = new_tokio_runtime();
+    //The I/O task needs a runtime
+    let (sender, _) = push_sender(&env)?;
+    assert_eq!(AsyncSender::new(sender, 2, OverflowPolicy::Wait).err().unwrap().kind(), ErrorKind::Unsupported);
+    let _runtime_guard = runtime.enter();
+
+    //Waits for space: nothing is lost
+    let (sender, endpoint) = push_sender(&env)?;
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    rec.start(100)?;
+    let async_sender = AsyncSender::new(sender, 2, OverflowPolicy::Wait)?;
+    let sender = runtime.block_on(async {
+        for id in 1..=MESSAGE_COUNT as u64 {
+            let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build().unwrap();
+            assert!(async_sender.send_message(message, id == 1).await.unwrap());
+            assert!(async_sender.queued() <= 2);
+        }
+        async_sender.close().await
+    })?;
+    let messages = rec.wait_messages(MESSAGE_COUNT as usize, 2000)?;
+    assert_eq!(messages.last().unwrap().message.id(), MESSAGE_COUNT as u64);
+    assert_eq!(sender.message_count(), MESSAGE_COUNT as u64);
+    rec.stop()?;
+
+    //No peer: the I/O task waits on the first message, and the queue overflows
+    for policy in [OverflowPolicy::DropOldest, OverflowPolicy::DropNewest] {
+        let (sender, endpoint) = push_sender(&env)?;
+        let async_sender = AsyncSender::new(sender, 2, policy)?;
+        assert_eq!(async_sender.policy(), policy);
+        runtime.block_on(async {
+            for id in 1..=MESSAGE_COUNT as u64 {
+                let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build().unwrap();
+                async_sender.send_message(message, true).await.unwrap();
+            }
+        });
+        let dropped = async_sender.dropped_count();
+        assert!(dropped >= (MESSAGE_COUNT - 3) as u64);
+        let mut rec = env.bsread.receiver(Some(vec![&endpoint]), SocketType::PULL, ConnectionMode::Individual)?;
+        rec.start(100)?;
+        let mut sender = runtime.block_on(async_sender.close())?;
+        let expected = MESSAGE_COUNT as u64 - dropped;
+        assert_eq!(sender.message_count(), expected);
+        let messages = rec.wait_messages(expected as usize, 2000)?;
+        let ids: Vec<u64> = messages.iter().map(|msg| msg.message.id()).collect();
+        match policy {
+            OverflowPolicy::DropOldest => assert_eq!(*ids.last().unwrap(), MESSAGE_COUNT as u64),
+            _ => assert_eq!(ids[0], 1),
+        }
+        rec.stop()?;
+        sender.stop();
+    }
+
+    //No peer, non-blocking: the socket does not accept the messages, which are dropped
+    let port = NEXT_PORT.fetch_add(1, Ordering::SeqCst);
+    let mut sender = env.bsread.sender(SocketType::PUSH, Transport::Tcp{port, host:None}, Some(false), None, None)?;
+    sender.start()?;
+    let async_sender = AsyncSender::new(sender, MESSAGE_COUNT as usize, OverflowPolicy::Wait)?;
+    runtime.block_on(async {
+        for id in 1..=MESSAGE_COUNT as u64 {
+            let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build().unwrap();
+            assert!(async_sender.send_message(message, true).await.unwrap());
+        }
+    });
+    let start = Instant::now();
+    while async_sender.dropped_count() < MESSAGE_COUNT as u64 && start.elapsed() < Duration::from_secs(2) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(async_sender.dropped_count(), MESSAGE_COUNT as u64);
+    let mut sender = runtime.block_on(async_sender.close())?;
+    assert_eq!(sender.message_count(), 0);
+    sender.stop();
+
+    //No peer, dropped: the I/O task exits and closes the socket, releasing the port
+    let (sender, _) = push_sender(&env)?;
+    let transport = sender.transport();
+    let async_sender = AsyncSender::new(sender, 2, OverflowPolicy::DropOldest)?;
+    runtime.block_on(async {
+        for id in 1..=MESSAGE_COUNT as u64 {
+            let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build().unwrap();
+            async_sender.send_message(message, true).await.unwrap();
+        }
+    });
+    drop(async_sender);
+    let mut sender = env.bsread.sender(SocketType::PUSH, transport, Some(true), None, None)?;
+    let start = Instant::now();
+    while sender.start().is_err() {
+        assert!(start.elapsed() < Duration::from_secs(2));
+        thread::sleep(Duration::from_millis(10));
+    }
+    sender.stop();
+    Ok(())
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics_exporter() ->  IOResult<()> {
+    use std::io::{Read, Write};
+    let env = TestEnvironment::new()?;
+    let (mut sender, endpoint) = push_sender(&env)?;
+    let mut rec = env.bsread.receiver(Some(vec![endpoint.as_str()]), SocketType::PULL, ConnectionMode::Individual)?;
+    rec.enable_monitoring()?;
+    rec.start(100)?;
+    let mut exporter = MetricsExporter::new("127.0.0.1:0")?;
+    exporter.add_receiver(&rec);
+    exporter.add_sender(&sender);
+    for id in [1, 2, 2, 5] {
+        let message = MessageBuilder::new().pulse_id(id).channel("counter", id).build()?;
+        sender.send_message(&message, id == 1)?;
+    }
+    //Messages are left in the buffer: the repeated ID is a receive error. The monitor updates the state asynchronously.
+    let start = Instant::now();
+    while (rec.available() < 3 || !exporter.render().contains("state=\"connected\"} 1")) && start.elapsed() < Duration::from_secs(2) {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut stream = std::net::TcpStream::connect(exporter.address())?;
+    stream.write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    let receiver = format!("receiver=\"{}\"", rec.index());
+    let labels = format!("{{{},endpoint=\"{}\"}}", receiver, endpoint);
+    assert!(response.contains(&format!("bsread_receiver_messages_total{{{}}} 3", receiver)));
+    assert!(response.contains(&format!("bsread_receiver_errors_total{{{}}} 1", receiver)));
+    assert!(response.contains(&format!("bsread_endpoint_repeated_id_total{} 1", labels)));
+    assert!(response.contains(&format!("bsread_endpoint_missing_pulses_total{} 2", labels)));
+    assert!(response.contains(&format!("bsread_endpoint_largest_gap{} 2", labels)));
+    assert!(response.contains("# TYPE bsread_endpoint_largest_gap gauge"));
+    assert!(response.contains(&format!("bsread_endpoint_state{{{},endpoint=\"{}\",state=\"connected\"}} 1", receiver, endpoint)));
+    assert!(response.contains(&format!("bsread_fifo_available{{{}}} 3", receiver)));
+    assert!(response.contains(&format!("bsread_fifo_dropped_total{{{}}} 0", receiver)));
+    assert!(response.contains(&format!("bsread_sender_messages_total{{endpoint=\"{}\"}} 4", sender.endpoint())));
+    assert_eq!(sender.message_count(), 4);
+    assert!(sender.byte_count() > 0);
+
+    //An idle client doesn't hold back the others
+    let _idle = std::net::TcpStream::connect(exporter.address())?;
+    let mut stream = std::net::TcpStream::connect(exporter.address())?;
+    stream.write_all(b"GET /other HTTP/1.1\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 404"));
+    exporter.stop();
+    assert!(std::net::TcpStream::connect(exporter.address()).is_err());
+    rec.stop()?;
+    sender.stop();
+    Ok(())
+}
+
+#[test]
+fn synchronizer() ->  IOResult<()> {
+    let received = |endpoint: &str, id: u64| -> BsreadResult<ReceivedMessage> {
+        let message = MessageBuilder::new().pulse_id(id).channel(&format!("{}:value", endpoint), id).build()?;
+        Ok(ReceivedMessage{endpoint: Some(endpoint.to_string()), message})
+    };
+    let sync = Synchronizer::new(vec!["A", "B"], 100, 3, 100);
+    assert!(sync.add(received("A", 1)?));
+    assert!(sync.add(received("B", 1)?));
+    let event = sync.get().unwrap();
+    assert_eq!(event.pulse_id, 1);
+    assert!(event.is_complete());
+    assert_eq!(event.channel_data("B:value").unwrap().value(), &Value::U64(1));
+
+    //Complete pulses wait for older incomplete ones, which are emitted after the timeout
+    assert!(sync.add(received("A", 2)?));
+    assert!(sync.add(received("A", 3)?));
+    assert!(sync.add(received("B", 3)?));
+    assert!(!sync.add(received("C", 3)?));
+    assert!(sync.get().is_none());
+    let events = sync.wait_events(2, 500)?;
+    assert_eq!(events.iter().map(|event| event.pulse_id).collect::<Vec<u64>>(), vec![2, 3]);
+    assert_eq!(events[0].missing, vec!["B".to_string()]);
+    assert!(events[0].message("A").is_some());
+    assert!(events[1].is_complete());
+    assert!(!sync.add(received("B", 2)?));
+    assert_eq!(sync.late_count(), 1);
+
+    //Oldest pulses are evicted beyond max_pending
+    for id in 4..8 {
+        sync.add(received("A", id)?);
+    }
+    assert_eq!(sync.pending(), 3);
+    assert_eq!(sync.evicted_count(), 1);
+    assert_eq!(sync.get().unwrap().pulse_id, 4);
+    sync.flush_all();
+    assert_eq!(sync.available(), 3);
+    assert_eq!((sync.complete_count(), sync.incomplete_count()), (2, 5));
+
+    //Pool streams
+    let env = TestEnvironment::new()?;
+    let (mut senders, endpoints): (Vec<Sender>, Vec<String>) = (0..2).map(|_| push_sender(&env)).collect::<IOResult<Vec<_>>>()?.into_iter().unzip();
+    let mut pool = env.bsread.pool(endpoints.iter().map(String::as_str).collect(), SocketType::PULL, ConnectionMode::Individual, 2)?;
+    let sync = pool.fork_synchronized(200, 100, 100)?;
+    thread::sleep(Duration::from_millis(100));
+    for id in 1..=MESSAGE_COUNT as u64 {
+        for (index, sender) in senders.iter_mut().enumerate() {
+            if index == 1 && id == 5 {
+                continue;
+            }
+            let message = MessageBuilder::new().pulse_id(id).channel(&format!("value{}", index), id).build()?;
+            sender.send_message(&message, id == 1)?;
+        }
+    }
+    let events = sync.wait_events(MESSAGE_COUNT as usize, 2000)?;
+    pool.stop()?;
+    assert_eq!(events.iter().map(|event| event.pulse_id).collect::<Vec<u64>>(), (1..=MESSAGE_COUNT as u64).collect::<Vec<u64>>());
+    for event in events.iter() {
+        assert_eq!(event.channel_data("value0").unwrap().value(), &Value::U64(event.pulse_id));
+        if event.pulse_id == 5 {
+            assert_eq!(event.missing, vec![endpoints[1].to_string()]);
+        } else {
+            assert!(event.is_complete());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn typed_accessors() ->  IOResult<()> {
+    let message = MessageBuilder::new()
+        .pulse_id(100)
+        .channel("f64", 2.5)
+        .channel("u16", 300u16)
+        .channel("i32", -5)
+        .channel("flag", true)
+        .channel("name", "text")
+        .channel("labels", vec!["A".to_string(), "B".to_string()])
+        .channel("au8", vec![1u8, 2, 3])
+        .build()?;
+    assert_eq!(message.get::<f64>("f64")?, 2.5);
+    assert_eq!(message.get::<f32>("f64")?, 2.5);
+    assert_eq!(message.get::<i64>("f64")?, 2);
+    assert_eq!(message.get::<u32>("u16")?, 300);
+    assert_eq!(message.get::<f64>("u16")?, 300.0);
+    assert_eq!(message.get::<i8>("i32")?, -5);
+    assert_eq!(message.get::<u8>("flag")?, 1);
+    assert!(message.get::<bool>("flag")?);
+    assert_eq!(message.get::<String>("name")?, "text");
+    assert_eq!(message.get::<Vec<String>>("labels")?, vec!["A", "B"]);
+    assert_eq!(message.get::<Vec<u8>>("au8")?, vec![1, 2, 3]);
+    assert_eq!(message.get::<Vec<f64>>("au8")?, vec![1.0, 2.0, 3.0]);
+    assert_eq!(message.get::<Vec<bool>>("au8")?, vec![true, true, true]);
+
+    let error = message.get::<u8>("u16").err().unwrap();
+    assert!(error.is_conversion());
+    assert_eq!(error.context().unwrap().channel.as_deref(), Some("u16"));
+    assert_eq!(error.context().unwrap().pulse_id, Some(100));
+    assert!(error.to_string().contains("cannot convert U16 to u8"));
+    let error = message.get::<u32>("i32").err().unwrap();
+    assert!(error.to_string().contains("I32") && error.to_string().contains("i32"));
+    assert!(message.get::<Vec<u16>>("name").err().unwrap().to_string().contains("cannot convert STR to Vec<u16>"));
+    assert!(message.get::<String>("f64").err().unwrap().is_conversion());
+    let error = message.get::<f64>("missing").err().unwrap();
+    assert!(error.is_validation());
+    assert_eq!(error.context().unwrap().channel.as_deref(), Some("missing"));
+    assert_eq!(f64::try_from(&Value::U8(3))?, 3.0);
+    Ok(())
+}
+
+//Fixed, so that failures can be reproduced. BSREAD_FUZZ_SEED runs another seed instead, for exploratory runs.
+const FUZZ_SEEDS: [u64; 3] = [1, 42, 0x5eed];
+
+#[test]
+fn parsing_fuzz() ->  IOResult<()> {
+    use rand::SeedableRng;
+    let seeds = match std::env::var("BSREAD_FUZZ_SEED") {
+        Ok(seed) => vec![seed.parse::<u64>().map_err(|e| IOError::new(ErrorKind::InvalidInput, format!("Invalid BSREAD_FUZZ_SEED: {}", e)))?],
+        Err(_) => FUZZ_SEEDS.to_vec(),
+    };
+    for seed in seeds {
+        println!("Fuzz seed: {}", seed);
+        let mut rng = rand::rngs::SmallRng::seed_from_u64(seed);
+        let valid: Vec<Vec<Vec<u8>>> = vec![encode_frames(1, Compression::None)?, encode_frames(1, Compression::Lz4)?,
+                                            encode_frames(1, Compression::BitshuffleLz4)?];
+        let mut parsed = 0;
+        for _ in 0..5000 {
+            let mut frames = valid[rng.random_range(0..valid.len())].clone();
+            for _ in 0..rng.random_range(1..4) {
+                let index = rng.random_range(0..frames.len());
+                let frame = &mut frames[index];
+                match rng.random_range(0..6) {
+                    //Flip bytes
+                    0 => if !frame.is_empty() {
+                        for _ in 0..rng.random_range(1..4) {
+                            let pos = rng.random_range(0..frame.len());
+                            frame[pos] = rng.random();
+                        }
+                    },
+                    //Truncate
+                    1 => { let len = rng.random_range(0..=frame.len()); frame.truncate(len); }
+                    //Extend with garbage
+                    2 => for _ in 0..rng.random_range(1..16) { frame.push(rng.random()); },
+                    //Replace with garbage
+                    3 => { let mut buf = vec![0u8; rng.random_range(0..64)]; rng.fill(&mut buf[..]); *frame = buf; }
+                    //Drop a frame
+                    4 => { frames.remove(index); if frames.is_empty() { break; } }
+                    //Duplicate a frame
+                    _ => { let copy = frame.clone(); frames.insert(index, copy); }
+                }
+            }
+            if parse_frames(frames).is_ok() {
+                parsed += 1;
+            }
+        }
+        println!("Parsed {} mutated messages", parsed);
+
+        //Random input straight into the decompressors
+        for _ in 0..5000 {
+            let mut buf = vec![0u8; rng.random_range(0..64)];
+            rng.fill(&mut buf[..]);
+            let _ = decompress_lz4(&buf, rng.random());
+            let _ = decompress_bitshuffle_lz4(&buf, [1, 2, 4, 8][rng.random_range(0..4)]);
+            //Plausible bitshuffle header followed by garbage blocks
+            if buf.len() >= 12 {
+                buf[0..6].fill(0);
+                buf[8..10].fill(0);
+                buf[10] = [0, 0x20, 0x01][rng.random_range(0..3)];
+                buf[11] = 0;
+                let _ = decompress_bitshuffle_lz4(&buf, [1, 2, 4, 8][rng.random_range(0..4)]);
+            }
+        }
+    }
     Ok(())
 }
 
@@ -1299,7 +2787,7 @@ fn delayed() ->  IOResult<()> {
 fn flawed() ->  IOResult<()> {
     let env = TestEnvironment::new()?;
     let endpoint = TXP_FLAWED.endpoint();
-    let mut diag_counts: HashMap<EndpointDiag, u32> = HashMap::new();
+    let mut diag_counts: HashMap<EndpointDiag, u64> = HashMap::new();
     let mut rec = env.bsread.receiver(Some(vec![&endpoint]), SocketType::SUB, CONNECTION_MODE)?;
     let event_receiver = rec.enable_monitoring()?;
     rec.fork(on_message, Some(MESSAGE_COUNT));
@@ -1313,6 +2801,7 @@ fn flawed() ->  IOResult<()> {
                         EndpointEvent::Diagnostic(endpoint, diag) => {
                             *diag_counts.entry(diag).or_insert(0) += 1;
                         }
+                        EndpointEvent::Gap(..) => {}
                     }
                 }
             }
@@ -1325,8 +2814,10 @@ fn flawed() ->  IOResult<()> {
         for diag in EndpointDiag::ALL {
             println!("{:?}: {}", diag, diag_counts.get(&diag).unwrap_or(&0));
         }
-        assert_eq!(*diag_counts.get(&EndpointDiag::RepeatedId).unwrap_or(&0), total_rx/10 + if ((total_rx % 10)>=3) {1} else {0});
-        assert_eq!(*diag_counts.get(&EndpointDiag::DecreasingId).unwrap_or(&0), total_rx/10  + if ((total_rx % 10)>=7) {1} else {0});
+        //One of each per 10 messages, the count of the last ones depending on the sender position when the test started
+        let per_ten = total_rx/10..=total_rx/10 + 1;
+        assert!(per_ten.contains(diag_counts.get(&EndpointDiag::RepeatedId).unwrap_or(&0)));
+        assert!(per_ten.contains(diag_counts.get(&EndpointDiag::DecreasingId).unwrap_or(&0)));
     }
     Ok(())
 }
@@ -0,0 +1,114 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Rates are computed over a rolling window, from snapshots of the counters taken at most once per interval.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Received sizes of a channel.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChannelThroughput {
+    /// Messages with data for the channel.
+    pub messages: u64,
+    pub compressed_bytes: u64,
+    pub decompressed_bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Rates {
+    pub messages_per_second: f64,
+    /// Received bytes per second.
+    pub bytes_per_second: f64,
+    pub decompressed_bytes_per_second: f64,
+}
+
+impl Rates {
+    pub fn megabytes_per_second(&self) -> f64 {
+        self.bytes_per_second / 1_000_000.0
+    }
+
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.bytes_per_second > 0.0).then(|| self.decompressed_bytes_per_second / self.bytes_per_second)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    time: Instant,
+    messages: u64,
+    bytes: u64,
+    decompressed_bytes: u64,
+}
+
+/// Received messages and bytes of an endpoint.
+#[derive(Debug, Clone)]
+pub struct Throughput {
+    pub messages: u64,
+    pub frames: u64,
+    /// Sizes of all received frames, with channel values as transmitted.
+    pub compressed_bytes: u64,
+    /// Same as compressed_bytes, with the sizes of channel values after decompression.
+    pub decompressed_bytes: u64,
+    pub channels: HashMap<String, ChannelThroughput>,
+    samples: VecDeque<Sample>,
+}
+
+impl Throughput {
+    pub fn new() -> Self {
+        let start = Sample { time: Instant::now(), messages: 0, bytes: 0, decompressed_bytes: 0 };
+        Self { messages: 0, frames: 0, compressed_bytes: 0, decompressed_bytes: 0, channels: HashMap::new(), samples: VecDeque::from([start]) }
+    }
+
+    pub(crate) fn update(&mut self, frames: usize, compressed_bytes: usize, decompressed_bytes: usize) {
+        self.messages += 1;
+        self.frames += frames as u64;
+        self.compressed_bytes += compressed_bytes as u64;
+        self.decompressed_bytes += decompressed_bytes as u64;
+        let now = Instant::now();
+        if self.samples.back().is_none_or(|sample| now.duration_since(sample.time) >= SAMPLE_INTERVAL) {
+            self.samples.push_back(Sample { time: now, messages: self.messages, bytes: self.compressed_bytes, decompressed_bytes: self.decompressed_bytes });
+            //Keeps the newest sample older than the window, as reference
+            while self.samples.len() > 1 && now.duration_since(self.samples[1].time) >= RATE_WINDOW {
+                self.samples.pop_front();
+            }
+        }
+    }
+
+    pub(crate) fn update_channel(&mut self, name: &str, compressed_bytes: usize, decompressed_bytes: usize) {
+        //Only clone name if entry is absent
+        if !self.channels.contains_key(name) {
+            self.channels.insert(name.to_string(), ChannelThroughput::default());
+        }
+        let channel = self.channels.get_mut(name).unwrap();
+        channel.messages += 1;
+        channel.compressed_bytes += compressed_bytes as u64;
+        channel.decompressed_bytes += decompressed_bytes as u64;
+    }
+
+    /// Overall ratio of decompressed to received bytes.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        (self.compressed_bytes > 0).then(|| self.decompressed_bytes as f64 / self.compressed_bytes as f64)
+    }
+
+    /// Rates over the last seconds, decreasing if no messages are received.
+    pub fn rates(&self) -> Rates {
+        let Some(reference) = self.samples.front() else {
+            return Rates::default();
+        };
+        let elapsed = reference.time.elapsed().as_secs_f64();
+        if elapsed <= 0.0 {
+            return Rates::default();
+        }
+        Rates {
+            messages_per_second: (self.messages - reference.messages) as f64 / elapsed,
+            bytes_per_second: (self.compressed_bytes - reference.bytes) as f64 / elapsed,
+            decompressed_bytes_per_second: (self.decompressed_bytes - reference.decompressed_bytes) as f64 / elapsed,
+        }
+    }
+}
+
+impl Default for Throughput {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -84,6 +84,12 @@ where
         Vec::from(self.order.clone())
     }
 
+    /// Remove all elements of the map
+    pub fn clear(& mut self) {
+        self.map.clear();
+        self.order.clear();
+    }
+
     pub fn set_max_size(&mut self, max_size: usize) {
         self.max_size = max_size;
 
@@ -96,65 +102,144 @@ where
 }
 
 
-/// Thread-safe FIFO queue with a maximum size
+/// Behavior of a bounded queue when it is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// The producer waits for space
+    Wait,
+    /// The new item is dropped
+    DropNewest,
+    /// The oldest queued item is dropped
+    DropOldest,
+}
+
+struct FifoState<K> {
+    items: VecDeque<(K, usize)>,
+    bytes: usize,
+    closed: bool,
+}
+
+/// Thread-safe FIFO queue with a maximum size, and optionally a maximum total size in bytes
 pub struct FifoQueue<K> {
-    queue: Mutex<VecDeque<K>>,          // Thread-safe FIFO
-    dropped_count: Mutex<u32>,        // Counter for dropped items
+    queue: Mutex<FifoState<K>>,       // Thread-safe FIFO
+    dropped_count: Mutex<u64>,        // Counter for dropped items
     max_size: usize,                  // Maximum size of the FIFO
+    max_bytes: Option<usize>,         // Maximum total size of the items
+    policy: OverflowPolicy,
     available: Condvar,
+    space: Condvar,
 }
 
 impl<K> FifoQueue<K> {
-    /// Creates a new FifoQueue given its maximum size
+    /// Creates a new FifoQueue given its maximum size, dropping the oldest items when full
     pub  fn new(max_size: usize) -> Self {
+        Self::with_policy(max_size, OverflowPolicy::DropOldest, None)
+    }
+
+    /// Creates a new FifoQueue given its maximum size, overflow policy and optional byte budget
+    pub fn with_policy(max_size: usize, policy: OverflowPolicy, max_bytes: Option<usize>) -> Self {
         Self {
-            queue: Mutex::new(VecDeque::new()),
+            queue: Mutex::new(FifoState { items: VecDeque::new(), bytes: 0, closed: false }),
             dropped_count: Mutex::new(0),
-            max_size,
+            max_size: max_size.max(1),
+            max_bytes,
+            policy,
             available: Condvar::new(),
+            space: Condvar::new(),
         }
     }
 
-    /// Adds a message to the FIFO. Drops the oldest if the FIFO is full.
-    pub  fn add(&self, message: K) {
-        let mut queue = self.queue.lock().unwrap();
-        let mut dropped_count = self.dropped_count.lock().unwrap();
+    /// Adds a message to the FIFO. If the FIFO is full, applies the overflow policy.
+    pub  fn add(&self, message: K) -> bool {
+        self.add_sized(message, 0)
+    }
 
-        if queue.len() >= self.max_size {
-            queue.pop_front(); // Drop the oldest element
-            *dropped_count += 1; // Increment the dropped counter
+    /// Adds a message of a given size in bytes, accounted in the byte budget.
+    /// Returns false if the message was dropped.
+    pub fn add_sized(&self, message: K, size: usize) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        //A single message larger than the budget is accepted if the FIFO is empty
+        while queue.items.len() >= self.max_size
+            || (!queue.items.is_empty() && self.max_bytes.is_some_and(|max| queue.bytes + size > max)) {
+            match self.policy {
+                OverflowPolicy::Wait if !queue.closed => {
+                    queue = self.space.wait(queue).unwrap();
+                }
+                OverflowPolicy::Wait | OverflowPolicy::DropNewest => {
+                    *self.dropped_count.lock().unwrap() += 1;
+                    return false;
+                }
+                OverflowPolicy::DropOldest => {
+                    if let Some((_, dropped_size)) = queue.items.pop_front() {
+                        queue.bytes -= dropped_size;
+                    }
+                    *self.dropped_count.lock().unwrap() += 1;
+                }
+            }
         }
-        queue.push_back(message);
+        queue.bytes += size;
+        queue.items.push_back((message, size));
         self.available.notify_one();
+        true
+    }
+
+    fn pop(&self, queue: &mut FifoState<K>) -> Option<K> {
+        let (message, size) = queue.items.pop_front()?;
+        queue.bytes -= size;
+        self.space.notify_one();
+        Some(message)
     }
 
     /// Retrieves the next message from the FIFO, or `None` if empty.
     pub fn get(&self) -> Option<K> {
         let mut queue = self.queue.lock().unwrap();
-        queue.pop_front()
+        self.pop(&mut queue)
     }
 
     pub fn wait(&self, timeout_ms: u64) -> Option<K> {
         let timeout = Duration::from_millis(timeout_ms);
         let mut queue = self.queue.lock().unwrap();
-        if queue.is_empty() {
+        if queue.items.is_empty() {
             let (guard, result) = self.available.wait_timeout(queue, timeout).unwrap();
             queue = guard;
-            if result.timed_out() && queue.is_empty() {
+            if result.timed_out() && queue.items.is_empty() {
                 return None;
             }
         }
-        queue.pop_front()
+        self.pop(&mut queue)
+    }
+
+    /// Releases producers waiting for space: from now on, messages that don't fit are dropped.
+    pub fn close(&self) {
+        self.queue.lock().unwrap().closed = true;
+        self.space.notify_all();
     }
 
     /// Retrieves the total count of dropped messages.
-    pub  fn dropped_count(&self) -> u32 {
+    pub  fn dropped_count(&self) -> u64 {
         *self.dropped_count.lock().unwrap()
     }
 
     /// Retrieves the count of available messages.
     pub fn available_count(&self) -> usize {
-        self.queue.lock().unwrap().len()
+        self.queue.lock().unwrap().items.len()
+    }
+
+    /// Retrieves the total size of the available messages, in bytes.
+    pub fn available_bytes(&self) -> usize {
+        self.queue.lock().unwrap().bytes
+    }
+
+    pub fn max_size(&self) -> usize {
+        self.max_size
+    }
+
+    pub fn max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
     }
 }
 
@@ -2,6 +2,11 @@ use std::io::Cursor;
 use byteorder::BigEndian;
 use std::mem;
 use crate::convert::*;
+use crate::BsreadError;
+use num_traits::{Float, NumCast};
+use serde::de::Error as DeError;
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 
 #[derive(Debug, PartialEq, Clone)]
@@ -392,4 +397,192 @@ impl Value {
         }
     }
 
-}
\ No newline at end of file
+}
+macro_rules! impl_from {
+    ($ty:ty, $variant_num:ident, $variant_arr:ident) => {
+        impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Value::$variant_num(value)
+            }
+        }
+        impl From<Vec<$ty>> for Value {
+            fn from(value: Vec<$ty>) -> Self {
+                Value::$variant_arr(value)
+            }
+        }
+    };
+}
+
+impl_from!(String, STR, ASTR);
+impl_from!(bool, BOOL, ABOOL);
+impl_from!(i8, I8, AI8);
+impl_from!(u8, U8, AU8);
+impl_from!(i16, I16, AI16);
+impl_from!(u16, U16, AU16);
+impl_from!(i32, I32, AI32);
+impl_from!(u32, U32, AU32);
+impl_from!(i64, I64, AI64);
+impl_from!(u64, U64, AU64);
+impl_from!(f32, F32, AF32);
+impl_from!(f64, F64, AF64);
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::STR(value.to_string())
+    }
+}
+
+// Conversions used by Message::get, following the same numeric casting rules as the to_* methods
+macro_rules! impl_try_from {
+    ($ty:ty, $to_num:ident, $to_arr:ident) => {
+        impl TryFrom<&Value> for $ty {
+            type Error = BsreadError;
+            fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                value.$to_num().ok_or_else(|| BsreadError::conversion(stringify!($ty), value.name()))
+            }
+        }
+        impl TryFrom<&Value> for Vec<$ty> {
+            type Error = BsreadError;
+            fn try_from(value: &Value) -> Result<Self, Self::Error> {
+                value.$to_arr().ok_or_else(|| BsreadError::conversion(concat!("Vec<", stringify!($ty), ">"), value.name()))
+            }
+        }
+    };
+}
+
+impl_try_from!(bool, to_bool, to_abool);
+impl_try_from!(i8, to_i8, to_ai8);
+impl_try_from!(u8, to_u8, to_au8);
+impl_try_from!(i16, to_i16, to_ai16);
+impl_try_from!(u16, to_u16, to_au16);
+impl_try_from!(i32, to_i32, to_ai32);
+impl_try_from!(u32, to_u32, to_au32);
+impl_try_from!(i64, to_i64, to_ai64);
+impl_try_from!(u64, to_u64, to_au64);
+impl_try_from!(f32, to_f32, to_af32);
+impl_try_from!(f64, to_f64, to_af64);
+
+impl TryFrom<&Value> for String {
+    type Error = BsreadError;
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value.as_str().map(str::to_string).ok_or_else(|| BsreadError::conversion("String", value.name()))
+    }
+}
+
+impl TryFrom<&Value> for Vec<String> {
+    type Error = BsreadError;
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        value.as_astr().map(<[String]>::to_vec).ok_or_else(|| BsreadError::conversion("Vec<String>", value.name()))
+    }
+}
+
+// Serialized as {"type": <bsread type>, "value": <scalar or array>}, so that the exact variant is restored
+macro_rules! serialize_value {
+    ($value:expr, $state:ident, $($variant:ident),*) => {
+        match $value {
+            $(Value::$variant(data) => $state.serialize_field("value", data)?,)*
+        }
+    };
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Value", 2)?;
+        state.serialize_field("type", self.kind())?;
+        serialize_value!(self, state, STR, BOOL, I8, U8, I16, U16, I32, U32, I64, U64, F32, F64,
+            ASTR, ABOOL, AI8, AU8, AI16, AU16, AI32, AU32, AI64, AU64, AF32, AF64);
+        state.end()
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedItem {
+    Bool(bool),
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    //Non-finite floats are serialized as null in JSON
+    Null,
+}
+
+impl SerializedItem {
+    fn to_bool(&self) -> Option<bool> {
+        match self {
+            SerializedItem::Bool(data) => Some(*data),
+            _ => None,
+        }
+    }
+    fn to_string(&self) -> Option<String> {
+        match self {
+            SerializedItem::Str(data) => Some(data.clone()),
+            _ => None,
+        }
+    }
+    fn to_int<T: NumCast>(&self) -> Option<T> {
+        match self {
+            SerializedItem::UInt(data) => num_traits::cast(*data),
+            SerializedItem::Int(data) => num_traits::cast(*data),
+            _ => None,
+        }
+    }
+    fn to_float<T: Float>(&self) -> Option<T> {
+        match self {
+            SerializedItem::UInt(data) => num_traits::cast(*data),
+            SerializedItem::Int(data) => num_traits::cast(*data),
+            SerializedItem::Float(data) => num_traits::cast(*data),
+            SerializedItem::Null => Some(T::nan()),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SerializedData {
+    Scalar(SerializedItem),
+    Array(Vec<SerializedItem>),
+}
+
+#[derive(Deserialize)]
+struct SerializedValue {
+    #[serde(rename = "type")]
+    kind: String,
+    value: SerializedData,
+}
+
+impl SerializedValue {
+    fn to_value(&self) -> Option<Value> {
+        macro_rules! convert {
+            ($scalar:ident, $array:ident, $convert:ident) => {
+                match &self.value {
+                    SerializedData::Scalar(item) => item.$convert().map(Value::$scalar),
+                    SerializedData::Array(items) => items.iter().map(|item| item.$convert()).collect::<Option<Vec<_>>>().map(Value::$array),
+                }
+            };
+        }
+        match self.kind.as_str() {
+            "string" => convert!(STR, ASTR, to_string),
+            "bool" => convert!(BOOL, ABOOL, to_bool),
+            "int8" => convert!(I8, AI8, to_int),
+            "uint8" => convert!(U8, AU8, to_int),
+            "int16" => convert!(I16, AI16, to_int),
+            "uint16" => convert!(U16, AU16, to_int),
+            "int32" => convert!(I32, AI32, to_int),
+            "uint32" => convert!(U32, AU32, to_int),
+            "int64" => convert!(I64, AI64, to_int),
+            "uint64" => convert!(U64, AU64, to_int),
+            "float32" => convert!(F32, AF32, to_float),
+            "float64" => convert!(F64, AF64, to_float),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedValue::deserialize(deserializer)?;
+        serialized.to_value().ok_or_else(|| D::Error::custom(format!("invalid value for type {}", serialized.kind)))
+    }
+}
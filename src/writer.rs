@@ -81,8 +81,15 @@ pub const WRITER_ABF64: fn(&mut Cursor<&mut Vec<u8>>, &[f64]) -> IOResult<()> =
 pub const WRITER_ABOOL: fn(&mut Cursor<&mut Vec<u8>>, &[bool]) -> IOResult<()>= |cursor: &mut Cursor<&mut Vec<u8>>, arr: &[bool]| {
     for i in 0..arr.len() { WRITER_BOOL(cursor, &arr[i])?;} return Ok(());
 };
+//String arrays are written fixed-width, each element padded with nulls to the longest one (at least one byte):
+//unambiguous for READER_ASTRING
 pub const WRITER_ASTRING: fn(&mut Cursor<&mut  Vec<u8>>, &[String]) -> IOResult<()> = |cursor: &mut Cursor<&mut Vec<u8>>, arr: &[String]| {
-    for i in 0..1 { WRITER_STRING(cursor, &arr[i].clone())?;} return Ok(());
+    let width = arr.iter().map(String::len).max().unwrap_or(0).max(1);
+    for i in 0..arr.len() {
+        WRITER_STRING(cursor, &arr[i])?;
+        cursor.write_all(&vec![0u8; width - arr[i].len()])?;
+    }
+    return Ok(());
 };
 
 